@@ -0,0 +1,32 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use fft_image_extractor::{open_source_with, write_wav, DecodeOptions, SampleStream};
+
+fn decode_benchmark(c: &mut Criterion) {
+    // Ten minutes of 32-bit float samples at 48 kHz
+    let sample_rate = 48_000;
+    let samples: Vec<f32> = (0..sample_rate as usize * 600)
+        .map(|n| (n as f32 * 0.05).sin())
+        .collect();
+    let path = std::env::temp_dir().join("fft-image-extractor-bench.wav");
+    write_wav(&path, &samples, sample_rate).unwrap();
+    let path = path.to_str().unwrap();
+
+    let decode = |mmap_threshold| {
+        let source = open_source_with(path, mmap_threshold).unwrap();
+        let options = DecodeOptions::default().hint(Some("wav".to_string()));
+        SampleStream::open(source, &options)
+            .unwrap()
+            .read_all()
+            .unwrap()
+    };
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+    group.bench_function("mapped", |b| b.iter(|| decode(0)));
+    group.bench_function("buffered", |b| b.iter(|| decode(u64::MAX)));
+    group.finish();
+
+    std::fs::remove_file(path).unwrap();
+}
+
+criterion_group!(benches, decode_benchmark);
+criterion_main!(benches);
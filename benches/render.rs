@@ -0,0 +1,19 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use fft_image_extractor::{compute_spectrogram, render_image, SpectrogramConfig};
+
+fn render_benchmark(c: &mut Criterion) {
+    let config = SpectrogramConfig::default()
+        .hop(256)
+        .row_height(1024)
+        .build()
+        .unwrap();
+    let samples: Vec<f32> = (0..config.sample_rate as usize * 30)
+        .map(|n| (n as f32 * 0.05).sin() + 0.5 * (n as f32 * 0.31).sin())
+        .collect();
+    let data = compute_spectrogram(&samples, &config).unwrap();
+
+    c.bench_function("render_image", |b| b.iter(|| render_image(&data, &config)));
+}
+
+criterion_group!(benches, render_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,455 @@
+use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, Pixel, Rgba};
+
+use crate::config::SpectrogramConfig;
+use crate::font::{draw_text, text_width, GLYPH_HEIGHT};
+use crate::render::{Amplitude, ImageLayout, Orientation};
+
+/// Length of a tick mark in pixels
+const TICK_LENGTH: u32 = 4;
+/// Space between a label and its tick mark
+const LABEL_GAP: u32 = 2;
+
+/// Frequencies that get a tick: the lowest frequency drawn and every power of ten above it
+fn tick_frequencies(freq_min: f32, freq_max: f32) -> Vec<f32> {
+    let mut ticks = vec![freq_min];
+    let mut decade = 10f32.powf(freq_min.log10().floor() + 1.0);
+    while decade <= freq_max {
+        ticks.push(decade);
+        decade *= 10.0;
+    }
+    ticks
+}
+
+/// Short label of a tick frequency, e.g. `20`, `1k` or `2.5k`
+fn format_frequency(freq: f32) -> String {
+    let (value, suffix) = if freq >= 1000.0 {
+        (freq / 1000.0, "k")
+    } else {
+        (freq, "")
+    };
+    let value = format!("{value:.1}");
+    format!("{}{suffix}", value.trim_end_matches(".0"))
+}
+
+/// Spacing between time ticks in seconds, the smallest that leaves room for the labels is used
+const TIME_STEPS: [f32; 15] = [
+    0.05, 0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0,
+];
+
+/// Label of a time tick, e.g. `0.5s`, `12s` or `1:05`
+fn format_time(seconds: f32) -> String {
+    if seconds >= 60.0 {
+        let seconds = seconds.round() as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        let value = format!("{seconds:.2}");
+        format!("{}s", value.trim_end_matches('0').trim_end_matches('.'))
+    }
+}
+
+/// Colors of the labels and of the margins around the image
+pub(crate) fn annotation_colors(config: &SpectrogramConfig) -> (Rgba<u8>, Rgba<u8>) {
+    if config.invert {
+        (Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255]))
+    } else {
+        (Rgba([255, 255, 255, 255]), Rgba([0, 0, 0, 255]))
+    }
+}
+
+/// Frequency ticks as the image row they sit on within a band, with their label
+fn frequency_ticks(config: &SpectrogramConfig) -> Vec<(u32, String)> {
+    let h = config.row_height;
+    tick_frequencies(config.min_freq, config.max_freq)
+        .into_iter()
+        .filter_map(|freq| {
+            let row = config
+                .scale
+                .row_for(freq, config.min_freq, config.max_freq, h)?;
+            Some((row.min(h - 1), format_frequency(freq)))
+        })
+        .collect()
+}
+
+/// Width of the widest label of a time tick over `frame_count` frames
+fn time_label_width(config: &SpectrogramConfig, frame_count: usize) -> u32 {
+    let duration =
+        config.time_offset + frame_count as f32 * config.hop as f32 / config.sample_rate as f32;
+    text_width(&format_time(duration))
+}
+
+/// Space taken by the frequency labels across the bands, and by a time ruler along a band
+pub(crate) fn axes_margins(config: &SpectrogramConfig, frame_count: usize) -> (u32, u32) {
+    if config.orientation == Orientation::Horizontal {
+        let freq_label_width = frequency_ticks(config)
+            .iter()
+            .map(|(_, label)| text_width(label))
+            .max()
+            .unwrap_or(0);
+        (
+            freq_label_width + LABEL_GAP + TICK_LENGTH,
+            TICK_LENGTH + LABEL_GAP + GLYPH_HEIGHT,
+        )
+    } else {
+        (
+            GLYPH_HEIGHT + LABEL_GAP + TICK_LENGTH,
+            TICK_LENGTH + LABEL_GAP + time_label_width(config, frame_count),
+        )
+    }
+}
+
+/// Adds a labeled frequency axis and a time ruler to `img`, which holds `frame_count` frames
+///
+/// Frequency ticks sit at the lowest frequency and every decade above it, placed with the same
+/// [`FrequencyScale::row_for`](crate::FrequencyScale::row_for) mapping as the bins. They go in a
+/// margin on the left of horizontal images and along the top of vertical ones. Every row band of
+/// a wrapped image gets its own time ruler, below the band (or to its right when vertical),
+/// starting at the time of the band's first frame, counted from [`SpectrogramConfig::time_offset`].
+pub(crate) fn add_axes(
+    img: DynamicImage,
+    config: &SpectrogramConfig,
+    frame_count: usize,
+) -> DynamicImage {
+    let h = config.row_height;
+    let (foreground, background) = annotation_colors(config);
+
+    let freq_ticks = frequency_ticks(config);
+    let freq_label_width = freq_ticks
+        .iter()
+        .map(|(_, label)| text_width(label))
+        .max()
+        .unwrap_or(0);
+
+    let (width, height) = img.dimensions();
+    let horizontal = config.orientation == Orientation::Horizontal;
+    // Number of time columns per band and number of bands
+    let (band_width, band_count) = if horizontal {
+        (width, height / h)
+    } else {
+        (height, width / h)
+    };
+
+    let seconds_per_column = config.hop as f32 / config.sample_rate as f32;
+    let time_label_width = time_label_width(config, frame_count);
+    let step = TIME_STEPS
+        .into_iter()
+        .find(|step| step / seconds_per_column >= (time_label_width + 4) as f32)
+        .unwrap_or(TIME_STEPS[TIME_STEPS.len() - 1]);
+
+    let (freq_margin, ruler) = axes_margins(config, frame_count);
+    let band_pitch = h + ruler;
+    let (out_width, out_height) = if horizontal {
+        (freq_margin + band_width, band_count * band_pitch)
+    } else {
+        (band_count * band_pitch, freq_margin + band_width)
+    };
+
+    let mut out = DynamicImage::new(out_width, out_height, img.color());
+    for x in 0..out_width {
+        for y in 0..out_height {
+            out.put_pixel(x, y, background);
+        }
+    }
+
+    // End of the last frequency label drawn, later labels that would overlap it are skipped, even
+    // across the boundary between two bands
+    let mut label_end = None;
+
+    for band in 0..band_count {
+        let band_start = band * band_pitch;
+        let (band_img, x, y) = if horizontal {
+            (img.crop_imm(0, band * h, width, h), freq_margin, band_start)
+        } else {
+            (
+                img.crop_imm(band * h, 0, h, height),
+                band_start,
+                freq_margin,
+            )
+        };
+        out.copy_from(&band_img, x, y)
+            .expect("the margins leave room for every band");
+
+        for (row, label) in &freq_ticks {
+            let position = band_start + row;
+            if horizontal {
+                for x in freq_margin - TICK_LENGTH..freq_margin {
+                    out.put_pixel(x, position, foreground);
+                }
+                let top = position
+                    .saturating_sub(GLYPH_HEIGHT / 2)
+                    .clamp(band_start, band_start + h.saturating_sub(GLYPH_HEIGHT));
+                if label_end.is_some_and(|end| top < end) {
+                    continue;
+                }
+                let left = freq_label_width - text_width(label);
+                draw_text(&mut out, left, top, label, foreground);
+                label_end = Some(top + GLYPH_HEIGHT + 1);
+            } else {
+                for y in freq_margin - TICK_LENGTH..freq_margin {
+                    out.put_pixel(position, y, foreground);
+                }
+                let text_w = text_width(label);
+                let left = position
+                    .saturating_sub(text_w / 2)
+                    .clamp(band_start, band_start + h.saturating_sub(text_w));
+                if label_end.is_some_and(|end| left < end) {
+                    continue;
+                }
+                draw_text(&mut out, left, 0, label, foreground);
+                label_end = Some(left + text_w + 2);
+            }
+        }
+
+        // Time ruler of this band, covering only the frames it holds
+        let first_column = band as usize * band_width as usize;
+        let columns = frame_count
+            .saturating_sub(first_column)
+            .min(band_width as usize);
+        let band_time = config.time_offset + first_column as f32 * seconds_per_column;
+        let mut tick = (band_time / step).ceil() as u32;
+        loop {
+            let time = tick as f32 * step;
+            let column = ((time - band_time) / seconds_per_column).round() as u32;
+            if column as usize >= columns {
+                break;
+            }
+            let label = format_time(time);
+            let ruler_start = band_start + h;
+            if horizontal {
+                let x = freq_margin + column;
+                for y in ruler_start..ruler_start + TICK_LENGTH {
+                    out.put_pixel(x, y, foreground);
+                }
+                let left = x.min(out_width.saturating_sub(text_width(&label)));
+                let top = ruler_start + TICK_LENGTH + LABEL_GAP;
+                draw_text(&mut out, left, top, &label, foreground);
+            } else {
+                let y = freq_margin + column;
+                for x in ruler_start..ruler_start + TICK_LENGTH {
+                    out.put_pixel(x, y, foreground);
+                }
+                let top = y.min(out_height.saturating_sub(GLYPH_HEIGHT));
+                let left = ruler_start + TICK_LENGTH + LABEL_GAP;
+                draw_text(&mut out, left, top, &label, foreground);
+            }
+            tick += 1;
+        }
+    }
+
+    out
+}
+
+/// Smallest spacing in pixels between two vertical grid lines
+const GRID_SPACING: f32 = 32.0;
+
+/// Octaves of 1 kHz within `freq_min..=freq_max`
+fn octave_frequencies(freq_min: f32, freq_max: f32) -> Vec<f32> {
+    let mut octaves = Vec::new();
+    let mut freq = 1000.0 * (freq_min / 1000.0).log2().ceil().exp2();
+    while freq <= freq_max {
+        octaves.push(freq);
+        freq *= 2.0;
+    }
+    octaves
+}
+
+/// Blends a grid over the bare spectrogram `img` of `frame_count` frames, before any margins are
+/// added
+///
+/// Horizontal lines mark the octaves of 1 kHz, placed with the same
+/// [`FrequencyScale::row_for`](crate::FrequencyScale::row_for) mapping as the bins, and vertical
+/// lines regular time intervals counted from [`SpectrogramConfig::time_offset`]. Gray images are drawn with the
+/// luminance of the grid color.
+pub(crate) fn add_grid(img: &mut DynamicImage, config: &SpectrogramConfig, frame_count: usize) {
+    let opacity = config.grid_opacity;
+    let mix = |value: f32, grid: f32| value * (1.0 - opacity) + grid * opacity;
+    let [r, g, b] = config.grid_color;
+    let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+
+    match img {
+        DynamicImage::ImageRgba8(buf) => blend_grid(buf, config, frame_count, |pixel| {
+            for (channel, grid) in pixel.0.iter_mut().zip(config.grid_color) {
+                *channel = mix(*channel as f32, grid as f32).round() as u8;
+            }
+        }),
+        DynamicImage::ImageLuma8(buf) => blend_grid(buf, config, frame_count, |pixel| {
+            pixel.0[0] = mix(pixel.0[0] as f32, luma).round() as u8;
+        }),
+        DynamicImage::ImageLuma16(buf) => blend_grid(buf, config, frame_count, |pixel| {
+            pixel.0[0] = mix(pixel.0[0] as f32, luma * 257.0).round() as u16;
+        }),
+        _ => {}
+    }
+}
+
+/// Applies `blend` to every pixel under a grid line, once where lines cross
+fn blend_grid<P: Pixel>(
+    buf: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+    config: &SpectrogramConfig,
+    frame_count: usize,
+    blend: impl Fn(&mut P),
+) {
+    let h = config.row_height;
+    let layout = ImageLayout::new(frame_count, config);
+    let rows: Vec<u32> = octave_frequencies(config.min_freq, config.max_freq)
+        .into_iter()
+        .filter_map(|freq| {
+            let row = config
+                .scale
+                .row_for(freq, config.min_freq, config.max_freq, h)?;
+            Some(row.min(h - 1))
+        })
+        .collect();
+
+    let seconds_per_column = config.hop as f32 / config.sample_rate as f32;
+    let step = TIME_STEPS
+        .into_iter()
+        .find(|step| step / seconds_per_column >= GRID_SPACING)
+        .unwrap_or(TIME_STEPS[TIME_STEPS.len() - 1]);
+
+    for band in 0..layout.row_count {
+        let first_column = band as usize * layout.w;
+        let columns = frame_count.saturating_sub(first_column).min(layout.w);
+        let mut blend_at = |column: usize, row: u32| {
+            let (x, y) = match config.orientation {
+                Orientation::Horizontal => (column as u32, band * h + row),
+                Orientation::Vertical => (band * h + row, column as u32),
+            };
+            blend(buf.get_pixel_mut(x, y));
+        };
+
+        for &row in &rows {
+            for column in 0..columns {
+                blend_at(column, row);
+            }
+        }
+
+        let band_time = config.time_offset + first_column as f32 * seconds_per_column;
+        let mut tick = (band_time / step).ceil() as u32;
+        loop {
+            let time = tick as f32 * step;
+            let column = ((time - band_time) / seconds_per_column).round() as usize;
+            if column >= columns {
+                break;
+            }
+            for row in (0..h).filter(|row| !rows.contains(row)) {
+                blend_at(column, row);
+            }
+            tick += 1;
+        }
+    }
+}
+
+/// Color of the pitch track, set apart from the centroid drawn in the color of the labels
+pub(crate) const PITCH_COLOR: Rgba<u8> = Rgba([255, 64, 64, 255]);
+
+/// Draws a line through `contour`, one frequency per frame or `None` to leave it out, over the
+/// bare spectrogram `img`, before any margins are added
+///
+/// Each frequency is placed with the same
+/// [`FrequencyScale::row_for`](crate::FrequencyScale::row_for) mapping as the bins, and
+/// consecutive frames of a band are joined by a vertical run so the line stays unbroken across
+/// jumps. Missing frames and frequencies outside the range leave a gap. Gray images are drawn with
+/// the luminance of `color`.
+pub(crate) fn add_contour(
+    img: &mut DynamicImage,
+    contour: &[Option<f32>],
+    color: Rgba<u8>,
+    config: &SpectrogramConfig,
+) {
+    let h = config.row_height;
+    let layout = ImageLayout::new(contour.len(), config);
+
+    let mut prev = None;
+    for (frame, freq) in contour.iter().enumerate() {
+        let band = (frame / layout.w) as u32;
+        let column = (frame % layout.w) as u32;
+        let row = freq
+            .and_then(|freq| {
+                config
+                    .scale
+                    .row_for(freq, config.min_freq, config.max_freq, h)
+            })
+            .map(|row| row.min(h - 1));
+        let Some(row) = row else {
+            prev = None;
+            continue;
+        };
+
+        // Rows from the previous frame's point, when it sits in the same band
+        let (start, end) = match prev {
+            Some((prev_band, prev_row)) if prev_band == band && column > 0 => {
+                (row.min(prev_row), row.max(prev_row))
+            }
+            _ => (row, row),
+        };
+        for row in start..=end {
+            let (x, y) = match config.orientation {
+                Orientation::Horizontal => (column, band * h + row),
+                Orientation::Vertical => (band * h + row, column),
+            };
+            img.put_pixel(x, y, color);
+        }
+        prev = Some((band, row));
+    }
+}
+
+/// Width of the colorbar in pixels
+const COLORBAR_WIDTH: u32 = 10;
+
+/// Labels at the top and bottom of the colorbar
+fn colorbar_labels(config: &SpectrogramConfig) -> (String, String) {
+    match config.amplitude {
+        Amplitude::Linear => ("1".to_string(), "0".to_string()),
+        Amplitude::Db => ("0dB".to_string(), format!("{}dB", config.db_floor)),
+    }
+}
+
+/// Width added to the right of the image by [`add_colorbar`]
+pub(crate) fn colorbar_margin(config: &SpectrogramConfig) -> u32 {
+    let (top_label, bottom_label) = colorbar_labels(config);
+    let label_width = text_width(&top_label).max(text_width(&bottom_label));
+    LABEL_GAP + COLORBAR_WIDTH + LABEL_GAP + label_width + LABEL_GAP
+}
+
+/// Adds a colorbar down the right edge of `img`, the loudest level at the top, labeled with the
+/// ends of the amplitude range
+///
+/// The bar goes through the same intensity-to-color mapping as the spectrogram.
+pub(crate) fn add_colorbar(img: DynamicImage, config: &SpectrogramConfig) -> DynamicImage {
+    let (foreground, background) = annotation_colors(config);
+    let (top_label, bottom_label) = colorbar_labels(config);
+
+    let (width, height) = img.dimensions();
+    let bar_x = width + LABEL_GAP;
+    let label_x = bar_x + COLORBAR_WIDTH + LABEL_GAP;
+    let out_width = width + colorbar_margin(config);
+
+    let mut out = DynamicImage::new(out_width, height, img.color());
+    for x in width..out_width {
+        for y in 0..height {
+            out.put_pixel(x, y, background);
+        }
+    }
+    out.copy_from(&img, 0, 0)
+        .expect("the margin leaves room for the image");
+
+    for y in 0..height {
+        let level = 1.0 - y as f32 / (height - 1).max(1) as f32;
+        let intensity = if config.invert { 1.0 - level } else { level };
+        let color = config.colormap.map_color(intensity);
+        for x in bar_x..bar_x + COLORBAR_WIDTH {
+            out.put_pixel(x, y, color);
+        }
+    }
+
+    draw_text(&mut out, label_x, 0, &top_label, foreground);
+    draw_text(
+        &mut out,
+        label_x,
+        height.saturating_sub(GLYPH_HEIGHT),
+        &bottom_label,
+        foreground,
+    );
+
+    out
+}
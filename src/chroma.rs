@@ -0,0 +1,57 @@
+use image::DynamicImage;
+use rayon::prelude::*;
+
+use crate::config::SpectrogramConfig;
+use crate::render::render_stripes;
+use crate::spectrogram::SpectrogramData;
+
+/// Number of pitch classes in an octave, from C to B
+pub const PITCH_CLASSES: usize = 12;
+
+/// Pitch class of `freq` (Hz), 0 being C and 11 being B, in equal temperament tuned to A4 = 440 Hz
+pub fn pitch_class(freq: f32) -> usize {
+    let semitones_from_c = 12.0 * (freq / 440.0).log2() + 9.0;
+    (semitones_from_c.round() as i64).rem_euclid(PITCH_CLASSES as i64) as usize
+}
+
+/// Folds every frame of `data` into the summed magnitudes of its twelve pitch classes
+///
+/// Only the bins between [`SpectrogramConfig::min_freq`] and [`SpectrogramConfig::max_freq`] are
+/// counted, the DC bin never is. Each frame is divided by its loudest pitch class, so every column
+/// spans the full intensity range.
+pub fn chromagram(data: &SpectrogramData, config: &SpectrogramConfig) -> Vec<[f32; PITCH_CLASSES]> {
+    let classes: Vec<Option<usize>> = data
+        .frequencies
+        .iter()
+        .map(|&freq| {
+            (freq > 0.0 && freq >= config.min_freq && freq <= config.max_freq)
+                .then(|| pitch_class(freq))
+        })
+        .collect();
+
+    data.frames
+        .par_iter()
+        .map(|frame| {
+            let mut chroma = [0.0; PITCH_CLASSES];
+            for (class, &magnitude) in classes.iter().zip(frame) {
+                if let Some(class) = class {
+                    chroma[*class] += magnitude;
+                }
+            }
+            let max = chroma.iter().fold(0.0f32, |max, &val| max.max(val));
+            if max != 0.0 {
+                for val in &mut chroma {
+                    *val /= max;
+                }
+            }
+            chroma
+        })
+        .collect()
+}
+
+/// Draws `chroma` in the layout, orientation and pixel format of
+/// [`render_image`](crate::render_image), each row band split into twelve stripes from C at the
+/// bottom to B at the top
+pub fn render_chroma(chroma: &[[f32; PITCH_CLASSES]], config: &SpectrogramConfig) -> DynamicImage {
+    render_stripes(chroma, config)
+}
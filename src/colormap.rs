@@ -0,0 +1,88 @@
+use clap::ValueEnum;
+use image::Rgba;
+use serde::Deserialize;
+
+/// Palette used to turn a normalized amplitude into a pixel color
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Colormap {
+    Gray,
+    Viridis,
+    Magma,
+    Inferno,
+    /// Diverging from blue through light gray to red, for values on either side of the middle
+    Coolwarm,
+}
+
+const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x28, 0xae, 0x80],
+    [0x5e, 0xc9, 0x62],
+    [0xad, 0xdc, 0x30],
+    [0xfd, 0xe7, 0x25],
+];
+
+const MAGMA: [[u8; 3]; 9] = [
+    [0x00, 0x00, 0x04],
+    [0x1c, 0x10, 0x44],
+    [0x4f, 0x12, 0x7b],
+    [0x81, 0x25, 0x81],
+    [0xb5, 0x36, 0x7a],
+    [0xe5, 0x50, 0x64],
+    [0xfb, 0x87, 0x61],
+    [0xfe, 0xc2, 0x87],
+    [0xfc, 0xfd, 0xbf],
+];
+
+const COOLWARM: [[u8; 3]; 9] = [
+    [0x3b, 0x4c, 0xc0],
+    [0x62, 0x82, 0xea],
+    [0x8d, 0xb0, 0xfe],
+    [0xb8, 0xd0, 0xf9],
+    [0xdd, 0xdd, 0xdd],
+    [0xf5, 0xc4, 0xad],
+    [0xf4, 0x9a, 0x7b],
+    [0xde, 0x60, 0x4d],
+    [0xb4, 0x04, 0x26],
+];
+
+const INFERNO: [[u8; 3]; 9] = [
+    [0x00, 0x00, 0x04],
+    [0x1f, 0x0c, 0x48],
+    [0x55, 0x0f, 0x6d],
+    [0x88, 0x22, 0x6a],
+    [0xba, 0x36, 0x55],
+    [0xe3, 0x59, 0x33],
+    [0xf9, 0x8e, 0x09],
+    [0xf9, 0xcb, 0x35],
+    [0xfc, 0xff, 0xa4],
+];
+
+impl Colormap {
+    /// Maps an intensity in `0.0..=1.0` to a color, interpolating between palette anchors
+    pub fn map_color(self, intensity: f32) -> Rgba<u8> {
+        let intensity = intensity.clamp(0.0, 1.0);
+        let anchors = match self {
+            Colormap::Gray => {
+                let val = (intensity * 255.0) as u8;
+                return Rgba([val, val, val, 255]);
+            }
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Coolwarm => &COOLWARM,
+        };
+
+        let position = intensity * (anchors.len() - 1) as f32;
+        let lower = (position.floor() as usize).min(anchors.len() - 2);
+        let t = position - lower as f32;
+        let (a, b) = (anchors[lower], anchors[lower + 1]);
+        let lerp = |i: usize| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * t).round() as u8;
+
+        Rgba([lerp(0), lerp(1), lerp(2), 255])
+    }
+}
@@ -0,0 +1,696 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::colormap::Colormap;
+use crate::denoise::Denoise;
+use crate::error::FftImageError;
+use crate::output::PngCompression;
+use crate::render::{Amplitude, BitDepth, Layout, Mode, Orientation, PixelFormat};
+use crate::scale::FrequencyScale;
+use crate::spectrogram::{Normalize, Transform};
+use crate::weighting::Weighting;
+use crate::window::WindowFn;
+
+/// Parameters controlling both the analysis and the rendering of a spectrogram
+///
+/// Start from [`SpectrogramConfig::default`], adjust fields with the builder methods and finish
+/// with [`SpectrogramConfig::build`] to check that the combination is valid:
+///
+/// ```
+/// use fft_image_extractor::SpectrogramConfig;
+///
+/// let config = SpectrogramConfig::default()
+///     .window(4096)
+///     .max_freq(12_000.0)
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.window, 4096);
+/// ```
+///
+/// It can also be read from a TOML file with [`SpectrogramConfig::from_toml_file`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpectrogramConfig {
+    /// Sample rate of the input (Hz)
+    #[serde(skip)]
+    pub sample_rate: u32,
+    /// Number of samples per FFT window, a power of two
+    pub window: usize,
+    /// Length of the FFT, a power of two of at least `window`
+    ///
+    /// Each window is zero-padded to this length, which narrows the bin spacing without improving
+    /// the true frequency resolution. `None` uses the window size.
+    pub fft_size: Option<usize>,
+    /// Number of samples between the starts of consecutive windows
+    pub hop: usize,
+    /// Window function applied to each block of samples, or to the kernels of the constant-Q
+    /// transform
+    pub window_fn: WindowFn,
+    /// Transform computing the frequency bins of each window
+    pub transform: Transform,
+    /// Number of bins to the octave with the constant-Q transform
+    pub bins_per_octave: usize,
+    /// Lowest frequency drawn in the image (Hz)
+    pub min_freq: f32,
+    /// Highest frequency analyzed and drawn (Hz)
+    pub max_freq: f32,
+    /// What each time column of the image shows
+    pub mode: Mode,
+    /// Height in pixels of a single row of the image
+    pub row_height: u32,
+    /// Height in pixels of the waveform panel above each row in combined mode
+    pub waveform_height: u32,
+    /// Number of triangular mel filters the spectrum is summed into in MFCC mode
+    pub mel_filters: usize,
+    /// Number of cepstral coefficients kept per frame in MFCC mode, at most `mel_filters`
+    pub mfcc_coeffs: usize,
+    /// Arrangement of the time columns in the image
+    pub layout: Layout,
+    /// Number of time columns per row in the wrapped layout, all frames fit in one row when this
+    /// exceeds the frame count
+    pub wrap_width: Option<usize>,
+    /// Direction of the time axis
+    pub orientation: Orientation,
+    /// Draw frequency and time ticks with labels in margins of the image
+    pub axes: bool,
+    /// Blend lines at octaves of 1 kHz and regular time intervals over the spectrogram
+    pub grid: bool,
+    /// Color of the grid lines
+    pub grid_color: [u8; 3],
+    /// Opacity of the grid lines, from 0 (invisible) to 1 (opaque)
+    pub grid_opacity: f32,
+    /// Draw the spectral centroid of every frame as a line over the spectrogram
+    pub centroid: bool,
+    /// Draw the dominant pitch of every frame as a red line over the spectrogram
+    pub pitch_track: bool,
+    /// Draw a colorbar legend of the amplitude range down the right edge of the image
+    pub colorbar: bool,
+    /// Time of the first frame in seconds, from which the time ruler and grid lines count
+    ///
+    /// Lets an image of a stretch cut out of a longer input be labeled with its times in the input.
+    #[serde(skip)]
+    pub time_offset: f32,
+    /// Mapping of frequency onto the vertical axis
+    pub scale: FrequencyScale,
+    /// Ramp the intensity linearly across the rows between two bins instead of repeating the lower
+    /// bin, smoothing the steps where the bins are sparse on the frequency axis
+    pub interpolate: bool,
+    /// Frequency weighting the magnitudes are multiplied by before they are normalized
+    pub weighting: Weighting,
+    /// How magnitudes are normalized before drawing
+    pub normalize: Normalize,
+    /// Number of neighboring frames each bin's magnitude is averaged over, centered on its own
+    /// frame, to even out flicker; 1 leaves the frames as they are
+    pub smooth: usize,
+    /// Filter cleaning up the magnitudes before they are smoothed and drawn
+    pub denoise: Denoise,
+    /// Frames by bins each magnitude is filtered over with [`Denoise::Median`], both odd
+    pub kernel: [usize; 2],
+    /// Low and high percentiles of all the magnitudes that are stretched onto the darkest and
+    /// brightest intensities, clipping the magnitudes outside; `None` leaves the range as normalized
+    ///
+    /// With decibel amplitude the stretch is even in decibels, below which the percentiles never
+    /// reach further than `db_floor` under the high one.
+    pub contrast: Option<[f32; 2]>,
+    /// Number of time columns the frames are averaged or interpolated into, whatever the length of
+    /// the input; `None` keeps one column per frame
+    ///
+    /// The resampled frames are no longer a hop apart, so the time ruler, grid and waveforms, which
+    /// place frames by their hop, cannot be drawn along with them.
+    pub time_bins: Option<usize>,
+    /// Mapping of magnitude onto pixel intensity
+    pub amplitude: Amplitude,
+    /// Lowest level drawn in decibel mode
+    pub db_floor: f32,
+    /// Normalized magnitude (or level in dB, in decibel mode) below which pixels are drawn black
+    pub threshold: Option<f32>,
+    /// Factor the normalized magnitudes are multiplied by before drawing, saturating at full
+    /// intensity
+    pub gain: f32,
+    /// Gamma applied to pixel intensities, values above 1 brighten the midtones
+    pub gamma: f32,
+    /// Palette used to color pixels
+    pub colormap: Colormap,
+    /// Flip the intensity ramp, so that silence is drawn with the color of the loudest bin
+    pub invert: bool,
+    /// Channel layout of the output image
+    pub format: PixelFormat,
+    /// Bits per channel of the output image
+    pub bit_depth: BitDepth,
+    /// How hard PNG output is compressed, smaller files taking longer to write
+    pub png_compression: PngCompression,
+    /// Color of the pixels below `threshold` and of the image area no frame is drawn into, as RGBA
+    ///
+    /// Grayscale images use its luminance. `None` draws them in the color of silence, or of the
+    /// last bin of the frame above for the area left unfilled.
+    pub background: Option<[u8; 4]>,
+    /// Number of Griffin-Lim iterations used when reconstructing audio
+    pub gl_iters: usize,
+}
+
+impl Default for SpectrogramConfig {
+    fn default() -> Self {
+        SpectrogramConfig {
+            sample_rate: 44_100,
+            window: 2048,
+            fft_size: None,
+            hop: 2048,
+            window_fn: WindowFn::Rect,
+            transform: Transform::Fft,
+            bins_per_octave: 24,
+            min_freq: 20.0,
+            max_freq: 10_000.0,
+            mode: Mode::Spectrogram,
+            row_height: 128,
+            waveform_height: 64,
+            mel_filters: 40,
+            mfcc_coeffs: 13,
+            layout: Layout::Wrapped,
+            wrap_width: None,
+            orientation: Orientation::Horizontal,
+            axes: false,
+            grid: false,
+            grid_color: [255, 255, 255],
+            grid_opacity: 0.3,
+            centroid: false,
+            pitch_track: false,
+            colorbar: false,
+            time_offset: 0.0,
+            scale: FrequencyScale::Log,
+            interpolate: false,
+            weighting: Weighting::Z,
+            normalize: Normalize::Column,
+            smooth: 1,
+            time_bins: None,
+            denoise: Denoise::None,
+            kernel: [3, 3],
+            contrast: None,
+            amplitude: Amplitude::Linear,
+            db_floor: -80.0,
+            threshold: None,
+            gain: 1.0,
+            gamma: 1.0,
+            colormap: Colormap::Gray,
+            invert: false,
+            format: PixelFormat::Rgba,
+            bit_depth: BitDepth::Eight,
+            png_compression: PngCompression::Default,
+            background: None,
+            gl_iters: 32,
+        }
+    }
+}
+
+impl SpectrogramConfig {
+    /// Reads the fields set in the TOML file at `path`, e.g. `window = 4096` or
+    /// `colormap = "magma"`, keeping the defaults of the others
+    ///
+    /// `hop` defaults to the window size. The sample rate comes from the input and cannot be set.
+    /// The result still has to be checked with [`SpectrogramConfig::build`].
+    pub fn from_toml_file(path: &Path) -> Result<Self, FftImageError> {
+        let text =
+            fs::read_to_string(path).map_err(|err| FftImageError::Open(path.to_path_buf(), err))?;
+        let parse_error = |err| FftImageError::ConfigFile(path.to_path_buf(), err);
+        let table: toml::Table = toml::from_str(&text).map_err(parse_error)?;
+        let has_hop = table.contains_key("hop");
+        let mut config: SpectrogramConfig =
+            toml::Value::Table(table).try_into().map_err(parse_error)?;
+        if !has_hop {
+            config.hop = config.window;
+        }
+        Ok(config)
+    }
+
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn fft_size(mut self, fft_size: Option<usize>) -> Self {
+        self.fft_size = fft_size;
+        self
+    }
+
+    pub fn hop(mut self, hop: usize) -> Self {
+        self.hop = hop;
+        self
+    }
+
+    pub fn window_fn(mut self, window_fn: WindowFn) -> Self {
+        self.window_fn = window_fn;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn bins_per_octave(mut self, bins_per_octave: usize) -> Self {
+        self.bins_per_octave = bins_per_octave;
+        self
+    }
+
+    pub fn min_freq(mut self, min_freq: f32) -> Self {
+        self.min_freq = min_freq;
+        self
+    }
+
+    pub fn max_freq(mut self, max_freq: f32) -> Self {
+        self.max_freq = max_freq;
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn row_height(mut self, row_height: u32) -> Self {
+        self.row_height = row_height;
+        self
+    }
+
+    pub fn waveform_height(mut self, waveform_height: u32) -> Self {
+        self.waveform_height = waveform_height;
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn wrap_width(mut self, wrap_width: Option<usize>) -> Self {
+        self.wrap_width = wrap_width;
+        self
+    }
+
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn axes(mut self, axes: bool) -> Self {
+        self.axes = axes;
+        self
+    }
+
+    pub fn mel_filters(mut self, mel_filters: usize) -> Self {
+        self.mel_filters = mel_filters;
+        self
+    }
+
+    pub fn mfcc_coeffs(mut self, mfcc_coeffs: usize) -> Self {
+        self.mfcc_coeffs = mfcc_coeffs;
+        self
+    }
+
+    pub fn grid(mut self, grid: bool) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    pub fn grid_color(mut self, grid_color: [u8; 3]) -> Self {
+        self.grid_color = grid_color;
+        self
+    }
+
+    pub fn grid_opacity(mut self, grid_opacity: f32) -> Self {
+        self.grid_opacity = grid_opacity;
+        self
+    }
+
+    pub fn centroid(mut self, centroid: bool) -> Self {
+        self.centroid = centroid;
+        self
+    }
+
+    pub fn pitch_track(mut self, pitch_track: bool) -> Self {
+        self.pitch_track = pitch_track;
+        self
+    }
+
+    pub fn colorbar(mut self, colorbar: bool) -> Self {
+        self.colorbar = colorbar;
+        self
+    }
+
+    pub fn time_offset(mut self, time_offset: f32) -> Self {
+        self.time_offset = time_offset;
+        self
+    }
+
+    pub fn scale(mut self, scale: FrequencyScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn interpolate(mut self, interpolate: bool) -> Self {
+        self.interpolate = interpolate;
+        self
+    }
+
+    pub fn weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    pub fn normalize(mut self, normalize: Normalize) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    pub fn smooth(mut self, smooth: usize) -> Self {
+        self.smooth = smooth;
+        self
+    }
+
+    pub fn time_bins(mut self, time_bins: Option<usize>) -> Self {
+        self.time_bins = time_bins;
+        self
+    }
+
+    pub fn denoise(mut self, denoise: Denoise) -> Self {
+        self.denoise = denoise;
+        self
+    }
+
+    pub fn kernel(mut self, kernel: [usize; 2]) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
+    pub fn contrast(mut self, contrast: Option<[f32; 2]>) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    pub fn amplitude(mut self, amplitude: Amplitude) -> Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+    pub fn db_floor(mut self, db_floor: f32) -> Self {
+        self.db_floor = db_floor;
+        self
+    }
+
+    pub fn threshold(mut self, threshold: Option<f32>) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+
+    pub fn colormap(mut self, colormap: Colormap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    pub fn format(mut self, format: PixelFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn bit_depth(mut self, bit_depth: BitDepth) -> Self {
+        self.bit_depth = bit_depth;
+        self
+    }
+
+    pub fn png_compression(mut self, png_compression: PngCompression) -> Self {
+        self.png_compression = png_compression;
+        self
+    }
+
+    pub fn background(mut self, background: Option<[u8; 4]>) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn gl_iters(mut self, gl_iters: usize) -> Self {
+        self.gl_iters = gl_iters;
+        self
+    }
+
+    /// Frequency above which the input carries no information (Hz)
+    pub fn nyquist(&self) -> f32 {
+        self.sample_rate as f32 / 2.0
+    }
+
+    /// Lowers [`max_freq`](Self::max_freq) to the Nyquist frequency of the sample rate when it lies
+    /// above, returning the requested frequency if it was reduced
+    pub fn clamp_max_freq(&mut self) -> Option<f32> {
+        let requested = self.max_freq;
+        (requested > self.nyquist()).then(|| {
+            self.max_freq = self.nyquist();
+            requested
+        })
+    }
+
+    /// Widens the frequency range to every bin the FFT yields at the sample rate, from the lowest
+    /// above DC, which no axis scale can place, up to the Nyquist frequency
+    pub fn span_full_spectrum(&mut self) {
+        self.min_freq = self.sample_rate as f32 / self.fft_len() as f32;
+        self.max_freq = self.nyquist();
+    }
+
+    /// Number of samples each window is zero-padded to before the FFT
+    pub fn fft_len(&self) -> usize {
+        self.fft_size.unwrap_or(self.window)
+    }
+
+    /// Height in pixels of a row band of the image, including the waveform panel in combined mode
+    pub fn band_height(&self) -> u32 {
+        match self.mode {
+            Mode::Combined => self.waveform_height + self.row_height,
+            Mode::Spectrogram | Mode::Waveform | Mode::Chroma | Mode::Mfcc => self.row_height,
+        }
+    }
+
+    /// Checks that the fields are consistent with each other
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.sample_rate == 0 {
+            return Err(ConfigError::SampleRate);
+        }
+        if self.window < 2 || !self.window.is_power_of_two() {
+            return Err(ConfigError::Window(self.window));
+        }
+        if let Some(fft_size) = self.fft_size {
+            if fft_size < self.window || !fft_size.is_power_of_two() {
+                return Err(ConfigError::FftSize(fft_size, self.window));
+            }
+        }
+        if self.hop == 0 {
+            return Err(ConfigError::Hop);
+        }
+        if self.bins_per_octave == 0 {
+            return Err(ConfigError::BinsPerOctave);
+        }
+        if !(self.min_freq > 0.0 && self.min_freq < self.max_freq) {
+            return Err(ConfigError::FrequencyRange(self.min_freq, self.max_freq));
+        }
+        if self.max_freq > self.nyquist() {
+            return Err(ConfigError::AboveNyquist(self.max_freq, self.nyquist()));
+        }
+        if self.row_height == 0 {
+            return Err(ConfigError::RowHeight);
+        }
+        if self.wrap_width == Some(0) {
+            return Err(ConfigError::WrapWidth);
+        }
+        if self.waveform_height == 0 {
+            return Err(ConfigError::WaveformHeight);
+        }
+        if self.mfcc_coeffs == 0 || self.mfcc_coeffs > self.mel_filters {
+            return Err(ConfigError::MfccCoeffs(self.mfcc_coeffs, self.mel_filters));
+        }
+        if !(0.0..=1.0).contains(&self.grid_opacity) {
+            return Err(ConfigError::GridOpacity(self.grid_opacity));
+        }
+        if !(self.time_offset >= 0.0 && self.time_offset.is_finite()) {
+            return Err(ConfigError::TimeOffset(self.time_offset));
+        }
+        if self.smooth == 0 {
+            return Err(ConfigError::Smooth);
+        }
+        if self.kernel.iter().any(|size| size % 2 == 0) {
+            return Err(ConfigError::Kernel(self.kernel));
+        }
+        if let Some([low, high]) = self.contrast {
+            if !(0.0 <= low && low < high && high <= 100.0) {
+                return Err(ConfigError::Contrast(low, high));
+            }
+        }
+        if self.time_bins == Some(0) {
+            return Err(ConfigError::TimeBins);
+        }
+        let waveform = matches!(self.mode, Mode::Waveform | Mode::Combined);
+        if self.time_bins.is_some() && (self.axes || self.grid || waveform) {
+            return Err(ConfigError::TimeBinsRuler);
+        }
+        if self.mode != Mode::Spectrogram
+            && (self.axes || self.colorbar || self.grid || self.centroid || self.pitch_track)
+        {
+            return Err(ConfigError::WaveformAnnotations);
+        }
+        if !(self.db_floor < 0.0 && self.db_floor.is_finite()) {
+            return Err(ConfigError::DbFloor(self.db_floor));
+        }
+        if let Some(threshold) = self.threshold {
+            let valid = match self.amplitude {
+                Amplitude::Linear => (0.0..=1.0).contains(&threshold),
+                Amplitude::Db => threshold <= 0.0,
+            };
+            if !valid {
+                return Err(ConfigError::Threshold(threshold, self.amplitude));
+            }
+        }
+        if !(self.gain > 0.0 && self.gain.is_finite()) {
+            return Err(ConfigError::Gain(self.gain));
+        }
+        if !(self.gamma > 0.0 && self.gamma.is_finite()) {
+            return Err(ConfigError::Gamma(self.gamma));
+        }
+        let grayscale = self.format == PixelFormat::Gray || self.bit_depth == BitDepth::Sixteen;
+        if grayscale && self.colormap != Colormap::Gray {
+            return Err(ConfigError::ColormapGrayscale(self.colormap));
+        }
+        Ok(())
+    }
+
+    /// Validates the configuration and returns it
+    pub fn build(self) -> Result<Self, ConfigError> {
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+/// Reason a [`SpectrogramConfig`] was rejected
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    SampleRate,
+    Window(usize),
+    FftSize(usize, usize),
+    Hop,
+    BinsPerOctave,
+    FrequencyRange(f32, f32),
+    AboveNyquist(f32, f32),
+    RowHeight,
+    WaveformHeight,
+    MfccCoeffs(usize, usize),
+    WrapWidth,
+    GridOpacity(f32),
+    TimeOffset(f32),
+    Smooth,
+    Kernel([usize; 2]),
+    Contrast(f32, f32),
+    TimeBins,
+    TimeBinsRuler,
+    WaveformAnnotations,
+    DbFloor(f32),
+    Threshold(f32, Amplitude),
+    Gain(f32),
+    Gamma(f32),
+    ColormapGrayscale(Colormap),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::SampleRate => write!(f, "sample rate must be greater than 0"),
+            ConfigError::Window(window) => {
+                write!(
+                    f,
+                    "window size {window} is not a power of two of at least 2"
+                )
+            }
+            ConfigError::FftSize(fft_size, window) => write!(
+                f,
+                "FFT size {fft_size} is not a power of two of at least the window size {window}"
+            ),
+            ConfigError::Hop => write!(f, "hop size must be at least 1"),
+            ConfigError::BinsPerOctave => write!(f, "there must be at least 1 bin per octave"),
+            ConfigError::FrequencyRange(min, max) => write!(
+                f,
+                "frequency range {min} Hz to {max} Hz is empty or starts at or below 0 Hz"
+            ),
+            ConfigError::AboveNyquist(max, nyquist) => write!(
+                f,
+                "max frequency {max} Hz exceeds the Nyquist frequency of {nyquist} Hz"
+            ),
+            ConfigError::RowHeight => write!(f, "row height must be at least 1 pixel"),
+            ConfigError::WrapWidth => write!(f, "wrap width must be at least 1 column"),
+            ConfigError::WaveformHeight => write!(f, "waveform height must be at least 1 pixel"),
+            ConfigError::MfccCoeffs(coeffs, filters) => write!(
+                f,
+                "{coeffs} MFCC coefficients requested, there must be between 1 and the number of mel \
+                 filters ({filters})"
+            ),
+            ConfigError::GridOpacity(opacity) => {
+                write!(f, "grid opacity {opacity} must be between 0 and 1")
+            }
+            ConfigError::TimeOffset(offset) => {
+                write!(f, "time offset {offset} must be a non-negative number of seconds")
+            }
+            ConfigError::Smooth => write!(f, "smoothing must span at least 1 frame"),
+            ConfigError::Kernel([frames, bins]) => write!(
+                f,
+                "median kernel {frames}x{bins} must span an odd number of frames and of bins"
+            ),
+            ConfigError::Contrast(low, high) => write!(
+                f,
+                "contrast percentiles {low} and {high} must rise from 0 to at most 100"
+            ),
+            ConfigError::TimeBins => write!(f, "there must be at least 1 time bin"),
+            ConfigError::TimeBinsRuler => write!(
+                f,
+                "axes, grid and waveforms place frames a hop apart and cannot be drawn with time \
+                 bins"
+            ),
+            ConfigError::WaveformAnnotations => {
+                write!(
+                    f,
+                    "axes, colorbar, grid, centroid and pitch track are only drawn in spectrogram mode"
+                )
+            }
+            ConfigError::DbFloor(floor) => write!(f, "decibel floor {floor} must be negative"),
+            ConfigError::Threshold(threshold, Amplitude::Linear) => write!(
+                f,
+                "threshold {threshold} must be between 0 and 1 of the normalized magnitude"
+            ),
+            ConfigError::Threshold(threshold, Amplitude::Db) => {
+                write!(f, "threshold {threshold} dB must not be above 0 dB")
+            }
+            ConfigError::Gain(gain) => write!(f, "gain {gain} must be greater than 0"),
+            ConfigError::Gamma(gamma) => write!(f, "gamma {gamma} must be greater than 0"),
+            ConfigError::ColormapGrayscale(colormap) => write!(
+                f,
+                "grayscale and 16-bit output cannot use the {colormap:?} colormap"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
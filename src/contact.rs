@@ -0,0 +1,63 @@
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::font::{draw_text, text_width, GLYPH_HEIGHT};
+
+/// Space in pixels around and between the tiles of a contact sheet
+const TILE_GAP: u32 = 4;
+/// Space between a thumbnail and its caption
+const CAPTION_GAP: u32 = 2;
+
+/// Tiles `images` into a grid of `columns` thumbnails per row, each scaled to `thumb_size` (width
+/// by height) and captioned below with its name, in the order given
+///
+/// Every image is stretched to fill its thumbnail, so recordings of any length take the same room
+/// and can be compared at a glance. Captions too wide for their thumbnail are cut short and end in
+/// `..`. The sheet has a black background with white captions.
+pub fn contact_sheet(
+    images: &[(String, DynamicImage)],
+    columns: usize,
+    thumb_size: [u32; 2],
+) -> RgbaImage {
+    let [thumb_width, thumb_height] = thumb_size;
+    let columns = columns.clamp(1, images.len().max(1)) as u32;
+    let rows = images.len().div_ceil(columns as usize) as u32;
+    let tile_width = thumb_width + TILE_GAP;
+    let tile_height = thumb_height + CAPTION_GAP + GLYPH_HEIGHT + TILE_GAP;
+
+    let mut sheet = RgbaImage::from_pixel(
+        TILE_GAP + columns * tile_width,
+        TILE_GAP + rows * tile_height,
+        Rgba([0, 0, 0, 255]),
+    );
+    for (index, (name, img)) in images.iter().enumerate() {
+        let x = TILE_GAP + (index as u32 % columns) * tile_width;
+        let y = TILE_GAP + (index as u32 / columns) * tile_height;
+        let thumbnail = img
+            .resize_exact(thumb_width, thumb_height, FilterType::Triangle)
+            .to_rgba8();
+        imageops::overlay(&mut sheet, &thumbnail, x.into(), y.into());
+
+        let caption = fit_caption(name, thumb_width);
+        draw_text(
+            &mut sheet,
+            x,
+            y + thumb_height + CAPTION_GAP,
+            &caption,
+            Rgba([255, 255, 255, 255]),
+        );
+    }
+    sheet
+}
+
+/// `name`, cut short and ending in `..` when it is wider than `width`
+fn fit_caption(name: &str, width: u32) -> String {
+    if text_width(name) <= width {
+        return name.to_string();
+    }
+    let mut caption = name.to_string();
+    while !caption.is_empty() && text_width(&format!("{caption}..")) > width {
+        caption.pop();
+    }
+    format!("{caption}..")
+}
@@ -0,0 +1,89 @@
+use std::f32::consts::PI;
+
+use rustfft::num_complex::Complex;
+use rustfft::Fft;
+
+use crate::config::SpectrogramConfig;
+
+/// Weight below which the FFT bins of a kernel are dropped, relative to its largest
+const SPARSITY: f32 = 0.0054;
+
+/// Center frequencies of the bins of the constant-Q transform, [`SpectrogramConfig::bins_per_octave`]
+/// to the octave from [`SpectrogramConfig::min_freq`] up to [`SpectrogramConfig::max_freq`] (Hz)
+pub fn cqt_frequencies(config: &SpectrogramConfig) -> Vec<f32> {
+    let bins_per_octave = config.bins_per_octave as f32;
+    (0..)
+        .map(|bin| config.min_freq * 2f32.powf(bin as f32 / bins_per_octave))
+        .take_while(|&freq| freq <= config.max_freq)
+        .collect()
+}
+
+/// Sparse spectral kernels turning the FFT of a block of samples into the bins of a constant-Q
+/// transform, after Brown and Puckette
+///
+/// Each bin correlates the block with a complex tone at its frequency, tapered by
+/// [`SpectrogramConfig::window_fn`] over as many periods as the quality factor `Q` of the bin
+/// spacing, centered on the block. Kernels of low bins that would be longer than the window are cut
+/// to its length, so their resolution is that of the FFT; a longer `window` carries the constant Q
+/// further down. The correlation is a sum over the few FFT bins where the spectrum of the kernel is
+/// not negligible.
+pub(crate) struct CqtKernels {
+    /// FFT bins and conjugated weights of each kernel
+    kernels: Vec<Vec<(usize, Complex<f32>)>>,
+}
+
+impl CqtKernels {
+    /// Kernels of the bins centered on `frequencies`, transformed with `fft` of length
+    /// [`SpectrogramConfig::fft_len`]
+    pub(crate) fn new(config: &SpectrogramConfig, frequencies: &[f32], fft: &dyn Fft<f32>) -> Self {
+        let q = 1.0 / (2f32.powf(1.0 / config.bins_per_octave as f32) - 1.0);
+        let fft_len = config.fft_len();
+        let sample_rate = config.sample_rate as f32;
+
+        let kernels = frequencies
+            .iter()
+            .map(|&freq| {
+                let len = ((q * sample_rate / freq).round() as usize).clamp(2, config.window);
+                let start = (config.window - len) / 2;
+                let coefficients = config.window_fn.coefficients(len);
+                // A full-scale tone comes out as loud as in the FFT of an untapered window
+                let gain =
+                    config.window as f32 / coefficients.iter().sum::<f32>().max(f32::MIN_POSITIVE);
+
+                let mut buffer = vec![Complex::new(0.0, 0.0); fft_len];
+                for (n, coefficient) in coefficients.iter().enumerate() {
+                    let position = start + n;
+                    buffer[position] = Complex::from_polar(
+                        coefficient * gain,
+                        2.0 * PI * freq * position as f32 / sample_rate,
+                    );
+                }
+                fft.process(&mut buffer);
+
+                let peak = buffer
+                    .iter()
+                    .fold(0.0f32, |peak, value| peak.max(value.norm()));
+                buffer
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, value)| value.norm() >= SPARSITY * peak)
+                    .map(|(bin, value)| (bin, value.conj() / fft_len as f32))
+                    .collect()
+            })
+            .collect();
+        CqtKernels { kernels }
+    }
+
+    /// Constant-Q bins of the block whose FFT is `spectrum`
+    pub(crate) fn apply(&self, spectrum: &[Complex<f32>]) -> Vec<Complex<f32>> {
+        self.kernels
+            .iter()
+            .map(|kernel| {
+                kernel
+                    .iter()
+                    .map(|&(bin, weight)| spectrum[bin] * weight)
+                    .sum()
+            })
+            .collect()
+    }
+}
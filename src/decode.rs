@@ -0,0 +1,587 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::FftImageError;
+use crate::progress::decode_spinner;
+use crate::raw::{RawPcm, RawReader};
+
+use indicatif::ProgressBar;
+use memmap2::Mmap;
+use serde::Serialize;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{self, CodecType, Decoder, DecoderOptions},
+    errors::Error,
+    formats::{FormatOptions, FormatReader},
+    io::MediaSource,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+/// Decoded audio samples along with the sample rate reported by the track
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    /// Decoding stopped early because [`DecodeOptions::max_duration`] was reached
+    pub truncated: bool,
+}
+
+/// Returns the samples between `start` and `end` seconds, measured at `sample_rate`
+/// Missing bounds default to the start and end of the input
+pub fn trim_samples(
+    samples: &[f32],
+    sample_rate: u32,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Result<&[f32], FftImageError> {
+    Ok(&samples[trim_range(samples.len(), sample_rate, start, end)?])
+}
+
+/// Indices of the samples [`trim_samples`] keeps out of `len`
+pub fn trim_range(
+    len: usize,
+    sample_rate: u32,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Result<Range<usize>, FftImageError> {
+    let duration = len as f64 / sample_rate as f64;
+    let start = start.unwrap_or(0.0);
+    let end = end.unwrap_or(duration);
+
+    if !((0.0..duration).contains(&start) && start < end && end <= duration) {
+        return Err(FftImageError::TimeRange {
+            start,
+            end,
+            duration,
+        });
+    }
+
+    let start_sample = (start * sample_rate as f64) as usize;
+    let end_sample = ((end * sample_rate as f64) as usize).min(len);
+    Ok(start_sample..end_sample)
+}
+
+/// Returns `samples` without the leading and trailing samples quieter than `threshold_db` (dBFS)
+///
+/// Only the ends are cut, quiet stretches in the middle are kept. An input that is silent
+/// throughout leaves nothing.
+pub fn trim_silence(samples: &[f32], threshold_db: f32) -> &[f32] {
+    &samples[silence_range(samples, threshold_db)]
+}
+
+/// Indices of the samples [`trim_silence`] keeps
+pub fn silence_range(samples: &[f32], threshold_db: f32) -> Range<usize> {
+    let threshold = 10f32.powf(threshold_db / 20.0);
+    let loud = |sample: &f32| sample.abs() >= threshold;
+    match samples.iter().position(loud) {
+        Some(first) => {
+            first
+                ..samples
+                    .iter()
+                    .rposition(loud)
+                    .map_or(first, |last| last + 1)
+        }
+        None => 0..0,
+    }
+}
+
+/// Which channel of a multichannel input is analyzed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelSelection {
+    /// Average of all channels
+    #[default]
+    Mono,
+    /// A single channel by index, starting at 0 for the left channel
+    Index(usize),
+    /// Half the sum of the left and right channels of a stereo input, what they have in common
+    Mid,
+    /// Half the difference of the left and right channels of a stereo input, what sets them apart
+    Side,
+}
+
+impl ChannelSelection {
+    /// Reduces one interleaved frame to a single sample
+    fn select(self, frame: &[f32]) -> f32 {
+        match self {
+            ChannelSelection::Mono => frame.iter().sum::<f32>() / frame.len() as f32,
+            ChannelSelection::Index(index) => frame[index],
+            ChannelSelection::Mid => (frame[0] + frame[1]) / 2.0,
+            ChannelSelection::Side => (frame[0] - frame[1]) / 2.0,
+        }
+    }
+}
+
+impl FromStr for ChannelSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mono" => Ok(ChannelSelection::Mono),
+            "left" => Ok(ChannelSelection::Index(0)),
+            "right" => Ok(ChannelSelection::Index(1)),
+            "mid" => Ok(ChannelSelection::Mid),
+            "side" => Ok(ChannelSelection::Side),
+            _ => s.parse().map(ChannelSelection::Index).map_err(|_| {
+                format!("`{s}` is not a channel, expected mono, left, right, mid, side or an index")
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ChannelSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelSelection::Mono => write!(f, "mono"),
+            ChannelSelection::Index(index) => write!(f, "{index}"),
+            ChannelSelection::Mid => write!(f, "mid"),
+            ChannelSelection::Side => write!(f, "side"),
+        }
+    }
+}
+
+/// Options controlling how an input is decoded
+#[derive(Clone, Debug, Default)]
+pub struct DecodeOptions {
+    /// Channel to extract from multichannel input
+    pub channel: ChannelSelection,
+    /// File extension of the container, used to help format detection
+    pub hint: Option<String>,
+    /// Stop decoding once this many seconds of audio have been collected
+    pub max_duration: Option<f64>,
+    /// Read the input as headerless PCM laid out this way instead of probing its container
+    pub raw: Option<RawPcm>,
+    /// Identifier of the track to decode, the container's default track when `None`
+    pub track: Option<u32>,
+}
+
+impl DecodeOptions {
+    pub fn channel(mut self, channel: ChannelSelection) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn hint(mut self, hint: Option<String>) -> Self {
+        self.hint = hint;
+        self
+    }
+
+    pub fn max_duration(mut self, max_duration: Option<f64>) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+
+    pub fn raw(mut self, raw: Option<RawPcm>) -> Self {
+        self.raw = raw;
+        self
+    }
+
+    pub fn track(mut self, track: Option<u32>) -> Self {
+        self.track = track;
+        self
+    }
+}
+
+/// File extensions of the containers the decoder can read
+pub const AUDIO_EXTENSIONS: [&str; 16] = [
+    "wav", "wave", "flac", "mp1", "mp2", "mp3", "aac", "mkv", "webm", "ogg", "oga", "ogv", "ogx",
+    "ogm", "spx", "opus",
+];
+
+/// Containers the decoder recognizes, as listed when probing fails
+pub const SUPPORTED_CONTAINERS: &str = "WAV, FLAC, Ogg, Matroska/WebM, MP1/MP2/MP3 and ADTS AAC";
+
+/// Symphonia feature adding the container of files with `extension`, for the containers Symphonia
+/// can read that are left out of this build
+pub fn container_feature(extension: &str) -> Option<&'static str> {
+    match extension.to_ascii_lowercase().as_str() {
+        "mp4" | "m4a" | "m4b" | "m4p" | "mov" => Some("isomp4"),
+        "aif" | "aiff" | "aifc" => Some("aiff"),
+        "caf" => Some("caf"),
+        _ => None,
+    }
+}
+
+/// Human-readable name of `codec`, along with the Symphonia feature that decodes it if there is one
+pub fn codec_name(codec: CodecType) -> (String, Option<&'static str>) {
+    let (name, feature) = match codec {
+        codecs::CODEC_TYPE_AAC => ("AAC", Some("aac")),
+        codecs::CODEC_TYPE_ALAC => ("ALAC", Some("alac")),
+        codecs::CODEC_TYPE_FLAC => ("FLAC", Some("flac")),
+        codecs::CODEC_TYPE_MP1 => ("MPEG Layer I", Some("mp1")),
+        codecs::CODEC_TYPE_MP2 => ("MPEG Layer II", Some("mp2")),
+        codecs::CODEC_TYPE_MP3 => ("MP3", Some("mp3")),
+        codecs::CODEC_TYPE_VORBIS => ("Vorbis", Some("vorbis")),
+        codecs::CODEC_TYPE_OPUS => ("Opus", None),
+        codecs::CODEC_TYPE_SPEEX => ("Speex", None),
+        codecs::CODEC_TYPE_MUSEPACK => ("Musepack", None),
+        codecs::CODEC_TYPE_WAVPACK => ("WavPack", None),
+        codecs::CODEC_TYPE_MONKEYS_AUDIO => ("Monkey's Audio", None),
+        codecs::CODEC_TYPE_TTA => ("TTA", None),
+        codecs::CODEC_TYPE_EAC3 => ("E-AC-3", None),
+        codecs::CODEC_TYPE_AC4 => ("AC-4", None),
+        codecs::CODEC_TYPE_DCA => ("DTS", None),
+        codecs::CODEC_TYPE_WMA => ("WMA", None),
+        codecs::CODEC_TYPE_ATRAC1
+        | codecs::CODEC_TYPE_ATRAC3
+        | codecs::CODEC_TYPE_ATRAC3PLUS
+        | codecs::CODEC_TYPE_ATRAC9 => ("ATRAC", None),
+        codecs::CODEC_TYPE_ADPCM_MS | codecs::CODEC_TYPE_ADPCM_IMA_WAV => ("ADPCM", Some("adpcm")),
+        codecs::CODEC_TYPE_ADPCM_G722
+        | codecs::CODEC_TYPE_ADPCM_G726
+        | codecs::CODEC_TYPE_ADPCM_G726LE
+        | codecs::CODEC_TYPE_ADPCM_IMA_QT => ("ADPCM", None),
+        codec => return (format!("an unknown codec ({codec})"), None),
+    };
+    (name.to_string(), feature)
+}
+
+/// Opens `path` for decoding, treating `-` as standard input
+///
+/// Standard input is read into memory in full, since some formats need to seek. Regular files are
+/// read through buffered IO; [`open_source_with`] can memory-map them instead.
+pub fn open_source(path: &str) -> Result<Box<dyn MediaSource>, FftImageError> {
+    open_source_with(path, u64::MAX)
+}
+
+/// Opens `path` for decoding like [`open_source`], memory-mapping regular files of at least
+/// `mmap_threshold` bytes
+///
+/// `0` maps every file and `u64::MAX` none. Mapping saves copying every packet through the read
+/// buffer and falls back to reading when it fails, but decoded a 115 MB WAV no faster from the page
+/// cache, and a file truncated while mapped faults the process, so [`open_source`] does not map.
+pub fn open_source_with(
+    path: &str,
+    mmap_threshold: u64,
+) -> Result<Box<dyn MediaSource>, FftImageError> {
+    if path == "-" {
+        let mut bytes = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut bytes)?;
+        return Ok(Box::new(Cursor::new(bytes)));
+    }
+
+    let file = File::open(path).map_err(|err| FftImageError::Open(Path::new(path).into(), err))?;
+    let metadata = file.metadata()?;
+    if metadata.is_file() && metadata.len() >= mmap_threshold {
+        // SAFETY: the map is only read from. Truncating the file while it is decoded would fault,
+        // as for any mapped file; the input is treated as immutable for the run.
+        match unsafe { Mmap::map(&file) } {
+            Ok(map) => return Ok(Box::new(Cursor::new(map))),
+            Err(err) => log::debug!("Could not map {path}, reading it instead: {err}"),
+        }
+    }
+    Ok(Box::new(file))
+}
+
+/// Decodes the default track of `source` into `f32` samples of the selected channel
+/// With [`ChannelSelection::Mono`], the channels of each frame are averaged
+pub fn extract_samples(
+    source: Box<dyn MediaSource>,
+    options: &DecodeOptions,
+) -> Result<DecodedAudio, FftImageError> {
+    SampleStream::open(source, options)?.read_all()
+}
+
+/// Probes the container of `mss`, helped by the file `extension` when there is one
+fn probe(
+    mss: MediaSourceStream,
+    extension: Option<&str>,
+) -> Result<Box<dyn FormatReader>, FftImageError> {
+    let mut hint = Hint::new();
+    if let Some(extension) = extension {
+        hint.with_extension(extension);
+    }
+
+    let format_opts: FormatOptions = Default::default();
+    let metadata_opts: MetadataOptions = Default::default();
+
+    let unrecognized = || FftImageError::UnrecognizedFormat {
+        extension: extension.map(str::to_owned),
+    };
+    symphonia::default::get_probe()
+        .format(&hint, mss, &format_opts, &metadata_opts)
+        .map(|probed| probed.format)
+        .map_err(|err| match err {
+            Error::Unsupported(_) => unrecognized(),
+            // Without a matching reader the probe scans the whole input for a marker
+            Error::IoError(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+                unrecognized()
+            }
+            err => FftImageError::Decode(err),
+        })
+}
+
+/// What an input holds in one of its tracks, as listed by [`probe_tracks`]
+#[derive(Clone, Debug, Serialize)]
+pub struct TrackInfo {
+    /// Identifier of the track in the container
+    pub id: u32,
+    /// The track is the one decoded when none is picked
+    pub default: bool,
+    pub codec: String,
+    /// Sample rate (Hz)
+    pub sample_rate: Option<u32>,
+    pub channels: Option<usize>,
+    /// Bits per sample of the decoded audio, or as stored when the codec does not say
+    pub bits_per_sample: Option<u32>,
+    /// Number of frames (samples per channel) reported by the container
+    pub frames: Option<u64>,
+    /// Length of the track in seconds
+    pub duration: Option<f64>,
+    pub language: Option<String>,
+}
+
+/// Probes the container of `source` and describes each of its tracks, without decoding any audio
+///
+/// `hint` is the file extension of the container, as in [`DecodeOptions::hint`].
+pub fn probe_tracks(
+    source: Box<dyn MediaSource>,
+    hint: Option<&str>,
+) -> Result<Vec<TrackInfo>, FftImageError> {
+    let format = probe(MediaSourceStream::new(source, Default::default()), hint)?;
+    let default_id = format.default_track().map(|track| track.id);
+
+    let tracks = format
+        .tracks()
+        .iter()
+        .map(|track| {
+            let params = &track.codec_params;
+            let codec = match symphonia::default::get_codecs().get_codec(params.codec) {
+                Some(descriptor) => descriptor.long_name.to_string(),
+                None => codec_name(params.codec).0,
+            };
+            let duration = match (params.time_base, params.n_frames, params.sample_rate) {
+                (Some(time_base), Some(frames), _) => {
+                    let time = time_base.calc_time(frames);
+                    Some(time.seconds as f64 + time.frac)
+                }
+                (None, Some(frames), Some(rate)) => Some(frames as f64 / rate as f64),
+                _ => None,
+            };
+            TrackInfo {
+                id: track.id,
+                default: Some(track.id) == default_id,
+                codec,
+                sample_rate: params.sample_rate,
+                channels: params.channels.map(|channels| channels.count()),
+                bits_per_sample: params.bits_per_sample.or(params.bits_per_coded_sample),
+                frames: params.n_frames,
+                duration,
+                language: track.language.clone(),
+            }
+        })
+        .collect();
+    Ok(tracks)
+}
+
+/// Decoder yielding the samples of the selected channel one packet at a time, so that long inputs
+/// can be analyzed without holding all of their samples in memory
+pub struct SampleStream {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    channel: ChannelSelection,
+    max_duration: Option<f64>,
+    max_samples: Option<usize>,
+    /// Number of samples per channel reported by the container
+    reported_len: Option<usize>,
+    sample_rate: u32,
+    channel_count: usize,
+    sample_buf: Option<SampleBuffer<f32>>,
+    /// Samples of the most recently decoded packet
+    chunk: Vec<f32>,
+    /// `chunk` was decoded by [`SampleStream::open`] and has not been returned yet
+    pending: bool,
+    sample_count: usize,
+    truncated: bool,
+    done: bool,
+    progress: ProgressBar,
+}
+
+impl SampleStream {
+    /// Probes `source`, or reads it as [`DecodeOptions::raw`] PCM, and decodes its first packet,
+    /// which determines the sample rate and channel layout
+    pub fn open(
+        source: Box<dyn MediaSource>,
+        options: &DecodeOptions,
+    ) -> Result<SampleStream, FftImageError> {
+        let mss = MediaSourceStream::new(source, Default::default());
+
+        let decoder_opts: DecoderOptions = Default::default();
+        let format: Box<dyn FormatReader> = match options.raw {
+            Some(raw) => Box::new(RawReader::new(mss, raw)?),
+            None => probe(mss, options.hint.as_deref())?,
+        };
+
+        let track = match options.track {
+            Some(id) => format
+                .tracks()
+                .iter()
+                .find(|track| track.id == id)
+                .ok_or_else(|| FftImageError::TrackNotFound {
+                    requested: id,
+                    available: format.tracks().iter().map(|track| track.id).collect(),
+                })?,
+            None => format.default_track().ok_or(FftImageError::NoTrack)?,
+        };
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &decoder_opts)
+            .map_err(|err| match err {
+                Error::Unsupported(_) => {
+                    let (codec, feature) = codec_name(track.codec_params.codec);
+                    FftImageError::UnsupportedCodec { codec, feature }
+                }
+                err => FftImageError::Decode(err),
+            })?;
+        let reported_len = track.codec_params.n_frames.map(|frames| frames as usize);
+
+        let mut stream = SampleStream {
+            track_id: track.id,
+            sample_rate: track.codec_params.sample_rate.unwrap_or(0),
+            format,
+            decoder,
+            channel: options.channel,
+            max_duration: options.max_duration,
+            max_samples: None,
+            reported_len,
+            channel_count: 1,
+            sample_buf: None,
+            chunk: Vec::new(),
+            pending: false,
+            sample_count: 0,
+            truncated: false,
+            done: false,
+            progress: decode_spinner(),
+        };
+        stream.pending = stream.decode_next()?;
+        Ok(stream)
+    }
+
+    /// Sample rate of the decoded audio (Hz)
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Decoding stopped early because [`DecodeOptions::max_duration`] was reached
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Number of samples the container reports for the track, capped at
+    /// [`DecodeOptions::max_duration`], or `None` when it is only known once decoded
+    pub fn reported_len(&self) -> Option<usize> {
+        let len = self.reported_len?;
+        Some(self.max_samples.map_or(len, |max| len.min(max)))
+    }
+
+    /// Decodes the rest of the input into memory
+    pub fn read_all(&mut self) -> Result<DecodedAudio, FftImageError> {
+        let mut samples = Vec::new();
+        while let Some(chunk) = self.next_chunk()? {
+            samples.extend_from_slice(chunk);
+        }
+
+        Ok(DecodedAudio {
+            samples,
+            sample_rate: self.sample_rate,
+            truncated: self.truncated,
+        })
+    }
+
+    /// Returns the samples of the next packet, or `None` once the input is exhausted
+    pub fn next_chunk(&mut self) -> Result<Option<&[f32]>, FftImageError> {
+        if self.pending {
+            self.pending = false;
+            return Ok(Some(&self.chunk));
+        }
+        if self.done || !self.decode_next()? {
+            return Ok(None);
+        }
+        Ok(Some(&self.chunk))
+    }
+
+    /// Decodes packets into `chunk` until one succeeds, returning `false` at the end of the input
+    fn decode_next(&mut self) -> Result<bool, FftImageError> {
+        while let Ok(packet) = self.format.next_packet() {
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let audio_buf = match self.decoder.decode(&packet) {
+                Ok(audio_buf) => audio_buf,
+                Err(Error::DecodeError(_)) => continue,
+                Err(_) => break,
+            };
+
+            let buf = match &mut self.sample_buf {
+                Some(buf) => buf,
+                None => {
+                    let spec = *audio_buf.spec();
+                    self.sample_rate = spec.rate;
+                    self.channel_count = spec.channels.count().max(1);
+                    log::debug!(
+                        "Decoding track {} at {} Hz with {} channel(s)",
+                        self.track_id,
+                        self.sample_rate,
+                        self.channel_count
+                    );
+
+                    match self.channel {
+                        ChannelSelection::Index(index) if index >= self.channel_count => {
+                            return Err(FftImageError::ChannelOutOfRange {
+                                requested: index,
+                                available: self.channel_count,
+                            });
+                        }
+                        ChannelSelection::Mid | ChannelSelection::Side
+                            if self.channel_count < 2 =>
+                        {
+                            return Err(FftImageError::NotStereo(self.channel));
+                        }
+                        _ => {}
+                    }
+
+                    self.max_samples = self
+                        .max_duration
+                        .map(|seconds| (seconds * self.sample_rate as f64) as usize);
+
+                    let duration = audio_buf.capacity() as u64;
+                    self.sample_buf
+                        .insert(SampleBuffer::<f32>::new(duration, spec))
+                }
+            };
+            buf.copy_interleaved_ref(audio_buf);
+
+            let channel = self.channel;
+            let frames = buf.samples().chunks_exact(self.channel_count);
+            self.chunk.clear();
+            self.chunk.extend(frames.map(|frame| channel.select(frame)));
+
+            if let Some(max) = self.max_samples {
+                if self.sample_count + self.chunk.len() > max {
+                    self.chunk.truncate(max - self.sample_count);
+                    self.truncated = true;
+                    self.done = true;
+                }
+            }
+
+            self.sample_count += self.chunk.len();
+            self.progress.set_position(self.sample_count as u64);
+            return Ok(true);
+        }
+
+        self.done = true;
+        Ok(false)
+    }
+}
+
+impl Drop for SampleStream {
+    fn drop(&mut self) {
+        self.progress.finish_and_clear();
+    }
+}
@@ -0,0 +1,75 @@
+use clap::ValueEnum;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Filter run over the magnitudes of the spectrogram to clean up noise before drawing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Denoise {
+    /// The magnitudes are drawn as analyzed
+    None,
+    /// Every magnitude is replaced with the median of its neighborhood, see [`median_filter`]
+    ///
+    /// Removes isolated clicks and speckles of interference while keeping the edges of tones and
+    /// onsets sharp, which averaging would blur.
+    Median,
+}
+
+/// Median of `values`, the upper of the two middle ones for an even count
+fn median(values: &mut [f32]) -> f32 {
+    let middle = values.len() / 2;
+    *values.select_nth_unstable_by(middle, f32::total_cmp).1
+}
+
+/// Replaces every magnitude of `frames` with the median of the `kernel[0]` frames by `kernel[1]`
+/// bins centered on it, both odd
+///
+/// The filter is separable: each bin is first filtered across the frames around it, then each
+/// frame across its bins. This is not quite the true two-dimensional median, but it rejects the
+/// same isolated outliers at a small fraction of the cost on large kernels. Frames and bins past
+/// the edges repeat the nearest ones, so the borders are filtered like the rest.
+pub fn median_filter(frames: &mut Vec<Vec<f32>>, kernel: [usize; 2]) {
+    let [time, frequency] = kernel.map(|size| size / 2);
+    if frames.is_empty() || (time == 0 && frequency == 0) {
+        return;
+    }
+
+    if time > 0 {
+        let last = frames.len() - 1;
+        let filtered = (0..frames.len())
+            .into_par_iter()
+            .map_init(Vec::new, |window, index| {
+                (0..frames[index].len())
+                    .map(|bin| {
+                        window.clear();
+                        window.extend((0..=2 * time).map(|offset| {
+                            frames[(index + offset).saturating_sub(time).min(last)][bin]
+                        }));
+                        median(window)
+                    })
+                    .collect()
+            })
+            .collect();
+        *frames = filtered;
+    }
+
+    if frequency > 0 {
+        frames
+            .par_iter_mut()
+            .for_each_init(Vec::new, |window, frame| {
+                let Some(last) = frame.len().checked_sub(1) else {
+                    return;
+                };
+                let filtered: Vec<f32> = (0..frame.len())
+                    .map(|bin| {
+                        window.clear();
+                        window.extend((0..=2 * frequency).map(|offset| {
+                            frame[(bin + offset).saturating_sub(frequency).min(last)]
+                        }));
+                        median(window)
+                    })
+                    .collect();
+                *frame = filtered;
+            });
+    }
+}
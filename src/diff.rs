@@ -0,0 +1,49 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::spectrogram::SpectrogramData;
+
+/// How two spectrograms are compared bin by bin
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffMode {
+    /// `|a - b|`, drawn like an ordinary spectrogram
+    Abs,
+    /// `a - b`, drawn around the middle of the colormap with
+    /// [`render_signed`](crate::render_signed)
+    Signed,
+}
+
+/// Difference of the magnitudes of `a` and `b`, which must share their analysis parameters
+///
+/// Frames are paired from the start, so the longer input is truncated to the length of the
+/// shorter one.
+pub fn diff_spectrograms(
+    a: &SpectrogramData,
+    b: &SpectrogramData,
+    mode: DiffMode,
+) -> SpectrogramData {
+    let frames = a
+        .frames
+        .iter()
+        .zip(&b.frames)
+        .map(|(frame_a, frame_b)| {
+            frame_a
+                .iter()
+                .zip(frame_b)
+                .map(|(&a, &b)| match mode {
+                    DiffMode::Abs => (a - b).abs(),
+                    DiffMode::Signed => a - b,
+                })
+                .collect()
+        })
+        .collect();
+
+    SpectrogramData {
+        sample_rate: a.sample_rate,
+        window: a.window,
+        hop: a.hop,
+        frequencies: a.frequencies.clone(),
+        frames,
+    }
+}
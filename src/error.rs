@@ -0,0 +1,335 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::ConfigError;
+use crate::decode::{container_feature, ChannelSelection, SUPPORTED_CONTAINERS};
+use crate::render::Mode;
+
+/// Everything that can go wrong while turning an audio file into an image
+#[derive(Debug)]
+pub enum FftImageError {
+    /// The input file could not be opened
+    Open(PathBuf, io::Error),
+    /// Reading the input or writing the output failed
+    Io(io::Error),
+    /// The input could not be probed or decoded
+    Decode(symphonia::core::errors::Error),
+    /// The input container has no track that can be decoded
+    NoTrack,
+    /// The requested track does not exist in the input, which holds the `available` ones
+    TrackNotFound { requested: u32, available: Vec<u32> },
+    /// The input is not in a container format the decoder recognizes, `extension` being the one
+    /// used as a hint
+    UnrecognizedFormat { extension: Option<String> },
+    /// The codec of the input track has no decoder in this build, along with the Symphonia feature
+    /// that adds one if there is such a feature
+    UnsupportedCodec {
+        codec: String,
+        feature: Option<&'static str>,
+    },
+    /// The requested channel does not exist in the input
+    ChannelOutOfRange { requested: usize, available: usize },
+    /// The mid or side signal was requested from an input with a single channel
+    NotStereo(ChannelSelection),
+    /// The input holds NaN or infinite samples, which have no spectrum
+    NonFiniteSamples,
+    /// The input holds no samples, so there is nothing to draw
+    EmptyInput,
+    /// A raw PCM input was declared with no channels or more than Symphonia can lay out
+    RawChannels(usize),
+    /// The length of a raw PCM input is not a whole number of frames of the declared layout
+    RawLength { len: u64, frame_size: usize },
+    /// The output image could not be encoded or saved
+    Image(image::ImageError),
+    /// The PNG output could not be encoded
+    Png(png::EncodingError),
+    /// The PNG image whose parameters were asked for could not be read
+    PngRead(PathBuf, png::DecodingError),
+    /// The PNG image whose parameters were asked for records none
+    NoParams(PathBuf),
+    /// The OpenEXR output could not be written
+    Exr(exr::error::Error),
+    /// The audio file could not be written
+    Wav(hound::Error),
+    /// The JSON export could not be written
+    Json(serde_json::Error),
+    /// A spectrogram matrix file is malformed or of an unknown version
+    InvalidMatrix(String),
+    /// The mask given to `invert` is a spectrogram drawn with an option that moves its pixels off
+    /// the bins, such as the margins of `--axes`
+    MaskLayout(PathBuf, String),
+    /// A flag placing the rows of the mask given to `invert` differs from the value recorded in
+    /// the mask, given as it appears there
+    MaskFlag(PathBuf, &'static str, String),
+    /// The bins of the spectrogram to invert are not evenly spaced from 0 Hz like those of the
+    /// Fourier transform, as with the constant-Q transform
+    UnevenBins,
+    /// The spectrogram parameters are inconsistent
+    Config(ConfigError),
+    /// A configuration file is not valid TOML or holds unknown or mistyped fields
+    ConfigFile(PathBuf, toml::de::Error),
+    /// The extension of the output path is not an image format that can be written
+    UnsupportedFormat(PathBuf),
+    /// The output image format cannot hold 16 bits per channel
+    FormatBitDepth(image::ImageFormat),
+    /// An option draws into the image, which an OpenEXR output of raw magnitudes has no room for
+    ExrFlag(&'static str),
+    /// The directory the output should be written to does not exist
+    MissingOutputDirectory(PathBuf),
+    /// An input glob pattern is malformed
+    Pattern(glob::PatternError),
+    /// No input file was left once the glob patterns were expanded
+    NoInputs,
+    /// An option naming a single output file was given along with several inputs
+    SingleInputFlag(&'static str),
+    /// An option covering the whole input was given along with `--split-duration`
+    SplitFlag(&'static str),
+    /// An option placing frames a hop apart was given along with `--time-bins`
+    TimeBinsFlag(&'static str),
+    /// An option naming a single output file was given along with `--stereo`
+    StereoFlag(&'static str),
+    /// An option arranging the columns of the image was given to `contact-sheet`, which draws every
+    /// thumbnail in the strip layout
+    ContactSheetFlag(&'static str),
+    /// An option was given in a mode it does not apply to
+    ModeFlag(&'static str, Mode),
+    /// The inputs being compared have different sample rates (Hz) and were not resampled
+    SampleRateMismatch(u32, u32),
+    /// Some inputs of a batch could not be processed, each was reported as it failed
+    BatchFailed { failed: usize, total: usize },
+    /// The requested time range is empty or extends past the end of the input (seconds)
+    TimeRange { start: f64, end: f64, duration: f64 },
+}
+
+impl fmt::Display for FftImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FftImageError::Open(path, err) => write!(f, "could not open {path:?}: {err}"),
+            FftImageError::Io(err) => write!(f, "{err}"),
+            FftImageError::Decode(err) => write!(f, "could not decode audio: {err}"),
+            FftImageError::NoTrack => write!(f, "no decodable audio track found"),
+            FftImageError::TrackNotFound {
+                requested,
+                available,
+            } => {
+                let available: Vec<String> = available.iter().map(u32::to_string).collect();
+                write!(
+                    f,
+                    "track {requested} does not exist, the input has tracks {}",
+                    available.join(", ")
+                )
+            }
+            FftImageError::UnrecognizedFormat { extension } => {
+                write!(
+                    f,
+                    "could not recognize the format of the input, the supported formats are \
+                     {SUPPORTED_CONTAINERS}"
+                )?;
+                match extension.as_deref().and_then(container_feature) {
+                    Some(feature) => write!(
+                        f,
+                        "; .{} files need Symphonia's `{feature}` feature",
+                        extension.as_deref().unwrap_or_default()
+                    ),
+                    None => Ok(()),
+                }
+            }
+            FftImageError::UnsupportedCodec { codec, feature } => match feature {
+                Some(feature) => write!(
+                    f,
+                    "the input is encoded with {codec}, which needs Symphonia's `{feature}` feature"
+                ),
+                None => write!(
+                    f,
+                    "the input is encoded with {codec}, which Symphonia has no decoder for"
+                ),
+            },
+            FftImageError::ChannelOutOfRange {
+                requested,
+                available,
+            } => write!(
+                f,
+                "channel {requested} does not exist, the input has {available} channel(s) (0 to {})",
+                available - 1
+            ),
+            FftImageError::NotStereo(channel) => write!(
+                f,
+                "the {channel} signal needs a stereo input, but the input has a single channel"
+            ),
+            FftImageError::NonFiniteSamples => {
+                write!(f, "could not compute spectrum: the input holds NaN or infinite samples")
+            }
+            FftImageError::EmptyInput => write!(f, "the input holds no audio samples"),
+            FftImageError::RawChannels(channels) => write!(
+                f,
+                "raw input channel count must be between 1 and 31, got {channels}"
+            ),
+            FftImageError::RawLength { len, frame_size } => write!(
+                f,
+                "the raw input is {len} bytes long, which is not a whole number of {frame_size}-byte \
+                 frames, check --raw-channels and --raw-format"
+            ),
+            FftImageError::Image(err) => write!(f, "could not write image: {err}"),
+            FftImageError::Png(err) => write!(f, "could not write image: {err}"),
+            FftImageError::PngRead(path, err) => write!(f, "could not read {path:?}: {err}"),
+            FftImageError::NoParams(path) => write!(
+                f,
+                "{path:?} records no analysis parameters, it was not written as PNG by this program"
+            ),
+            FftImageError::Exr(err) => write!(f, "could not write OpenEXR image: {err}"),
+            FftImageError::Wav(err) => write!(f, "could not write audio: {err}"),
+            FftImageError::Json(err) => write!(f, "could not write JSON: {err}"),
+            FftImageError::InvalidMatrix(reason) => {
+                write!(f, "invalid spectrogram matrix: {reason}")
+            }
+            FftImageError::MaskLayout(path, flag) => write!(
+                f,
+                "the mask {path:?} was drawn with {flag}, which moves its pixels off the bins; draw \
+                 a horizontal spectrogram with --layout strip and without --axes or --colorbar"
+            ),
+            FftImageError::MaskFlag(path, flag, recorded) => write!(
+                f,
+                "the mask {path:?} was drawn with {flag} {recorded}, leave {flag} out to use it"
+            ),
+            FftImageError::UnevenBins => write!(
+                f,
+                "only spectrograms with bins evenly spaced from 0 Hz can be inverted, not those of \
+                 --transform cqt"
+            ),
+            FftImageError::Config(err) => write!(f, "invalid configuration: {err}"),
+            FftImageError::ConfigFile(path, err) => write!(f, "invalid config file {path:?}: {err}"),
+            FftImageError::UnsupportedFormat(path) => write!(
+                f,
+                "cannot write {path:?}, the extension must be one of png, tiff, bmp, jpg or webp"
+            ),
+            FftImageError::FormatBitDepth(format) => write!(
+                f,
+                "{format:?} images cannot hold 16-bit samples, use png or tiff instead"
+            ),
+            FftImageError::ExrFlag(flag) => write!(
+                f,
+                "{flag} cannot be used with OpenEXR output, which holds the raw magnitudes"
+            ),
+            FftImageError::MissingOutputDirectory(dir) => {
+                write!(f, "output directory {dir:?} does not exist")
+            }
+            FftImageError::Pattern(err) => write!(f, "invalid input pattern: {err}"),
+            FftImageError::NoInputs => write!(f, "no input files to process"),
+            FftImageError::SingleInputFlag(flag) => {
+                write!(f, "{flag} names a single file and cannot be used with several inputs")
+            }
+            FftImageError::SplitFlag(flag) => write!(
+                f,
+                "{flag} covers the whole input and cannot be used with --split-duration"
+            ),
+            FftImageError::TimeBinsFlag(flag) => write!(
+                f,
+                "{flag} needs the frames a hop apart and cannot be used with --time-bins"
+            ),
+            FftImageError::StereoFlag(flag) => write!(
+                f,
+                "{flag} names a single file and cannot be used with --stereo, which draws several"
+            ),
+            FftImageError::ContactSheetFlag(flag) => write!(
+                f,
+                "{flag} cannot be used with contact-sheet, whose thumbnails are drawn in the strip \
+                 layout"
+            ),
+            FftImageError::ModeFlag(flag, mode) => {
+                let mode = format!("{mode:?}").to_lowercase();
+                write!(f, "{flag} cannot be used in {mode} mode")
+            }
+            FftImageError::SampleRateMismatch(a, b) => write!(
+                f,
+                "the inputs have different sample rates ({a} Hz and {b} Hz), pass --resample to \
+                 compare them"
+            ),
+            FftImageError::BatchFailed { failed, total } => {
+                write!(f, "{failed} of {total} inputs could not be processed")
+            }
+            FftImageError::TimeRange {
+                start,
+                end,
+                duration,
+            } => write!(
+                f,
+                "time range {start} s to {end} s is invalid, the start must come before the end and \
+                 both must lie within the input duration of {duration:.3} s"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FftImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FftImageError::Open(_, err) | FftImageError::Io(err) => Some(err),
+            FftImageError::Decode(err) => Some(err),
+            FftImageError::Image(err) => Some(err),
+            FftImageError::Png(err) => Some(err),
+            FftImageError::PngRead(_, err) => Some(err),
+            FftImageError::Exr(err) => Some(err),
+            FftImageError::Wav(err) => Some(err),
+            FftImageError::Json(err) => Some(err),
+            FftImageError::Config(err) => Some(err),
+            FftImageError::Pattern(err) => Some(err),
+            FftImageError::ConfigFile(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FftImageError {
+    fn from(err: io::Error) -> Self {
+        FftImageError::Io(err)
+    }
+}
+
+impl From<symphonia::core::errors::Error> for FftImageError {
+    fn from(err: symphonia::core::errors::Error) -> Self {
+        FftImageError::Decode(err)
+    }
+}
+
+impl From<image::ImageError> for FftImageError {
+    fn from(err: image::ImageError) -> Self {
+        FftImageError::Image(err)
+    }
+}
+
+impl From<png::EncodingError> for FftImageError {
+    fn from(err: png::EncodingError) -> Self {
+        FftImageError::Png(err)
+    }
+}
+
+impl From<exr::error::Error> for FftImageError {
+    fn from(err: exr::error::Error) -> Self {
+        FftImageError::Exr(err)
+    }
+}
+
+impl From<hound::Error> for FftImageError {
+    fn from(err: hound::Error) -> Self {
+        FftImageError::Wav(err)
+    }
+}
+
+impl From<serde_json::Error> for FftImageError {
+    fn from(err: serde_json::Error) -> Self {
+        FftImageError::Json(err)
+    }
+}
+
+impl From<ConfigError> for FftImageError {
+    fn from(err: ConfigError) -> Self {
+        FftImageError::Config(err)
+    }
+}
+
+impl From<glob::PatternError> for FftImageError {
+    fn from(err: glob::PatternError) -> Self {
+        FftImageError::Pattern(err)
+    }
+}
@@ -0,0 +1,266 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::config::SpectrogramConfig;
+use crate::error::FftImageError;
+use crate::features::{dominant_pitch, spectral_centroid, spectral_flux, spectral_rolloff};
+use crate::spectrogram::{Normalize, SpectrogramData};
+use crate::window::WindowFn;
+
+/// Writes the magnitudes of `data` to `path` as CSV
+///
+/// The header row holds the center frequency of each bin (Hz), followed by one row per analysis
+/// frame with one magnitude per bin.
+pub fn write_csv(path: &Path, data: &SpectrogramData) -> Result<(), FftImageError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    write_csv_row(&mut writer, &data.frequencies)?;
+    for frame in &data.frames {
+        write_csv_row(&mut writer, frame)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_csv_row(writer: &mut impl Write, values: &[f32]) -> Result<(), FftImageError> {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        write!(writer, "{value}")?;
+    }
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Writes the spectral centroid of every frame of `data`, from [`spectral_centroid`], to `path`
+/// as CSV
+///
+/// The header row is `time,centroid`, followed by one row per analysis frame with the start of the
+/// frame in seconds, counted from [`SpectrogramConfig::time_offset`], and the centroid in Hz. The
+/// centroid of a silent frame is left empty.
+pub fn write_centroid_csv(
+    path: &Path,
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    write_series_csv(path, "centroid", &spectral_centroid(data), data, config)
+}
+
+/// Writes the dominant pitch of every frame of `data`, from [`dominant_pitch`], to `path` as CSV
+///
+/// The header row is `time,pitch`, followed by one row per analysis frame like
+/// [`write_centroid_csv`], the pitch of a silent frame left empty.
+pub fn write_pitch_csv(
+    path: &Path,
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    write_series_csv(path, "pitch", &dominant_pitch(data), data, config)
+}
+
+/// Writes the spectral centroid, rolloff and flux of every frame of `data` to `path` as CSV
+///
+/// The header row is `time,centroid,rolloff,flux`, followed by one row per analysis frame with
+/// its start in seconds like [`write_centroid_csv`], the centroid and rolloff in Hz from
+/// [`spectral_centroid`] and [`spectral_rolloff`], empty for a silent frame, and the flux from
+/// [`spectral_flux`] in the units of the magnitudes.
+pub fn write_features_csv(
+    path: &Path,
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let centroid = spectral_centroid(data);
+    let rolloff = spectral_rolloff(data);
+    let flux = spectral_flux(data);
+    let optional = |value: Option<f32>| value.map_or_else(String::new, |value| value.to_string());
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "time,centroid,rolloff,flux")?;
+    for frame in 0..data.frames.len() {
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            frame_time(frame, data, config),
+            optional(centroid[frame]),
+            optional(rolloff[frame]),
+            flux[frame]
+        )?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes one `time,<name>` row per frame of `data` holding its value in `series`
+fn write_series_csv(
+    path: &Path,
+    name: &str,
+    series: &[Option<f32>],
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writeln!(writer, "time,{name}")?;
+    for (frame, value) in series.iter().enumerate() {
+        let time = frame_time(frame, data, config);
+        match value {
+            Some(value) => writeln!(writer, "{time},{value}")?,
+            None => writeln!(writer, "{time},")?,
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Start of `frame` in seconds, counted from [`SpectrogramConfig::time_offset`]
+fn frame_time(frame: usize, data: &SpectrogramData, config: &SpectrogramConfig) -> f64 {
+    config.time_offset as f64 + frame as f64 * data.hop as f64 / data.sample_rate as f64
+}
+
+/// Pitch of one analysis frame in the JSON written by [`write_pitch_json`]
+#[derive(Serialize)]
+struct JsonPitch {
+    /// Start of the frame in seconds
+    time: f64,
+    /// Dominant frequency (Hz), `null` for a silent frame
+    pitch: Option<f32>,
+}
+
+/// Writes the dominant pitch of every frame of `data`, from [`dominant_pitch`], to `path` as a JSON
+/// array of `{"time": seconds, "pitch": Hz}` objects, the pitch of a silent frame `null`
+pub fn write_pitch_json(
+    path: &Path,
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let pitches: Vec<JsonPitch> = dominant_pitch(data)
+        .into_iter()
+        .enumerate()
+        .map(|(frame, pitch)| JsonPitch {
+            time: frame_time(frame, data, config),
+            pitch,
+        })
+        .collect();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &pitches)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the magnitudes of `data` to `path` as a NumPy `.npy` array
+///
+/// The array is little-endian `float32` of shape `(frames, bins)` in C order: the first axis is
+/// the analysis frame (time) and the second the frequency bin, as listed by [`write_npy_freqs`].
+pub fn write_npy(path: &Path, data: &SpectrogramData) -> Result<(), FftImageError> {
+    let bins = data.frequencies.len();
+    let shape = format!("({}, {bins})", data.frames.len());
+    write_npy_array(path, &shape, data.frames.iter().flatten())
+}
+
+/// Writes the center frequency of each bin of `data` (Hz) to `path` as a 1-D NumPy `.npy` array
+pub fn write_npy_freqs(path: &Path, data: &SpectrogramData) -> Result<(), FftImageError> {
+    let shape = format!("({},)", data.frequencies.len());
+    write_npy_array(path, &shape, data.frequencies.iter())
+}
+
+/// Writes a version 1.0 `.npy` file holding `values` as little-endian `float32` in C order
+fn write_npy_array<'a>(
+    path: &Path,
+    shape: &str,
+    values: impl Iterator<Item = &'a f32>,
+) -> Result<(), FftImageError> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {shape}, }}");
+    // The header is padded with spaces and ends in a newline so the data starts 64-byte aligned
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    let padding = unpadded.next_multiple_of(64) - unpadded;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    for value in values {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the coefficients from [`mfcc`](crate::mfcc) to `path` as CSV
+///
+/// The header row names each coefficient (`c0`, `c1`, ...), followed by one row per analysis frame.
+pub fn write_mfcc_csv(path: &Path, coeffs: &[Vec<f32>]) -> Result<(), FftImageError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    let count = coeffs.first().map_or(0, Vec::len);
+    let header: Vec<String> = (0..count).map(|k| format!("c{k}")).collect();
+    writeln!(writer, "{}", header.join(","))?;
+    for frame in coeffs {
+        write_csv_row(&mut writer, frame)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the coefficients from [`mfcc`](crate::mfcc) to `path` as a NumPy `.npy` array
+///
+/// The array is little-endian `float32` of shape `(frames, coefficients)` in C order.
+pub fn write_mfcc_npy(path: &Path, coeffs: &[Vec<f32>]) -> Result<(), FftImageError> {
+    let count = coeffs.first().map_or(0, Vec::len);
+    let shape = format!("({}, {count})", coeffs.len());
+    write_npy_array(path, &shape, coeffs.iter().flatten())
+}
+
+/// Self-describing JSON representation of a spectrogram
+#[derive(Serialize)]
+struct JsonSpectrogram<'a> {
+    sample_rate: u32,
+    window: usize,
+    fft_size: usize,
+    hop: usize,
+    window_fn: WindowFn,
+    normalize: Normalize,
+    min_freq: f32,
+    max_freq: f32,
+    /// Center frequency of each bin (Hz)
+    frequencies: &'a [f32],
+    /// One array per analysis frame, each holding one magnitude per bin
+    magnitudes: &'a [Vec<f32>],
+}
+
+/// Writes `data` to `path` as JSON, along with the parameters it was computed with
+pub fn write_json(
+    path: &Path,
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let json = JsonSpectrogram {
+        sample_rate: data.sample_rate,
+        window: data.window,
+        fft_size: config.fft_len(),
+        hop: data.hop,
+        window_fn: config.window_fn,
+        normalize: config.normalize,
+        min_freq: config.min_freq,
+        max_freq: config.max_freq,
+        frequencies: &data.frequencies,
+        magnitudes: &data.frames,
+    };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut writer, &json)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use rayon::prelude::*;
+
+use crate::spectrogram::SpectrogramData;
+
+/// Spectral centroid of every frame of `data`, the magnitude-weighted mean of the bin frequencies
+/// (Hz), or `None` for a silent frame
+///
+/// The centroid tracks the brightness of the sound: it rises with the energy of the upper
+/// harmonics and noise, and sits on the frequency of a pure tone.
+pub fn spectral_centroid(data: &SpectrogramData) -> Vec<Option<f32>> {
+    data.frames
+        .par_iter()
+        .map(|frame| {
+            let (weighted, total) = data.frequencies.iter().zip(frame).fold(
+                (0.0, 0.0),
+                |(weighted, total), (&freq, &magnitude)| {
+                    (weighted + freq * magnitude, total + magnitude)
+                },
+            );
+            (total > 0.0).then(|| weighted / total)
+        })
+        .collect()
+}
+
+/// Fraction of the energy of a frame lying at or below its spectral rolloff
+pub const ROLLOFF: f32 = 0.85;
+
+/// Spectral rolloff of every frame of `data`, the frequency of the lowest bin at or below which
+/// [`ROLLOFF`] of its energy lies (Hz), or `None` for a silent frame
+///
+/// The energy of a bin is its squared magnitude. Like the centroid, the rolloff rises with the
+/// brightness of the sound, but ignores how the energy is spread below it, which tells tonal
+/// frames from noisy ones with the same centroid.
+pub fn spectral_rolloff(data: &SpectrogramData) -> Vec<Option<f32>> {
+    data.frames
+        .par_iter()
+        .map(|frame| {
+            let total: f32 = frame.iter().map(|magnitude| magnitude * magnitude).sum();
+            if total <= 0.0 {
+                return None;
+            }
+            let mut energy = 0.0;
+            let bin = frame
+                .iter()
+                .position(|magnitude| {
+                    energy += magnitude * magnitude;
+                    energy >= ROLLOFF * total
+                })
+                .unwrap_or(frame.len() - 1);
+            Some(data.frequencies[bin])
+        })
+        .collect()
+}
+
+/// Spectral flux of every frame of `data`, the Euclidean distance between its magnitudes and
+/// those of the frame before it, 0 for the first frame
+///
+/// The flux peaks at onsets and changes of timbre, and stays low through steady sounds.
+pub fn spectral_flux(data: &SpectrogramData) -> Vec<f32> {
+    let mut flux = vec![0.0; data.frames.len().min(1)];
+    flux.par_extend(data.frames.par_windows(2).map(|pair| {
+        pair[0]
+            .iter()
+            .zip(&pair[1])
+            .map(|(before, after)| (after - before) * (after - before))
+            .sum::<f32>()
+            .sqrt()
+    }));
+    flux
+}
+
+/// Frequency of the strongest bin of every frame of `data` (Hz), refined between the bins by
+/// parabolic interpolation, or `None` for a silent frame
+///
+/// A parabola is fitted through the logarithms of the peak magnitude and of its two neighbors,
+/// and its vertex taken as the peak, which for a tone under a smooth window lands well within a
+/// tenth of a bin of its frequency. The DC bin is never the peak. For monophonic content such as a
+/// single voice or instrument, this traces the melody, though a strong harmonic can win over a weak
+/// fundamental.
+pub fn dominant_pitch(data: &SpectrogramData) -> Vec<Option<f32>> {
+    let freqs = &data.frequencies;
+    data.frames
+        .par_iter()
+        .map(|frame| {
+            let peak = (0..frame.len())
+                .filter(|&bin| freqs[bin] > 0.0 && frame[bin] > 0.0)
+                .max_by(|&a, &b| frame[a].total_cmp(&frame[b]))?;
+            if peak == 0 || peak + 1 >= frame.len() {
+                return Some(freqs[peak]);
+            }
+
+            let [below, center, above] = [frame[peak - 1], frame[peak], frame[peak + 1]]
+                .map(|m| m.max(f32::MIN_POSITIVE).ln());
+            let curvature = below - 2.0 * center + above;
+            // Offset of the vertex from the peak bin, in bins
+            let offset = if curvature < 0.0 {
+                (0.5 * (below - above) / curvature).clamp(-0.5, 0.5)
+            } else {
+                0.0
+            };
+            let neighbor = if offset < 0.0 { peak - 1 } else { peak + 1 };
+            Some(freqs[peak] + offset.abs() * (freqs[neighbor] - freqs[peak]))
+        })
+        .collect()
+}
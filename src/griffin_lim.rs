@@ -0,0 +1,158 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+use crate::config::SpectrogramConfig;
+use crate::spectrogram::SpectrogramData;
+
+/// Estimates a signal whose spectrogram magnitudes match `spectrogram` using the Griffin-Lim algorithm
+///
+/// Phases start out random and are refined over `config.gl_iters` rounds, each resynthesizing the
+/// signal by weighted overlap-add and keeping only the phase of its new spectrum. `config.window_fn`
+/// is used as both the analysis and the synthesis window. The FFT length is read from the spacing
+/// of the bins, so spectrograms analyzed with a `fft_size` above the window are transformed at the
+/// length they were zero-padded to. Bins above the analyzed frequency range are treated as silent.
+/// The bins must be evenly spaced from 0 Hz, as those of [`Transform::Fft`](crate::Transform::Fft)
+/// are; constant-Q bins would land on the wrong FFT bins.
+///
+/// The output is peak-normalized, since the magnitudes in a [`SpectrogramData`] are normalized per
+/// frame and carry no absolute level.
+pub fn reconstruct(spectrogram: &SpectrogramData, config: &SpectrogramConfig) -> Vec<f32> {
+    let window = spectrogram.window;
+    let hop = spectrogram.hop;
+    let fft_len = fft_len(spectrogram);
+    let bins = fft_len / 2 + 1;
+
+    if spectrogram.frames.is_empty() {
+        return Vec::new();
+    }
+
+    // Place each stored bin at its position in the full FFT output
+    let magnitudes: Vec<Vec<f32>> = spectrogram
+        .frames
+        .iter()
+        .map(|frame| {
+            let mut magnitude = vec![0.0; bins];
+            for (&freq, &val) in spectrogram.frequencies.iter().zip(frame) {
+                let bin = (freq * fft_len as f32 / spectrogram.sample_rate as f32).round() as usize;
+                if bin < bins {
+                    magnitude[bin] = val;
+                }
+            }
+            magnitude
+        })
+        .collect();
+
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut phases: Vec<Vec<f32>> = magnitudes
+        .iter()
+        .map(|magnitude| {
+            magnitude
+                .iter()
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    (seed >> 40) as f32 / (1u64 << 24) as f32 * 2.0 * PI - PI
+                })
+                .collect()
+        })
+        .collect();
+
+    let coefficients = config.window_fn.coefficients(window);
+    let mut planner = FftPlanner::new();
+    let forward = planner.plan_fft_forward(fft_len);
+    let inverse = planner.plan_fft_inverse(fft_len);
+
+    let mut signal = overlap_add(&magnitudes, &phases, &coefficients, hop, &inverse);
+    let mut buffer = vec![Complex::new(0.0, 0.0); fft_len];
+
+    for _ in 0..config.gl_iters {
+        for (frame, phase) in phases.iter_mut().enumerate() {
+            let start = frame * hop;
+            buffer.fill(Complex::new(0.0, 0.0));
+            for (i, value) in buffer[..window].iter_mut().enumerate() {
+                *value = Complex::new(signal[start + i] * coefficients[i], 0.0);
+            }
+            forward.process(&mut buffer);
+
+            for (bin, phase) in phase.iter_mut().enumerate() {
+                *phase = buffer[bin].arg();
+            }
+        }
+
+        signal = overlap_add(&magnitudes, &phases, &coefficients, hop, &inverse);
+    }
+
+    let peak = signal
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    if peak > 0.0 {
+        for sample in &mut signal {
+            *sample /= peak;
+        }
+    }
+
+    signal
+}
+
+/// Length of the FFT the bins of `spectrogram` came from, the sample rate over their spacing
+fn fft_len(spectrogram: &SpectrogramData) -> usize {
+    match *spectrogram.frequencies.as_slice() {
+        [first, second, ..] if second > first => {
+            (spectrogram.sample_rate as f32 / (second - first)).round() as usize
+        }
+        _ => spectrogram.window,
+    }
+    .max(spectrogram.window)
+}
+
+/// Inverse transforms every frame and sums them, dividing out the accumulated window energy
+///
+/// Frames are transformed at the length of `inverse`, and only their first window of samples,
+/// before the zero padding, is added back.
+fn overlap_add(
+    magnitudes: &[Vec<f32>],
+    phases: &[Vec<f32>],
+    coefficients: &[f32],
+    hop: usize,
+    inverse: &Arc<dyn Fft<f32>>,
+) -> Vec<f32> {
+    let window = coefficients.len();
+    let fft_len = inverse.len();
+    let len = (magnitudes.len() - 1) * hop + window;
+
+    let mut signal = vec![0.0; len];
+    let mut energy = vec![0.0; len];
+    let mut buffer = vec![Complex::new(0.0, 0.0); fft_len];
+
+    for (frame, (magnitude, phase)) in magnitudes.iter().zip(phases).enumerate() {
+        for (bin, (&m, &p)) in magnitude.iter().zip(phase).enumerate() {
+            buffer[bin] = Complex::from_polar(m, p);
+        }
+        // Mirror the positive frequencies so the inverse transform is real
+        for bin in 1..fft_len / 2 {
+            buffer[fft_len - bin] = buffer[bin].conj();
+        }
+        inverse.process(&mut buffer);
+
+        let start = frame * hop;
+        for (i, (value, coefficient)) in buffer.iter().zip(coefficients).enumerate() {
+            signal[start + i] += value.re / fft_len as f32 * coefficient;
+            energy[start + i] += coefficient * coefficient;
+        }
+    }
+
+    // Tapered windows leave almost no energy at the very edges, so dividing by it there would only
+    // amplify noise
+    let floor = energy.iter().fold(0.0f32, |max, &e| max.max(e)) * 0.1;
+    for (sample, energy) in signal.iter_mut().zip(energy) {
+        if floor > 0.0 {
+            *sample /= energy.max(floor);
+        }
+    }
+
+    signal
+}
@@ -0,0 +1,77 @@
+//! Renders audio files as spectrogram images.
+//!
+//! [`extract_samples`] decodes an audio file, [`compute_spectrogram`] turns the samples into a
+//! matrix of per-window magnitude spectra, and [`render`] draws that matrix into an image.
+//! [`reconstruct`] goes the other way, estimating audio from a matrix of magnitudes, and
+//! [`sonify`] from an image.
+
+mod axes;
+mod chroma;
+mod colormap;
+mod config;
+mod contact;
+mod cqt;
+mod decode;
+mod denoise;
+mod diff;
+mod error;
+mod export;
+mod features;
+mod font;
+mod griffin_lim;
+mod matrix;
+mod mfcc;
+mod output;
+mod progress;
+mod raw;
+mod render;
+mod resample;
+mod scale;
+mod sonify;
+mod spectrogram;
+mod wav;
+mod waveform;
+mod weighting;
+mod window;
+
+pub use chroma::{chromagram, pitch_class, render_chroma, PITCH_CLASSES};
+pub use colormap::Colormap;
+pub use config::{ConfigError, SpectrogramConfig};
+pub use contact::contact_sheet;
+pub use cqt::cqt_frequencies;
+pub use decode::{
+    codec_name, container_feature, extract_samples, open_source, open_source_with, probe_tracks,
+    silence_range, trim_range, trim_samples, trim_silence, ChannelSelection, DecodeOptions,
+    DecodedAudio, SampleStream, TrackInfo, AUDIO_EXTENSIONS, SUPPORTED_CONTAINERS,
+};
+pub use denoise::{median_filter, Denoise};
+pub use diff::{diff_spectrograms, DiffMode};
+pub use error::FftImageError;
+pub use export::{
+    write_centroid_csv, write_csv, write_features_csv, write_json, write_mfcc_csv, write_mfcc_npy,
+    write_npy, write_npy_freqs, write_pitch_csv, write_pitch_json,
+};
+pub use features::{dominant_pitch, spectral_centroid, spectral_flux, spectral_rolloff, ROLLOFF};
+pub use griffin_lim::reconstruct;
+pub use matrix::{load_matrix, save_matrix};
+pub use mfcc::{mel_filterbank, mfcc, render_mfcc};
+pub use output::{
+    output_format, png_params, read_png_params, save_image, write_exr, write_png, PngCompression,
+};
+pub use raw::{RawFormat, RawPcm};
+pub use render::{
+    nearest_power_of_two_below, render, render_image, render_phase, render_signed, Amplitude,
+    BitDepth, ImageLayout, Layout, Mode, Orientation, PixelFormat,
+};
+pub use resample::resample;
+pub use scale::{bark_to_hz, hz_to_bark, hz_to_mel, mel_to_hz, FrequencyScale};
+pub use sonify::{apply_mask, image_to_spectrogram, sonify};
+pub use spectrogram::{
+    bin_frequencies, compute_phase_spectrogram, compute_spectrogram, compute_spectrogram_with,
+    frame_count, AmplitudeScaler, Normalize, SpectralFrame, SpectralFrames, SpectrogramData,
+    SpectrogramStream, Transform,
+};
+pub use wav::write_wav;
+pub use waveform::{render_combined, render_waveform, waveform_envelope};
+pub use weighting::Weighting;
+pub use window::WindowFn;
@@ -1,96 +1,1167 @@
-use clap::{arg, command, Parser};
-use std::cmp::min;
-use std::fs::File;
-
-use symphonia::core::{
-    audio::SampleBuffer, codecs::DecoderOptions, errors::Error, formats::FormatOptions,
-    io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+use clap::parser::ValueSource;
+use clap::{
+    ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
 };
+use image::{DynamicImage, ImageFormat};
+use log::{debug, error, info, warn, LevelFilter};
+use serde::Serialize;
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-use image::{ImageBuffer, Rgba};
-use spectrum_analyzer::scaling::scale_to_zero_to_one;
-use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
-use std::f32::consts::LN_10;
+use fft_image_extractor::{
+    apply_mask, bin_frequencies, chromagram, compute_phase_spectrogram, compute_spectrogram,
+    contact_sheet, diff_spectrograms, frame_count, load_matrix, mfcc, open_source, output_format,
+    probe_tracks, read_png_params, reconstruct, render_chroma, render_combined, render_image,
+    render_mfcc, render_phase, render_signed, render_waveform, resample, save_image, save_matrix,
+    silence_range, sonify, trim_range, trim_samples, write_centroid_csv, write_csv, write_exr,
+    write_features_csv, write_json, write_mfcc_csv, write_mfcc_npy, write_npy, write_npy_freqs,
+    write_pitch_csv, write_pitch_json, write_png, write_wav, Amplitude, BitDepth, ChannelSelection,
+    Colormap, DecodeOptions, Denoise, DiffMode, FftImageError, FrequencyScale, ImageLayout, Layout,
+    Mode, Normalize, Orientation, PixelFormat, PngCompression, RawFormat, RawPcm, SampleStream,
+    SpectrogramConfig, SpectrogramData, SpectrogramStream, TrackInfo, Transform, Weighting,
+    WindowFn, AUDIO_EXTENSIONS,
+};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct FftResampler {
-    /// Input audio file
-    #[arg(short, long)]
+    #[command(subcommand)]
+    command: Command,
+
+    /// Number of worker threads used for the analysis and rendering
+    /// Defaults to one per CPU core
+    #[arg(long, global = true, value_parser = parse_threads)]
+    threads: Option<usize>,
+
+    /// Print more diagnostics, repeat for more detail
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Print fewer diagnostics, repeat to silence everything but errors
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "verbose")]
+    quiet: u8,
+}
+
+/// How the inputs are decoded into the samples to analyze
+#[derive(Clone, Args)]
+#[command(next_help_heading = "Input")]
+struct InputArgs {
+    /// File extension of the input container (e.g. `wav`, `flac`)
+    /// Helps format detection, mostly useful when reading from standard input
+    #[arg(long)]
+    hint: Option<String>,
+
+    /// Channel to analyze: mono (average of all channels), left, right, a channel index, or the
+    /// mid (L+R) or side (L-R) signal of a stereo input
+    #[arg(long, default_value_t = ChannelSelection::Mono)]
+    channel: ChannelSelection,
+
+    /// ID of the track to analyze in a container holding several, as listed by `info`
+    /// Defaults to the container's default track
+    #[arg(long)]
+    track: Option<u32>,
+
+    /// Sample rate of the input in Hz
+    /// Overrides the rate reported by the decoder, useful for raw/headerless inputs
+    #[arg(long)]
+    sample_rate: Option<u32>,
+
+    /// Read the inputs as headerless PCM laid out by `--raw-rate`, `--raw-channels` and
+    /// `--raw-format` instead of probing their container
+    #[arg(long, requires = "raw_rate")]
+    raw: bool,
+
+    /// Sample rate of raw inputs in Hz
+    #[arg(long, requires = "raw", value_parser = parse_sample_rate)]
+    raw_rate: Option<u32>,
+
+    /// Number of interleaved channels of raw inputs
+    #[arg(long, default_value_t = 1, requires = "raw")]
+    raw_channels: usize,
+
+    /// Sample encoding of raw inputs
+    #[arg(long, value_enum, default_value_t = RawFormat::I16le, requires = "raw")]
+    raw_format: RawFormat,
+
+    /// Stop decoding after this many seconds of audio
+    /// Bounds memory use and output size for long inputs
+    #[arg(long, value_parser = parse_duration)]
+    max_duration: Option<f64>,
+
+    /// Resample the input to this rate (Hz) before analysis
+    /// Lines up the frequency axes of recordings made at different rates
+    #[arg(long, value_parser = parse_sample_rate)]
+    resample: Option<u32>,
+
+    /// Start of the analyzed range, in seconds from the beginning of the input
+    #[arg(long)]
+    start: Option<f64>,
+
+    /// End of the analyzed range, in seconds from the beginning of the input
+    #[arg(long)]
+    end: Option<f64>,
+
+    /// Cut the leading and trailing samples quieter than `--silence-threshold` before the
+    /// analysis, after `--start` and `--end`
+    /// Keeps dead air at the ends of a recording from drawing blank columns
+    #[arg(long)]
+    trim_silence: bool,
+
+    /// Level in dBFS below which `--trim-silence` counts samples as silent
+    #[arg(long, default_value_t = -60.0, allow_negative_numbers = true, requires = "trim_silence")]
+    silence_threshold: f32,
+}
+
+/// Parameters of the analysis and of the image drawn from it, which `--config` presets
+#[derive(Clone, Args)]
+#[command(next_help_heading = "Analysis and drawing")]
+struct ImageArgs {
+    /// TOML file of analysis and rendering parameters, named like the fields of
+    /// `SpectrogramConfig` (e.g. `window = 4096`, `colormap = "magma"`)
+    /// Flags given on the command line take precedence
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// What the image shows along the time axis
+    /// `waveform` skips the FFT and draws the min/max envelope of the samples under each column,
+    /// with the same layout as the spectrogram; `combined` draws that envelope in a panel above
+    /// every row of the spectrogram, aligned column for column; `chroma` folds the spectrum into
+    /// twelve stripes per row, one per pitch class from C at the bottom to B at the top, `--height
+    /// 12` giving one pixel to each; `mfcc` draws one stripe per mel-frequency cepstral
+    /// coefficient, the first at the bottom, each scaled over its own range
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().mode)]
+    mode: Mode,
+
+    /// Height in pixels of each image row, which the frequency axis is stretched onto
+    /// Independent of the number of frequency bins, which `--window` and `--fft-size` set
+    #[arg(long, default_value_t = SpectrogramConfig::default().row_height)]
+    height: u32,
+
+    /// Former name of `--height`
+    #[arg(short, long, hide = true, conflicts_with = "height")]
+    width: Option<u32>,
+
+    /// Height of the waveform panel above each row with `--mode combined`
+    #[arg(long, default_value_t = SpectrogramConfig::default().waveform_height, value_parser = parse_waveform_height)]
+    waveform_height: u32,
+
+    /// Number of triangular mel filters the spectrum is summed into with `--mode mfcc`
+    #[arg(long, default_value_t = SpectrogramConfig::default().mel_filters)]
+    mel_filters: usize,
+
+    /// Number of cepstral coefficients kept per frame with `--mode mfcc`, at most `--mel-filters`
+    #[arg(long, default_value_t = SpectrogramConfig::default().mfcc_coeffs)]
+    num_coeffs: usize,
+
+    /// Arrangement of the time columns in the image
+    /// `wrapped` stacks rows of columns top to bottom; `strip` draws a single row with one column
+    /// per window, like most audio tools
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().layout)]
+    layout: Layout,
+
+    /// Number of time columns per row in the wrapped layout
+    /// Sets the aspect ratio of the image; defaults to a quarter of the largest power of two below
+    /// the number of windows
+    #[arg(long, value_parser = parse_wrap_width)]
+    wrap_width: Option<usize>,
+
+    /// Direction of the time axis
+    /// `vertical` runs time top to bottom with frequency across, which suits long recordings
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().orientation)]
+    orientation: Orientation,
+
+    /// Label the frequency axis with ticks at the lowest frequency and every power of ten, and add
+    /// a time ruler below every row
+    #[arg(long)]
+    axes: bool,
+
+    /// Blend faint lines over the spectrogram at octaves of 1 kHz and at regular time intervals
+    /// A lighter aid to reading the image than `--axes`
+    #[arg(long)]
+    grid: bool,
+
+    /// Color of the grid lines, as hexadecimal `rrggbb`
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    grid_color: [u8; 3],
+
+    /// Opacity of the grid lines, from 0 (invisible) to 1 (opaque)
+    #[arg(long, default_value_t = SpectrogramConfig::default().grid_opacity, value_parser = parse_opacity)]
+    grid_opacity: f32,
+
+    /// Draw the spectral centroid of every frame, the magnitude-weighted mean frequency that
+    /// tracks the brightness of the sound, as a line over the spectrogram
+    #[arg(long)]
+    centroid: bool,
+
+    /// Draw the dominant pitch of every frame, the frequency of its strongest bin refined between
+    /// bins, as a red line over the spectrogram, apart from the `--centroid` line
+    /// Traces the melody of monophonic content such as a single voice; a smooth `--window-fn` such
+    /// as `hann` keeps the interpolation accurate
+    #[arg(long)]
+    pitch_track: bool,
+
+    /// Draw a colorbar down the right edge, labeled with the amplitude range (in dB with
+    /// `--amplitude db`)
+    #[arg(long)]
+    colorbar: bool,
+
+    /// Number of samples per FFT window
+    /// Must be a power of two
+    #[arg(long, default_value_t = SpectrogramConfig::default().window, value_parser = parse_window)]
+    window: usize,
+
+    /// Length of the FFT each window is zero-padded to
+    /// Must be a power of two of at least the window size. Padding interpolates the spectrum onto
+    /// more closely spaced bins but does not improve the true frequency resolution, which is set
+    /// by `--window`
+    #[arg(long, value_parser = parse_window)]
+    fft_size: Option<usize>,
+
+    /// Number of samples between the starts of consecutive windows
+    /// Defaults to the window size (no overlap)
+    #[arg(long, value_parser = parse_hop)]
+    hop: Option<usize>,
+
+    /// Window function applied to each block of samples before the FFT
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().window_fn)]
+    window_fn: WindowFn,
+
+    /// Transform computing the frequency bins of each window
+    /// `cqt` spaces the bins geometrically, `--bins-per-octave` to the octave from `--min-freq` up
+    /// to `--max-freq`, with a resolution proportional to frequency: fine at the bass, fast at the
+    /// treble. Bins whose kernel would outgrow `--window` fall back to its resolution
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().transform)]
+    transform: Transform,
+
+    /// Number of bins to the octave with `--transform cqt`
+    #[arg(
+        long,
+        default_value_t = SpectrogramConfig::default().bins_per_octave,
+        value_parser = parse_bins_per_octave
+    )]
+    bins_per_octave: usize,
+
+    /// Color palette used to map amplitude to pixel color
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().colormap)]
+    colormap: Colormap,
+
+    /// Flip the intensity ramp, e.g. dark signal on a white background with the gray colormap
+    #[arg(long)]
+    invert: bool,
+
+    /// How spectrum magnitudes are scaled before drawing
+    /// `column` divides each column by its loudest bin, so recordings of different loudness look
+    /// alike; `global` divides by the loudest bin of the whole input, which keeps every column's raw
+    /// spectrum in memory until the maximum is known; `length` and `sqrt-length` divide by the FFT
+    /// length or its square root; `none` draws raw magnitudes. Combine with `--amplitude db` for a
+    /// decibel scale. `--mode mfcc` takes `column` as `none`, keeping the level of each frame
+    #[arg(
+        long,
+        visible_alias = "scaling",
+        value_enum,
+        default_value_t = SpectrogramConfig::default().normalize
+    )]
+    normalize: Normalize,
+
+    /// Frequency weighting applied to every bin before normalizing
+    /// `a` follows the sensitivity of hearing at moderate levels, so the image shows what is
+    /// audible rather than raw energy; `c` only rolls off the extremes, for loud sounds; `z` leaves
+    /// the spectrum flat
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().weighting)]
+    weighting: Weighting,
+
+    /// Filter cleaning up the magnitudes before they are smoothed and drawn
+    /// `median` replaces every magnitude with the median of its `--kernel` neighborhood, which
+    /// removes clicks and sparse interference while keeping the edges of tones sharp
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().denoise)]
+    denoise: Denoise,
+
+    /// Size of the `--denoise median` neighborhood, as frames by bins such as `5x3`
+    /// Both must be odd
+    #[arg(long, value_name = "NxM", default_value = "3x3", value_parser = parse_kernel)]
+    kernel: [usize; 2],
+
+    /// Average the magnitude of every frequency bin over this many consecutive frames, centered on
+    /// each frame so that nothing shifts in time
+    /// Evens out the flicker of noisy recordings and brings out sustained tones; 1 turns it off
+    #[arg(long, value_name = "FRAMES", default_value_t = SpectrogramConfig::default().smooth, value_parser = parse_smooth)]
+    smooth: usize,
+
+    /// Stretch the magnitudes between these low and high percentiles of the whole input, such as
+    /// `2,98`, onto the full intensity range, clipping the rest
+    /// Unlike normalizing to the loudest bin, a few loud outliers no longer wash out everything
+    /// else. Applies in decibels with `--amplitude db`
+    #[arg(long, value_name = "LOW,HIGH", value_parser = parse_contrast)]
+    contrast: Option<[f32; 2]>,
+
+    /// Average or interpolate the frames into exactly this many time columns, whatever the length
+    /// of the input
+    /// With `--layout strip` and `--height`, every image comes out the same size, as datasets for
+    /// machine learning want. The columns no longer lie a hop apart, so `--axes`, `--grid`,
+    /// `--phase`, `--json`, `--save-matrix`, `--dump-wav` and the exports with a time column cannot
+    /// be used along with it
+    #[arg(long, value_name = "N", value_parser = parse_time_bins)]
+    time_bins: Option<usize>,
+
+    /// Draw an image of exactly WIDTHxHEIGHT pixels, whatever the duration and sample rate of the
+    /// input
+    /// Shorthand for `--layout strip` with `--time-bins` and `--height` set to the time and
+    /// frequency sides, which are swapped with `--orientation vertical`. Along time, each column
+    /// averages the frames it covers, weighted by how much of each it covers, or when there are
+    /// fewer frames than columns interpolates linearly between the two nearest. Along frequency,
+    /// the bins falling on one row are averaged and the rows between bins repeat the bin below, or
+    /// ramp between them with `--interpolate`
+    #[arg(
+        long,
+        value_name = "WIDTHxHEIGHT",
+        value_parser = parse_size,
+        conflicts_with_all = ["time_bins", "height", "width", "layout", "wrap_width", "colorbar"]
+    )]
+    size: Option<[u32; 2]>,
+
+    /// Channel layout of the output image
+    /// `gray` writes a single byte per pixel, a quarter of the size of `rgba`
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().format)]
+    format: PixelFormat,
+
+    /// Bits per channel of the output image
+    /// 16-bit output is grayscale and preserves more dynamic range, at the cost of larger files
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().bit_depth)]
+    bit_depth: BitDepth,
+
+    /// How hard PNG output is compressed
+    /// `fast` writes larger files quickly, `best` the smallest ones slowly; the pixels are the same
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().png_compression)]
+    png_compression: PngCompression,
+
+    /// How spectrum magnitudes are mapped to pixel intensity
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().amplitude)]
+    amplitude: Amplitude,
+
+    /// Lowest level shown in decibel mode (dB relative to the loudest bin)
+    #[arg(
+        long,
+        default_value_t = SpectrogramConfig::default().db_floor,
+        allow_negative_numbers = true,
+        value_parser = parse_db_floor
+    )]
+    db_floor: f32,
+
+    /// Pixels below this level are drawn black, or in the `--background` color, applied after
+    /// normalization
+    /// A fraction of the normalized magnitude (0.0 to 1.0), or a level in dB with `--amplitude db`
+    #[arg(long, allow_negative_numbers = true)]
+    threshold: Option<f32>,
+
+    /// Color of the pixels below `--threshold` and of the image area no frame is drawn into, as
+    /// hexadecimal `rrggbb` or `rrggbbaa` such as `#202020`
+    /// Grayscale images use its luminance. Defaults to the color of silence
+    #[arg(long, value_parser = parse_background)]
+    background: Option<[u8; 4]>,
+
+    /// Multiplier applied to the normalized magnitudes before drawing
+    /// Brightens quiet recordings; the brightest bins clip to full intensity once boosted past it
+    #[arg(long, default_value_t = SpectrogramConfig::default().gain, value_parser = parse_gain)]
+    gain: f32,
+
+    /// Gamma correction applied to pixel intensities as `intensity^(1 / gamma)`
+    /// Values above 1 brighten the midtones; 2.2 is a good starting point for perceptual viewing
+    #[arg(long, default_value_t = SpectrogramConfig::default().gamma)]
+    gamma: f32,
+
+    /// Mapping of frequency onto the vertical axis
+    /// With `mel`, each pixel row corresponds to one mel band; `bark` gives each critical band of
+    /// hearing the same height, a psychoacoustic scale close to `mel`
+    #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().scale)]
+    scale: FrequencyScale,
+
+    /// Ramp the intensity smoothly between the rows of neighboring frequency bins instead of
+    /// holding each bin's value up to the next, which draws visible steps where bins are sparse
+    #[arg(long)]
+    interpolate: bool,
+
+    /// Highest frequency shown in the output image (Hz)
+    /// Clamped to the Nyquist frequency of the input, with a warning unless left at the default
+    #[arg(long, default_value_t = SpectrogramConfig::default().max_freq)]
+    max_freq: f32,
+
+    /// Whether `--max-freq` or the `--config` file sets the maximum frequency, set by [`preset`]
+    #[arg(skip)]
+    max_freq_requested: bool,
+
+    /// Lowest frequency shown in the output image (Hz)
+    /// The frequency axis is logarithmic, so this must be greater than 0
+    #[arg(long, default_value_t = SpectrogramConfig::default().min_freq, value_parser = parse_min_freq)]
+    min_freq: f32,
+
+    /// Show every frequency bin, from the lowest above 0 Hz up to the Nyquist frequency of the
+    /// input, in place of `--min-freq` and `--max-freq`
+    #[arg(long, conflicts_with_all = ["min_freq", "max_freq"])]
+    full_spectrum: bool,
+}
+
+/// Files the analysis is also written to, next to the image
+#[derive(Clone, Default, Args)]
+#[command(next_help_heading = "Exports")]
+struct ExportArgs {
+    /// Also write the spectrogram magnitudes, bin frequencies and analysis parameters to this JSON
+    /// file
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// Also write the spectrogram magnitudes to this CSV file, one row per frame and one column per
+    /// frequency bin
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// Also write the spectrogram magnitudes to this NumPy `.npy` file, a little-endian float32
+    /// array of shape (frames, bins) with time along the first axis
+    #[arg(long)]
+    npy: Option<PathBuf>,
+
+    /// Also write the center frequency of each bin (Hz) to this NumPy `.npy` file, matching the
+    /// second axis of `--npy`
+    #[arg(long)]
+    npy_freqs: Option<PathBuf>,
+
+    /// Also write the spectral centroid of every frame to this CSV file, one `time,centroid` row
+    /// per frame in seconds and Hz, empty for silent frames
+    #[arg(long)]
+    centroid_csv: Option<PathBuf>,
+
+    /// Also write the dominant pitch of every frame to this CSV file, one `time,pitch` row per
+    /// frame in seconds and Hz, empty for silent frames
+    #[arg(long)]
+    pitch_csv: Option<PathBuf>,
+
+    /// Also write the dominant pitch of every frame to this JSON file, as an array of
+    /// `{"time": seconds, "pitch": Hz}` objects with a `null` pitch for silent frames
+    #[arg(long)]
+    pitch_json: Option<PathBuf>,
+
+    /// Also write the spectral centroid, rolloff and flux of every frame to this CSV file, one
+    /// `time,centroid,rolloff,flux` row per frame
+    /// The rolloff is the frequency below which 85% of the energy of the frame lies, and the flux
+    /// the distance between the magnitudes of the frame and of the one before it. The centroid and
+    /// rolloff are empty for silent frames
+    #[arg(long)]
+    features: Option<PathBuf>,
+
+    /// Also write the spectrogram magnitudes to this file, for use with `invert`
+    #[arg(long)]
+    save_matrix: Option<PathBuf>,
+
+    /// Also write the samples that were analyzed, after channel selection, resampling and
+    /// trimming, to this 32-bit float WAV file at the effective sample rate
+    #[arg(long)]
+    dump_wav: Option<PathBuf>,
+}
+
+#[derive(Clone, Args)]
+struct RenderArgs {
+    /// Input audio files, or `-` to read from standard input
+    /// Repeat or list several to write one image per input, named after it. Glob patterns such as
+    /// `*.wav` are expanded, keeping only the files with an audio extension
+    #[arg(short, long, required_unless_present = "load_matrix", num_args = 1..)]
+    file: Vec<String>,
+
+    /// Draw the spectrogram matrix written by `--save-matrix` instead of analyzing an input
+    /// Only the drawing parameters can be given, the analysis ones being those the matrix was
+    /// computed with, so trying out colormaps, gamma or layouts skips the decoding and the FFT.
+    /// Defaults to writing the image next to the matrix
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "file", "hint", "channel", "track", "sample_rate", "raw", "max_duration", "resample",
+            "start", "end", "trim_silence", "stereo", "split_duration", "dry_run", "phase", "diff",
+            "dump_wav", "window", "fft_size", "hop", "window_fn", "transform", "bins_per_octave",
+            "normalize", "weighting", "denoise", "kernel", "smooth", "contrast", "time_bins",
+        ]
+    )]
+    load_matrix: Option<PathBuf>,
+
+    /// Output image path, or `-` to write a PNG to standard output
+    /// Only allowed with a single input
+    /// The format is picked from the extension: png, tiff, bmp, jpg, webp or exr. Only png and tiff
+    /// can hold 16-bit images; exr holds the raw float magnitudes in a single channel, with the
+    /// layout of the drawn image. Defaults to the input name with ".png"
+    #[arg(short, long, conflicts_with = "out_dir")]
+    output: Option<PathBuf>,
+
+    /// Directory the images are written to, each named after its input
+    /// Defaults to the directory of each input
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    /// Draw several signals of a stereo input, each to its own image
+    /// `mid-side` draws the mid (L+R) signal to `name.mid.png` and the side (L-R) signal, which
+    /// holds the stereo width and reverb a mono downmix hides, to `name.side.png`
+    #[arg(long, value_enum, conflicts_with = "channel")]
+    stereo: Option<Stereo>,
+
+    /// Draw the input as a sequence of images `name.000.png`, `name.001.png`, ... each covering
+    /// this many seconds of audio
+    /// Each image runs on 5% into the next, so that events at the boundaries show whole in one of
+    /// them
+    #[arg(long, value_parser = parse_duration)]
+    split_duration: Option<f64>,
+
+    /// Print the size of the image and the memory needed to draw it, without rendering
+    /// The length of the input comes from the container when it reports one, otherwise the input
+    /// is decoded without being analyzed
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Also write a phase spectrogram next to the output, named `<base>.phase.<ext>`
+    #[arg(long, conflicts_with = "diff")]
+    phase: bool,
+
+    /// Former form of the `diff` subcommand, drawing the difference between the spectrograms of
+    /// the input and this file
+    #[arg(long, hide = true)]
+    diff: Option<String>,
+
+    /// How the spectrograms are compared with `--diff`
+    #[arg(long, value_enum, default_value_t = DiffMode::Abs, hide = true, requires = "diff")]
+    diff_mode: DiffMode,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    image: ImageArgs,
+
+    #[command(flatten)]
+    export: ExportArgs,
+}
+
+#[derive(Clone, Args)]
+struct DiffArgs {
+    /// Input audio file, or `-` to read from standard input
     file: String,
 
-    /// Width of the output image
-    /// Controls the number of frequency bins
-    #[arg(short, long, default_value = "128")]
-    width: u32,
+    /// Audio file compared against the input
+    other: String,
+
+    /// Output image path, or `-` to write a PNG to standard output
+    /// The format is picked from the extension, as with `render`. Defaults to the input name with
+    /// ".png"
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// How the spectrograms are compared
+    /// `abs` draws `|a - b|` like a spectrogram; `signed` draws `a - b` around the middle of the
+    /// colormap, which defaults to the diverging `coolwarm`
+    #[arg(long, value_enum, default_value_t = DiffMode::Abs)]
+    diff_mode: DiffMode,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    image: ImageArgs,
+
+    #[command(flatten)]
+    export: ExportArgs,
+}
+
+impl From<DiffArgs> for RenderArgs {
+    fn from(args: DiffArgs) -> Self {
+        RenderArgs {
+            file: vec![args.file],
+            load_matrix: None,
+            input: args.input,
+            output: args.output,
+            out_dir: None,
+            image: args.image,
+            stereo: None,
+            split_duration: None,
+            dry_run: false,
+            phase: false,
+            diff: Some(args.other),
+            diff_mode: args.diff_mode,
+            export: args.export,
+        }
+    }
+}
+
+#[derive(Clone, Args)]
+struct ContactSheetArgs {
+    /// Input audio files, one thumbnail each in the order given
+    /// Glob patterns such as `*.wav` are expanded, keeping only the files with an audio extension
+    #[arg(required = true)]
+    file: Vec<String>,
+
+    /// Output image path
+    /// The format is picked from the extension, as with `render`, except OpenEXR
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Number of thumbnails per row of the sheet
+    #[arg(long, default_value_t = 4, value_parser = parse_columns)]
+    columns: usize,
+
+    /// Size of each thumbnail in pixels, as WIDTHxHEIGHT
+    /// Every spectrogram is drawn in the strip layout, then stretched to this size whatever its
+    /// length
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "256x128", value_parser = parse_size)]
+    thumb_size: [u32; 2],
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    image: ImageArgs,
+}
+
+impl From<ContactSheetArgs> for RenderArgs {
+    fn from(args: ContactSheetArgs) -> Self {
+        RenderArgs {
+            file: args.file,
+            load_matrix: None,
+            input: args.input,
+            output: Some(args.output),
+            out_dir: None,
+            image: args.image,
+            stereo: None,
+            split_duration: None,
+            dry_run: false,
+            phase: false,
+            diff: None,
+            diff_mode: DiffMode::Abs,
+            export: ExportArgs::default(),
+        }
+    }
+}
+
+/// Signals drawn from a stereo input with `--stereo`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Stereo {
+    MidSide,
+}
+
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// Draw the spectrogram of audio files, or their waveform, chromagram or MFCCs
+    /// The default when the first argument is not a subcommand, as in earlier versions
+    Render(RenderArgs),
+    /// Draw the difference between the spectrograms of two audio files
+    /// Both are analyzed with the same parameters; the longer one is truncated to the shorter.
+    /// Inputs with different sample rates need `--resample`
+    Diff(DiffArgs),
+    /// Tile thumbnails of the spectrograms of several audio files into one image, each captioned
+    /// with its file name
+    ContactSheet(ContactSheetArgs),
+    /// Reconstruct audio from a saved spectrogram matrix using the Griffin-Lim algorithm
+    Invert {
+        /// Spectrogram matrix written with --save-matrix
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output WAV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Number of Griffin-Lim iterations
+        /// More iterations give a better phase estimate at the cost of speed
+        #[arg(long, default_value_t = SpectrogramConfig::default().gl_iters)]
+        gl_iters: usize,
+
+        /// Window function the matrix was computed with
+        #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().window_fn)]
+        window_fn: WindowFn,
+
+        /// Image whose pure black pixels silence the bins under them before the reconstruction
+        /// Columns are time, stretched to the frames of the matrix, and rows frequency placed with
+        /// `--scale`, `--min-freq` and `--max-freq`, so hum, clicks or tones can be painted out in
+        /// black on a spectrogram drawn with `--layout strip`. Spectrograms drawn by this program
+        /// record the three, which are used when the flags are left out and must match them when
+        /// given. Bins too quiet to show up are black there already and are silenced too; drawing
+        /// with `--amplitude db` keeps them. Only horizontal spectrograms without `--axes` or
+        /// `--colorbar` are accepted, the pixels of the others lying off the bins
+        #[arg(long)]
+        mask: Option<PathBuf>,
+
+        /// Mapping of frequency onto the rows of the mask
+        /// Defaults to the scale recorded in the mask, or log
+        #[arg(long, value_enum, requires = "mask")]
+        scale: Option<FrequencyScale>,
+
+        /// Frequency of the top row of the mask (Hz)
+        /// Defaults to the frequency recorded in the mask, or 20 Hz
+        #[arg(long, value_parser = parse_min_freq, requires = "mask")]
+        min_freq: Option<f32>,
+
+        /// Frequency of the bottom row of the mask (Hz)
+        /// Defaults to the frequency recorded in the mask, or 10000 Hz, lowered to the Nyquist
+        /// frequency of the matrix as when drawing
+        #[arg(long, requires = "mask")]
+        max_freq: Option<f32>,
+    },
+    /// Synthesize audio whose spectrogram looks like an image
+    /// Columns are time and rows frequency, lowest at the top as drawn with `--layout strip`; pixel
+    /// brightness is the magnitude
+    ImageToAudio {
+        /// Input image
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output WAV file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Sample rate of the output in Hz
+        #[arg(long, default_value_t = SpectrogramConfig::default().sample_rate, value_parser = parse_sample_rate)]
+        sample_rate: u32,
+
+        /// Length of the output in seconds
+        /// Defaults to one hop of samples per image column
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<f64>,
+
+        /// Number of samples per FFT window, frames start a quarter window apart
+        #[arg(long, default_value_t = SpectrogramConfig::default().window, value_parser = parse_window)]
+        window: usize,
+
+        /// Mapping of frequency onto the rows of the image
+        #[arg(long, value_enum, default_value_t = SpectrogramConfig::default().scale)]
+        scale: FrequencyScale,
+
+        /// Frequency of the top row of the image (Hz)
+        #[arg(long, default_value_t = SpectrogramConfig::default().min_freq, value_parser = parse_min_freq)]
+        min_freq: f32,
+
+        /// Frequency of the bottom row of the image (Hz)
+        #[arg(long, default_value_t = SpectrogramConfig::default().max_freq)]
+        max_freq: f32,
+
+        /// Number of Griffin-Lim iterations refining the synthesized phases
+        #[arg(long, default_value_t = SpectrogramConfig::default().gl_iters)]
+        gl_iters: usize,
+    },
+    /// Print the tracks of an audio file with their codec, sample rate, channels, bit depth and
+    /// duration, without decoding it
+    /// The track IDs and channel counts shown are the values `--track` and `--channel` take
+    Info {
+        /// Audio file to inspect, or `-` for standard input
+        file: String,
+
+        /// File extension hinting the container format, as with `render --hint`
+        #[arg(long)]
+        hint: Option<String>,
+
+        /// Print the tracks as JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the analysis parameters recorded in a PNG image written by this program
+    ShowParams {
+        /// PNG image to inspect
+        image: PathBuf,
+    },
+}
+
+fn parse_db_floor(s: &str) -> Result<f32, String> {
+    let floor: f32 = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if !(floor < 0.0 && floor.is_finite()) {
+        return Err("decibel floor must be negative".to_string());
+    }
+    Ok(floor)
+}
+
+fn parse_gain(s: &str) -> Result<f32, String> {
+    let gain: f32 = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if !(gain > 0.0 && gain.is_finite()) {
+        return Err("gain must be greater than 0".to_string());
+    }
+    Ok(gain)
+}
+
+fn parse_duration(s: &str) -> Result<f64, String> {
+    let duration: f64 = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if !(duration > 0.0 && duration.is_finite()) {
+        return Err("duration must be greater than 0 seconds".to_string());
+    }
+    Ok(duration)
+}
+
+fn parse_window(s: &str) -> Result<usize, String> {
+    let window: usize = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if window < 2 || !window.is_power_of_two() {
+        let above = nearest_power_of_two_above(window.max(2) as u32);
+        return Err(format!(
+            "window size must be a power of two of at least 2 (try {})",
+            above
+        ));
+    }
+    Ok(window)
+}
+
+fn parse_hop(s: &str) -> Result<usize, String> {
+    let hop: usize = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if hop == 0 {
+        return Err("hop size must be at least 1".to_string());
+    }
+    Ok(hop)
+}
+
+fn parse_bins_per_octave(s: &str) -> Result<usize, String> {
+    let bins: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if bins == 0 {
+        return Err("there must be at least 1 bin per octave".to_string());
+    }
+    Ok(bins)
+}
+
+fn parse_wrap_width(s: &str) -> Result<usize, String> {
+    let width: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if width == 0 {
+        return Err("wrap width must be at least 1 column".to_string());
+    }
+    Ok(width)
 }
 
-fn extract_samples(file: Box<File>) -> Vec<f32> {
-    let mss = MediaSourceStream::new(file, Default::default());
+fn parse_smooth(s: &str) -> Result<usize, String> {
+    let frames: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if frames == 0 {
+        return Err("smoothing must span at least 1 frame".to_string());
+    }
+    Ok(frames)
+}
 
-    let hint = Hint::new();
-    let format_opts: FormatOptions = Default::default();
-    let metadata_opts: MetadataOptions = Default::default();
-    let decoder_opts: DecoderOptions = Default::default();
+fn parse_columns(s: &str) -> Result<usize, String> {
+    let columns: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if columns == 0 {
+        return Err("there must be at least 1 column".to_string());
+    }
+    Ok(columns)
+}
 
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &format_opts, &metadata_opts)
-        .unwrap();
+fn parse_size(s: &str) -> Result<[u32; 2], String> {
+    let size = s
+        .split_once('x')
+        .and_then(|(width, height)| Some([width.parse().ok()?, height.parse().ok()?]));
+    match size {
+        Some(size) if size.iter().all(|&side| side > 0) => Ok(size),
+        Some(_) => Err("sizes must be at least 1 pixel wide and high".to_string()),
+        None => Err(format!("`{s}` is not a size of the form WIDTHxHEIGHT")),
+    }
+}
 
-    let mut format = probed.format;
+fn parse_time_bins(s: &str) -> Result<usize, String> {
+    let bins: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if bins == 0 {
+        return Err("there must be at least 1 time bin".to_string());
+    }
+    Ok(bins)
+}
 
-    let track = format.default_track().unwrap();
+fn parse_kernel(s: &str) -> Result<[usize; 2], String> {
+    let sizes = s
+        .split_once('x')
+        .and_then(|(frames, bins)| Some([frames.parse().ok()?, bins.parse().ok()?]));
+    match sizes {
+        Some(sizes) if sizes.iter().all(|size| size % 2 == 1) => Ok(sizes),
+        Some(_) => Err("kernel sizes must be odd".to_string()),
+        None => Err(format!("`{s}` is not a kernel size of the form NxM")),
+    }
+}
 
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&track.codec_params, &decoder_opts)
-        .unwrap();
+fn parse_contrast(s: &str) -> Result<[f32; 2], String> {
+    let percentiles = s
+        .split_once(',')
+        .and_then(|(low, high)| Some([low.trim().parse().ok()?, high.trim().parse().ok()?]));
+    match percentiles {
+        Some([low, high]) if 0.0 <= low && low < high && high <= 100.0 => Ok([low, high]),
+        Some(_) => Err("percentiles must rise from 0 to at most 100".to_string()),
+        None => Err(format!("`{s}` is not a pair of percentiles such as `2,98`")),
+    }
+}
 
-    let track_id = track.id;
+fn parse_waveform_height(s: &str) -> Result<u32, String> {
+    let height: u32 = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if height == 0 {
+        return Err("waveform height must be at least 1 pixel".to_string());
+    }
+    Ok(height)
+}
 
-    let mut sample_count = 0;
-    let mut sample_buf = None;
-    let mut all_samples = Vec::new();
+/// Bytes of a hexadecimal string such as `#202020`, the leading `#` being optional
+fn hex_bytes(s: &str) -> Option<Vec<u8>> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    match hex_bytes(s).as_deref() {
+        Some(&[r, g, b]) => Ok([r, g, b]),
+        _ => Err(format!("`{s}` is not a color of the form rrggbb")),
+    }
+}
+
+fn parse_background(s: &str) -> Result<[u8; 4], String> {
+    match hex_bytes(s).as_deref() {
+        Some(&[r, g, b]) => Ok([r, g, b, 255]),
+        Some(&[r, g, b, a]) => Ok([r, g, b, a]),
+        _ => Err(format!(
+            "`{s}` is not a color of the form rrggbb or rrggbbaa"
+        )),
+    }
+}
+
+fn parse_opacity(s: &str) -> Result<f32, String> {
+    let opacity: f32 = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err("opacity must be between 0 and 1".to_string());
+    }
+    Ok(opacity)
+}
 
-    while let Ok(packet) = format.next_packet() {
-        if packet.track_id() != track_id {
+fn parse_threads(s: &str) -> Result<usize, String> {
+    let threads: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if threads == 0 {
+        return Err("thread count must be at least 1".to_string());
+    }
+    Ok(threads)
+}
+
+fn parse_sample_rate(s: &str) -> Result<u32, String> {
+    let rate: u32 = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a whole number"))?;
+    if rate == 0 {
+        return Err("sample rate must be greater than 0".to_string());
+    }
+    Ok(rate)
+}
+
+fn parse_min_freq(s: &str) -> Result<f32, String> {
+    let freq: f32 = s.parse().map_err(|_| format!("`{s}` is not a number"))?;
+    if !(freq > 0.0 && freq.is_finite()) {
+        return Err("minimum frequency must be greater than 0 on a logarithmic axis".to_string());
+    }
+    Ok(freq)
+}
+
+fn output_path(input: &str, output: Option<PathBuf>, out_dir: Option<&Path>) -> PathBuf {
+    output.unwrap_or_else(|| {
+        let name = if input == "-" {
+            PathBuf::from("stdin.png")
+        } else {
+            Path::new(input).with_extension("png")
+        };
+        match out_dir {
+            Some(dir) => dir.join(name.file_name().unwrap_or_default()),
+            None => name,
+        }
+    })
+}
+
+/// Expands the glob patterns among `inputs`, leaving plain paths and `-` as given
+///
+/// Shells on some platforms pass patterns through unexpanded. Matches without an audio extension
+/// are skipped with a warning, unless the inputs are `raw` PCM, which has no extension of its own.
+fn expand_inputs(inputs: &[String], raw: bool) -> Result<Vec<String>, FftImageError> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        if input == "-" || !input.contains(['*', '?', '[']) {
+            expanded.push(input.clone());
             continue;
         }
+        let mut matched = false;
+        for path in glob::glob(input)?.filter_map(Result::ok) {
+            matched = true;
+            if path.is_dir() {
+                continue;
+            }
+            let extension = path.extension().unwrap_or_default().to_string_lossy();
+            if !raw && !AUDIO_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+                warn!("skipping {path:?}, it is not an audio file");
+                continue;
+            }
+            expanded.push(path.to_string_lossy().into_owned());
+        }
+        if !matched {
+            warn!("{input} matched no files");
+        }
+    }
+    if expanded.is_empty() {
+        return Err(FftImageError::NoInputs);
+    }
+    Ok(expanded)
+}
 
-        match decoder.decode(&packet) {
-            Ok(audio_buf) => {
-                if sample_buf.is_none() {
-                    let spec = *audio_buf.spec();
+/// Extension of `input`, which hints at its container format
+fn input_extension(input: &str) -> Option<String> {
+    Path::new(input)
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+}
 
-                    let duration = audio_buf.capacity() as u64;
+/// Opens `input` for decoding, the container format hinted by `hint` or the file extension
+fn open_input(
+    args: &InputArgs,
+    input: &str,
+    hint: Option<String>,
+) -> Result<SampleStream, FftImageError> {
+    let options = DecodeOptions::default()
+        .channel(args.channel)
+        .hint(hint.or_else(|| input_extension(input)))
+        .track(args.track)
+        .max_duration(args.max_duration)
+        .raw(args.raw_rate.map(|sample_rate| RawPcm {
+            sample_rate,
+            channels: args.raw_channels,
+            format: args.raw_format,
+        }));
+    SampleStream::open(open_source(input)?, &options)
+}
 
-                    sample_buf = Some(SampleBuffer::<f32>::new(duration, spec));
-                }
+/// Decodes the rest of `stream`, read at `input_rate`, into the samples to analyze, resampled to
+/// `sample_rate`, trimmed to `--start` and `--end` and cut down to the sound within with
+/// `--trim-silence`
+fn read_samples(
+    args: &InputArgs,
+    stream: &mut SampleStream,
+    input_rate: u32,
+    sample_rate: u32,
+) -> Result<Vec<f32>, FftImageError> {
+    let mut samples = stream.read_all()?.samples;
+    debug!("Decoded {} samples", samples.len());
 
-                if let Some(buf) = &mut sample_buf {
-                    buf.copy_interleaved_ref(audio_buf);
+    if sample_rate != input_rate {
+        samples = resample(&samples, input_rate, sample_rate);
+    }
+    if args.start.is_some() || args.end.is_some() {
+        samples = trim_samples(&samples, sample_rate, args.start, args.end)?.to_vec();
+    }
+    if args.trim_silence {
+        let kept = silence_range(&samples, args.silence_threshold);
+        let seconds = |count: usize| count as f64 / sample_rate as f64;
+        info!(
+            "Trimmed {:.2} s of silence at the start and {:.2} s at the end",
+            seconds(kept.start),
+            seconds(samples.len() - kept.end)
+        );
+        samples.truncate(kept.end);
+        samples.drain(..kept.start);
+    }
+    Ok(samples)
+}
 
-                    sample_count += buf.samples().len();
-                    all_samples.extend_from_slice(buf.samples());
-                    print!("\rDecoded {} samples", sample_count);
-                }
+fn warn_truncated(args: &InputArgs, stream: &SampleStream) {
+    if stream.truncated() {
+        warn!(
+            "input is longer than {} s, only the beginning was decoded",
+            args.max_duration.unwrap_or_default()
+        );
+    }
+}
+
+/// Reports the layout of the image `process_file` would draw for the input of `stream`
+fn dry_run(
+    args: &InputArgs,
+    config: &SpectrogramConfig,
+    stream: &mut SampleStream,
+    input_rate: u32,
+) -> Result<(), FftImageError> {
+    let input_len = match stream.reported_len() {
+        Some(len) => len,
+        None => {
+            let mut len = 0;
+            while let Some(chunk) = stream.next_chunk()? {
+                len += chunk.len();
             }
-            Err(Error::DecodeError(_)) => (),
-            Err(_) => break,
+            len
         }
+    };
+    let mut sample_count = input_len;
+    if config.sample_rate != input_rate {
+        let ratio = config.sample_rate as f64 / input_rate as f64;
+        sample_count = (input_len as f64 * ratio).round() as usize;
+    }
+    if args.start.is_some() || args.end.is_some() {
+        sample_count = trim_range(sample_count, config.sample_rate, args.start, args.end)?.len();
+    }
+    if args.trim_silence {
+        warn!(
+            "the estimate is for the input before --trim-silence, which can only make it smaller"
+        );
     }
-    all_samples
-}
 
-const SAMPLING_RATE: u32 = 44_100;
-const SAMPLING_WINDOW: usize = 2048;
-const FREQUENCY_MAX: f32 = 10_000.0;
+    let frames = frame_count(sample_count, config.window, config.hop);
+    let layout = ImageLayout::new(frames, config);
+    let (width, height) = layout.dimensions(config);
 
-fn nearest_power_of_two_below(x: u32) -> u32 {
-    let mut n = 1;
-    while n * 2 < x {
-        n *= 2;
+    let bytes_per_pixel = match (config.format, config.bit_depth) {
+        (PixelFormat::Rgba, BitDepth::Eight) => 4,
+        (PixelFormat::Gray, BitDepth::Eight) => 1,
+        (_, BitDepth::Sixteen) => 2,
+    };
+    let image_bytes = width as u64 * height as u64 * bytes_per_pixel;
+    // The spectrum of every frame, or the samples the waveform is drawn from
+    let data_bytes = match config.mode {
+        Mode::Spectrogram | Mode::Chroma | Mode::Mfcc => {
+            frames * bin_frequencies(config).len() * size_of::<f32>()
+        }
+        Mode::Waveform => sample_count * size_of::<f32>(),
+        Mode::Combined => {
+            (frames * bin_frequencies(config).len() + sample_count) * size_of::<f32>()
+        }
+    } as u64;
+
+    println!("Samples:      {sample_count} at {} Hz", config.sample_rate);
+    println!("Frames:       {} (total_width)", layout.total_width);
+    println!("Row width:    {} columns (w)", layout.w);
+    println!("Rows:         {} (row_count)", layout.row_count);
+    println!("Stacked rows: {} pixels (img_height)", layout.img_height);
+    println!("Image:        {width} x {height} pixels");
+    println!(
+        "Memory:       about {}",
+        format_bytes(image_bytes + data_bytes)
+    );
+    println!(
+        "File size:    at most {} uncompressed",
+        format_bytes(image_bytes)
+    );
+    Ok(())
+}
+
+/// Formats a byte count with a binary unit, e.g. `12.3 MiB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
     }
-    n
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
 }
 
 fn nearest_power_of_two_above(x: u32) -> u32 {
@@ -101,85 +1172,1169 @@ fn nearest_power_of_two_above(x: u32) -> u32 {
     n
 }
 
+/// Puts `render` before the arguments when they do not start with a subcommand, so that command
+/// lines from before the subcommands, such as `fft-resampler -f song.wav`, still draw an image
+fn default_to_render(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    // The global options may come before the subcommand
+    let mut index = 1;
+    while let Some(arg) = args.get(index).and_then(|arg| arg.to_str()) {
+        let counted = arg.len() > 1
+            && arg
+                .strip_prefix('-')
+                .is_some_and(|flags| flags.chars().all(|c| c == 'v' || c == 'q'));
+        if arg == "--threads" {
+            index += 2;
+        } else if counted || arg == "--verbose" || arg == "--quiet" || arg.starts_with("--threads=")
+        {
+            index += 1;
+        } else {
+            break;
+        }
+    }
+
+    let Some(first) = args.get(index).and_then(|arg| arg.to_str()) else {
+        return args;
+    };
+    let command = FftResampler::command();
+    let known = ["help", "-h", "--help", "-V", "--version"].contains(&first)
+        || command
+            .get_subcommands()
+            .any(|subcommand| subcommand.get_name() == first);
+    if !known {
+        args.insert(index, OsString::from("render"));
+    }
+    args
+}
+
+/// Sends log records to stderr at the level selected by `--verbose`/`--quiet`
+/// Dependencies only log warnings and errors; `RUST_LOG` overrides the flags
+fn init_logging(verbose: u8, quiet: u8) {
+    let level = match (verbose, quiet) {
+        (0, 0) => LevelFilter::Info,
+        (1, _) => LevelFilter::Debug,
+        (_, 0) => LevelFilter::Trace,
+        (_, 1) => LevelFilter::Warn,
+        _ => LevelFilter::Error,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level.min(LevelFilter::Warn))
+        .filter_module("fft_resampler", level)
+        .filter_module("fft_image_extractor", level)
+        .parse_default_env()
+        .format(|buf, record| match record.level() {
+            log::Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+            log::Level::Error => writeln!(buf, "Error: {}", record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .init();
+}
+
 fn main() {
-    let cli = FftResampler::parse();
+    let matches = FftResampler::command().get_matches_from(default_to_render(env::args_os()));
+    let cli = FftResampler::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    init_logging(cli.verbose, cli.quiet);
 
-    let file = Box::new(File::open(&cli.file).unwrap());
-    let audio_samples = extract_samples(file);
-    let sample_count = audio_samples.len();
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .expect("the thread pool is only configured once");
+    }
+
+    let (_, sub_matches) = matches.subcommand().expect("clap requires a subcommand");
+    let result = match cli.command {
+        Command::Render(ref args) => {
+            let mut args = args.clone();
+            preset(&mut args, sub_matches).and_then(|preset| run(&args, &preset))
+        }
+        Command::Diff(ref args) => {
+            let mut args = RenderArgs::from(args.clone());
+            preset(&mut args, sub_matches).and_then(|preset| run(&args, &preset))
+        }
+        Command::ContactSheet(ref args) => {
+            let ContactSheetArgs {
+                ref output,
+                columns,
+                thumb_size,
+                ..
+            } = *args;
+            let mut sheet = RenderArgs::from(args.clone());
+            let layout_flag = [("--layout", "layout"), ("--wrap-width", "wrap_width")]
+                .into_iter()
+                .find(|(_, id)| sub_matches.value_source(id) == Some(ValueSource::CommandLine));
+            match layout_flag {
+                Some((flag, _)) => Err(FftImageError::ContactSheetFlag(flag)),
+                None => preset(&mut sheet, sub_matches).and_then(|preset| {
+                    write_contact_sheet(&sheet, &preset, output, columns, thumb_size)
+                }),
+            }
+        }
+        Command::Invert {
+            ref input,
+            ref output,
+            gl_iters,
+            window_fn,
+            ref mask,
+            scale,
+            min_freq,
+            max_freq,
+        } => {
+            let config = SpectrogramConfig::default()
+                .window_fn(window_fn)
+                .gl_iters(gl_iters);
+            let rows = MaskRows {
+                scale,
+                min_freq,
+                max_freq,
+            };
+            invert(input, output, mask.as_deref(), rows, config)
+        }
+        Command::ImageToAudio {
+            ref input,
+            ref output,
+            sample_rate,
+            duration,
+            window,
+            scale,
+            min_freq,
+            max_freq,
+            gl_iters,
+        } => SpectrogramConfig::default()
+            .sample_rate(sample_rate)
+            .window(window)
+            .hop(window / 4)
+            .scale(scale)
+            .min_freq(min_freq)
+            .max_freq(max_freq)
+            .gl_iters(gl_iters)
+            .build()
+            .map_err(FftImageError::from)
+            .and_then(|config| image_to_audio(input, output, duration, &config)),
+        Command::Info {
+            ref file,
+            ref hint,
+            json,
+        } => info(file, hint.clone(), json),
+        Command::ShowParams { ref image } => show_params(image),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {err}");
+        std::process::exit(1);
+    }
+}
+
+/// Output of `info --json`
+#[derive(Serialize)]
+struct InfoJson<'a> {
+    tracks: &'a [TrackInfo],
+}
+
+/// Prints the tracks of `input`, as text or as JSON
+fn info(input: &str, hint: Option<String>, json: bool) -> Result<(), FftImageError> {
+    let hint = hint.or_else(|| input_extension(input));
+    let tracks = probe_tracks(open_source(input)?, hint.as_deref())?;
+    if json {
+        let stdout = io::stdout().lock();
+        serde_json::to_writer_pretty(stdout, &InfoJson { tracks: &tracks })?;
+        println!();
+        return Ok(());
+    }
 
-    println!("\nFinished, with {} samples", audio_samples.len());
-    let total_width = sample_count / SAMPLING_WINDOW;
-    let h = cli.width;
+    let unknown = || "unknown".to_string();
+    for (index, track) in tracks.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        let default = if track.default { " (default)" } else { "" };
+        println!("Track {}{default}", track.id);
+        println!("  Codec:        {}", track.codec);
+        let rate = track.sample_rate.map(|rate| format!("{rate} Hz"));
+        println!("  Sample rate:  {}", rate.unwrap_or_else(unknown));
+        let channels = track.channels.map(|channels| channels.to_string());
+        println!("  Channels:     {}", channels.unwrap_or_else(unknown));
+        let bits = track.bits_per_sample.map(|bits| format!("{bits} bits"));
+        println!("  Bit depth:    {}", bits.unwrap_or_else(unknown));
+        let duration = match (track.duration, track.frames) {
+            (Some(duration), Some(frames)) => format!("{duration:.3} s ({frames} frames)"),
+            (Some(duration), None) => format!("{duration:.3} s"),
+            _ => unknown(),
+        };
+        println!("  Duration:     {duration}");
+        if let Some(language) = &track.language {
+            println!("  Language:     {language}");
+        }
+    }
+    Ok(())
+}
 
-    // Find the nearest power of two to the total width
-    let nearest_w = nearest_power_of_two_below(total_width as u32);
-    let w: usize = nearest_w as usize / 4; // width of a single row
+/// Prints the text chunks of the PNG at `path`, which record the settings it was drawn with
+fn show_params(path: &Path) -> Result<(), FftImageError> {
+    let params = read_png_params(path)?;
+    if params.is_empty() {
+        return Err(FftImageError::NoParams(path.to_path_buf()));
+    }
+    let width = params
+        .iter()
+        .map(|(keyword, _)| keyword.len())
+        .max()
+        .unwrap_or(0);
+    for (keyword, value) in params {
+        println!(
+            "{:<width$} {value}",
+            format!("{keyword}:"),
+            width = width + 1
+        );
+    }
+    Ok(())
+}
+
+/// Placement of the rows of an `invert` mask given on the command line
+#[derive(Clone, Copy)]
+struct MaskRows {
+    scale: Option<FrequencyScale>,
+    min_freq: Option<f32>,
+    max_freq: Option<f32>,
+}
+
+/// Places the rows of the mask at `path` as it was drawn, according to the parameters recorded in
+/// its PNG text chunks
+///
+/// Masks drawn in another mode, with margins, wrapped rows or time running down the image are
+/// rejected, since their pixels would no longer line up with the bins and frames. The recorded
+/// scale and frequency range stand in for the flags left out and must match those given; masks
+/// painted from scratch record nothing and take the flags or their defaults.
+fn mask_config(
+    path: &Path,
+    rows: MaskRows,
+    mut config: SpectrogramConfig,
+) -> Result<SpectrogramConfig, FftImageError> {
+    let params = if ImageFormat::from_path(path).ok() == Some(ImageFormat::Png) {
+        // Unreadable files record no parameters either, opening the image reports them
+        read_png_params(path).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let recorded = |keyword: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == keyword)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let flag = if let Some(mode) = recorded("mode").filter(|&mode| mode != "spectrogram") {
+        Some(format!("--mode {mode}"))
+    } else if recorded("orientation").is_some_and(|orientation| orientation != "horizontal") {
+        Some("--orientation vertical".to_string())
+    } else if recorded("axes") == Some("true") {
+        Some("--axes".to_string())
+    } else if recorded("colorbar") == Some("true") {
+        Some("--colorbar".to_string())
+    } else if recorded("layout").is_some_and(|layout| layout != "strip") {
+        Some("--layout wrapped".to_string())
+    } else {
+        None
+    };
+    if let Some(flag) = flag {
+        return Err(FftImageError::MaskLayout(path.to_path_buf(), flag));
+    }
 
-    let img_row_height = h;
-    let row_count: u32 = (total_width / w) as u32 + 1;
+    let scale = |value: &str| FrequencyScale::from_str(value, false).ok();
+    if let Some(scale) = mask_param(path, &params, "--scale", rows.scale, scale)? {
+        config.scale = scale;
+    }
+    let freq = |value: &str| value.parse().ok();
+    if let Some(min_freq) = mask_param(path, &params, "--min-freq", rows.min_freq, freq)? {
+        config.min_freq = min_freq;
+    }
+    // The maximum frequency is recorded after lowering it to the Nyquist frequency
+    let max_freq = rows.max_freq.map(|max_freq| max_freq.min(config.nyquist()));
+    if let Some(max_freq) = mask_param(path, &params, "--max-freq", max_freq, freq)? {
+        config.max_freq = max_freq;
+    }
+    config.clamp_max_freq();
+    Ok(config)
+}
 
-    let img_height = img_row_height * row_count;
+/// Value of `flag` recorded in the mask `params`, or else the one given on the command line,
+/// which must match the recorded one
+fn mask_param<T: PartialEq>(
+    path: &Path,
+    params: &[(String, String)],
+    flag: &'static str,
+    given: Option<T>,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<T>, FftImageError> {
+    let recorded = params
+        .iter()
+        .find(|(keyword, _)| keyword == &flag[2..])
+        .and_then(|(_, value)| Some((value, parse(value)?)));
+    match (given, recorded) {
+        (Some(given), Some((value, recorded))) if given != recorded => Err(
+            FftImageError::MaskFlag(path.to_path_buf(), flag, value.clone()),
+        ),
+        (given, recorded) => Ok(recorded.map(|(_, recorded)| recorded).or(given)),
+    }
+}
 
-    let mut img = ImageBuffer::new(w as u32, img_height);
+fn invert(
+    input: &Path,
+    output: &Path,
+    mask: Option<&Path>,
+    rows: MaskRows,
+    config: SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let mut data = load_matrix(input)?;
+    // Griffin-Lim resynthesizes through the inverse FFT, which puts bin `k` at `k` times the spacing
+    let spacing = data.frequencies.get(1).copied().unwrap_or_default();
+    let tolerance = spacing * 1e-3;
+    if !data
+        .frequencies
+        .iter()
+        .enumerate()
+        .all(|(bin, &freq)| (freq - bin as f32 * spacing).abs() <= tolerance)
+    {
+        return Err(FftImageError::UnevenBins);
+    }
 
-    let freq_min: f32 = 20.0; // Minimum frequency (Hz)
+    if let Some(path) = mask {
+        let config = config
+            .clone()
+            .sample_rate(data.sample_rate)
+            .window(data.window)
+            .hop(data.hop);
+        let config = mask_config(path, rows, config)?.build()?;
 
-    let log_freq_min = freq_min.ln() / LN_10;
-    let log_freq_max = FREQUENCY_MAX.ln() / LN_10;
+        let img = image::open(path)?;
+        let masked = apply_mask(&mut data, &img, &config);
+        info!(
+            "Masked {masked} of {} bins",
+            data.frame_count() * data.frequencies.len()
+        );
+    }
 
-    for sampling_x in 0..total_width {
-        print!("Processing column {} of {}\r", sampling_x, total_width);
-        let sample_end = min((sampling_x + 1) * SAMPLING_WINDOW, sample_count);
-        let sample_start = sampling_x * SAMPLING_WINDOW;
-        let freqs = samples_fft_to_spectrum(
-            &audio_samples[sample_start..sample_end],
-            SAMPLING_RATE,
-            FrequencyLimit::Range(0.0, FREQUENCY_MAX),
-            Some(&scale_to_zero_to_one),
-        )
-        .unwrap();
+    info!("Reconstructing {} frames ...", data.frame_count());
+    let samples = reconstruct(&data, &config);
 
-        let data = freqs.data();
-        let data_size = data.len();
+    info!("Saving audio as {output:?} ...");
+    write_wav(output, &samples, data.sample_rate)
+}
 
-        let mut prev_val: u8 = 0;
+/// Parameters read from `--config`, overridden by the flags given on the command line
+///
+/// Also records in `cli` whether the maximum frequency was asked for, to warn when it is lowered.
+fn preset(cli: &mut RenderArgs, matches: &ArgMatches) -> Result<SpectrogramConfig, FftImageError> {
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let mut config = match &cli.image.config {
+        Some(path) => SpectrogramConfig::from_toml_file(path)?,
+        None => SpectrogramConfig::default(),
+    };
+    cli.image.max_freq_requested = given("max_freq")
+        || cli.image.config.as_deref().is_some_and(|path| {
+            let text = std::fs::read_to_string(path).unwrap_or_default();
+            text.parse::<toml::Table>()
+                .is_ok_and(|table| table.contains_key("max_freq"))
+        });
 
-        let img_x = (sampling_x % w) as u32;
-        let img_row_offset = (sampling_x / w) as u32 * img_row_height;
-        let mut img_row = img_row_offset;
+    if given("window") {
+        config.window = cli.image.window;
+        config.hop = cli.image.window;
+    }
+    if let Some(hop) = cli.image.hop {
+        config.hop = hop;
+    }
+    if cli.image.fft_size.is_some() {
+        config.fft_size = cli.image.fft_size;
+    }
+    if given("window_fn") {
+        config.window_fn = cli.image.window_fn;
+    }
+    if given("transform") {
+        config.transform = cli.image.transform;
+    }
+    if given("bins_per_octave") {
+        config.bins_per_octave = cli.image.bins_per_octave;
+    }
+    if given("min_freq") {
+        config.min_freq = cli.image.min_freq;
+    }
+    if given("max_freq") {
+        config.max_freq = cli.image.max_freq;
+    }
+    if given("mode") {
+        config.mode = cli.image.mode;
+    }
+    if given("height") {
+        config.row_height = cli.image.height;
+    }
+    if let Some(width) = cli.image.width {
+        warn!("--width is deprecated, it sets the row height, use --height instead");
+        config.row_height = width;
+    }
+    if given("waveform_height") {
+        config.waveform_height = cli.image.waveform_height;
+    }
+    if given("mel_filters") {
+        config.mel_filters = cli.image.mel_filters;
+    }
+    if given("num_coeffs") {
+        config.mfcc_coeffs = cli.image.num_coeffs;
+    }
+    if given("layout") {
+        config.layout = cli.image.layout;
+    }
+    if cli.image.wrap_width.is_some() {
+        config.wrap_width = cli.image.wrap_width;
+    }
+    if given("orientation") {
+        config.orientation = cli.image.orientation;
+    }
+    config.axes |= cli.image.axes;
+    config.colorbar |= cli.image.colorbar;
+    config.grid |= cli.image.grid;
+    config.centroid |= cli.image.centroid;
+    config.pitch_track |= cli.image.pitch_track;
+    if given("grid_color") {
+        config.grid_color = cli.image.grid_color;
+    }
+    if given("grid_opacity") {
+        config.grid_opacity = cli.image.grid_opacity;
+    }
+    if given("scale") {
+        config.scale = cli.image.scale;
+    }
+    config.interpolate |= cli.image.interpolate;
+    if given("weighting") {
+        config.weighting = cli.image.weighting;
+    }
+    if given("normalize") {
+        config.normalize = cli.image.normalize;
+    }
+    if given("denoise") {
+        config.denoise = cli.image.denoise;
+    }
+    if given("kernel") {
+        config.kernel = cli.image.kernel;
+    }
+    if given("smooth") {
+        config.smooth = cli.image.smooth;
+    }
+    if cli.image.contrast.is_some() {
+        config.contrast = cli.image.contrast;
+    }
+    if cli.image.time_bins.is_some() {
+        config.time_bins = cli.image.time_bins;
+    }
+    if given("amplitude") {
+        config.amplitude = cli.image.amplitude;
+    }
+    if given("db_floor") {
+        config.db_floor = cli.image.db_floor;
+    }
+    if cli.image.background.is_some() {
+        config.background = cli.image.background;
+    }
+    if cli.image.threshold.is_some() {
+        config.threshold = cli.image.threshold;
+    }
+    if given("gain") {
+        config.gain = cli.image.gain;
+    }
+    if given("gamma") {
+        config.gamma = cli.image.gamma;
+    }
+    if given("colormap") {
+        config.colormap = cli.image.colormap;
+    } else if cli.diff_mode == DiffMode::Signed
+        && config.colormap == Colormap::Gray
+        && config.format == PixelFormat::Rgba
+        && config.bit_depth == BitDepth::Eight
+    {
+        // Tells the signs apart, unlike the default gray
+        config.colormap = Colormap::Coolwarm;
+    }
+    config.invert |= cli.image.invert;
+    if given("format") {
+        config.format = cli.image.format;
+    }
+    if given("bit_depth") {
+        config.bit_depth = cli.image.bit_depth;
+    }
+    if given("png_compression") {
+        config.png_compression = cli.image.png_compression;
+    }
+    if let Some([width, height]) = cli.image.size {
+        let (time, frequency) = match config.orientation {
+            Orientation::Horizontal => (width, height),
+            Orientation::Vertical => (height, width),
+        };
+        config.layout = Layout::Strip;
+        config.time_bins = Some(time as usize);
+        config.row_height = frequency;
+    }
+    Ok(config)
+}
 
-        for sampling_y in 0..data_size {
-            let freq = freqs.data()[sampling_y].0.val();
-            let log_freq = freq.ln() / LN_10;
+fn image_to_audio(
+    input: &Path,
+    output: &Path,
+    duration: Option<f64>,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let img = image::open(input)?;
+    let duration =
+        duration.unwrap_or((img.width() as usize * config.hop) as f64 / config.sample_rate as f64);
 
-            if log_freq < log_freq_min || log_freq > log_freq_max {
-                continue;
-            }
+    info!("Synthesizing {duration:.2} s of audio ...");
+    let samples = sonify(&img, duration, config);
 
-            let img_row_target: u32 = ((log_freq - log_freq_min) / (log_freq_max - log_freq_min)
-                * img_row_height as f32)
-                .round() as u32
-                + img_row_offset;
+    info!("Saving audio as {output:?} ...");
+    write_wav(output, &samples, config.sample_rate)
+}
 
-            while img_row < img_row_target {
-                let pixel = [prev_val; 4];
-                img.put_pixel(img_x, img_row, Rgba(pixel));
-                img_row += 1;
+/// Processes every input, going on past failures and reporting how many failed at the end
+fn run(cli: &RenderArgs, preset: &SpectrogramConfig) -> Result<(), FftImageError> {
+    if let Some(dir) = &cli.out_dir {
+        if !dir.is_dir() {
+            return Err(FftImageError::MissingOutputDirectory(dir.clone()));
+        }
+    }
+    if let Some(path) = &cli.load_matrix {
+        return render_matrix(cli, preset, path);
+    }
+    let inputs = expand_inputs(&cli.file, cli.input.raw)?;
+    // A single input keeps its own error rather than a summary
+    if let [input] = inputs.as_slice() {
+        return process_file(cli, preset, input);
+    }
+    let single_outputs = [
+        ("--output", cli.output.is_some()),
+        ("--json", cli.export.json.is_some()),
+        ("--csv", cli.export.csv.is_some()),
+        ("--npy", cli.export.npy.is_some()),
+        ("--npy-freqs", cli.export.npy_freqs.is_some()),
+        ("--centroid-csv", cli.export.centroid_csv.is_some()),
+        ("--pitch-csv", cli.export.pitch_csv.is_some()),
+        ("--pitch-json", cli.export.pitch_json.is_some()),
+        ("--features", cli.export.features.is_some()),
+        ("--save-matrix", cli.export.save_matrix.is_some()),
+        ("--dump-wav", cli.export.dump_wav.is_some()),
+    ];
+    if let Some((flag, _)) = single_outputs.iter().find(|(_, given)| *given) {
+        return Err(FftImageError::SingleInputFlag(flag));
+    }
+
+    let mut failed = 0;
+    for input in &inputs {
+        if let Err(err) = process_file(cli, preset, input) {
+            error!("{input}: {err}");
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(FftImageError::BatchFailed {
+            failed,
+            total: inputs.len(),
+        });
+    }
+    Ok(())
+}
+
+fn process_file(
+    cli: &RenderArgs,
+    preset: &SpectrogramConfig,
+    input: &str,
+) -> Result<(), FftImageError> {
+    if cli.stereo == Some(Stereo::MidSide) {
+        return process_mid_side(cli, preset, input);
+    }
+    let (img_name, format) = image_target(cli, preset, input)?;
+    let to_stdout = img_name == Path::new("-");
+    check_mode_flags(cli, preset)?;
+    if cli.split_duration.is_some() {
+        let whole_input = [
+            ("-o -", to_stdout),
+            ("--phase", cli.phase),
+            ("--diff", cli.diff.is_some()),
+            ("--json", cli.export.json.is_some()),
+            ("--csv", cli.export.csv.is_some()),
+            ("--npy", cli.export.npy.is_some()),
+            ("--npy-freqs", cli.export.npy_freqs.is_some()),
+            ("--centroid-csv", cli.export.centroid_csv.is_some()),
+            ("--pitch-csv", cli.export.pitch_csv.is_some()),
+            ("--pitch-json", cli.export.pitch_json.is_some()),
+            ("--features", cli.export.features.is_some()),
+            ("--save-matrix", cli.export.save_matrix.is_some()),
+        ];
+        if let Some((flag, _)) = whole_input.iter().find(|(_, given)| *given) {
+            return Err(FftImageError::SplitFlag(flag));
+        }
+    }
+
+    let mut stream = open_input(&cli.input, input, cli.input.hint.clone())?;
+    let input_rate = cli.input.sample_rate.unwrap_or(stream.sample_rate());
+    let sample_rate = cli.input.resample.unwrap_or(input_rate);
+    if sample_rate > input_rate {
+        warn!(
+            "upsampling from {input_rate} Hz to {sample_rate} Hz adds no information above {} Hz",
+            input_rate / 2
+        );
+    }
+    let config = analysis_config(cli, preset.clone().sample_rate(sample_rate))?;
+
+    if cli.dry_run {
+        return dry_run(&cli.input, &config, &mut stream, input_rate);
+    }
+
+    // The input compared against, opened before the analysis so that a mismatch fails early
+    let mut other = None;
+    if let Some(path) = &cli.diff {
+        let other_stream = open_input(&cli.input, path, None)?;
+        let other_rate = cli.input.sample_rate.unwrap_or(other_stream.sample_rate());
+        if other_rate != input_rate && cli.input.resample.is_none() {
+            return Err(FftImageError::SampleRateMismatch(input_rate, other_rate));
+        }
+        other = Some((other_stream, other_rate));
+    }
+
+    // Drawing the waveform, resampling, trimming and the phase image need all samples at once;
+    // otherwise the spectrogram is computed as the input decodes, without holding on to the samples
+    let needs_samples = matches!(config.mode, Mode::Waveform | Mode::Combined)
+        || cli.phase
+        || cli.input.resample.is_some()
+        || cli.input.start.is_some()
+        || cli.input.end.is_some()
+        || cli.input.trim_silence
+        || cli.export.dump_wav.is_some()
+        || cli.split_duration.is_some();
+
+    let mut samples = Vec::new();
+    if needs_samples {
+        samples = read_samples(&cli.input, &mut stream, input_rate, sample_rate)?;
+    }
+    if let Some(path) = &cli.export.dump_wav {
+        write_wav(path, &samples, sample_rate)?;
+    }
+    if let Some(duration) = cli.split_duration {
+        write_split(cli, &config, &samples, &img_name, format, duration)?;
+        warn_truncated(&cli.input, &stream);
+        return Ok(());
+    }
+
+    let img = match config.mode {
+        Mode::Waveform if samples.is_empty() => return Err(FftImageError::EmptyInput),
+        Mode::Waveform => render_waveform(&samples, &config),
+        Mode::Spectrogram | Mode::Combined | Mode::Chroma | Mode::Mfcc => {
+            let data = if needs_samples {
+                compute_spectrogram(&samples, &config)?
+            } else {
+                let mut spectrogram = SpectrogramStream::new(&config);
+                while let Some(chunk) = stream.next_chunk()? {
+                    spectrogram.push(chunk)?;
+                }
+                spectrogram.finish()?
+            };
+            let data = match other {
+                Some((mut other_stream, other_rate)) => {
+                    let other_samples =
+                        read_samples(&cli.input, &mut other_stream, other_rate, sample_rate)?;
+                    let other_data = compute_spectrogram(&other_samples, &config)?;
+                    if other_data.frame_count() != data.frame_count() {
+                        warn!(
+                            "the inputs differ in length, comparing their first {} frames",
+                            data.frame_count().min(other_data.frame_count())
+                        );
+                    }
+                    diff_spectrograms(&data, &other_data, cli.diff_mode)
+                }
+                None => data,
+            };
+            match write_spectrogram(cli, input, &samples, &data, &config, &img_name, format)? {
+                Some(img) => img,
+                None => {
+                    warn_truncated(&cli.input, &stream);
+                    return Ok(());
+                }
             }
+        }
+    };
+    warn_truncated(&cli.input, &stream);
+    write_image(&img, &img_name, format, &config)
+}
+
+/// Draws the spectrogram matrix at `path`, written by `--save-matrix`, with the drawing parameters
+/// of `preset`, skipping the decoding and the analysis
+fn render_matrix(
+    cli: &RenderArgs,
+    preset: &SpectrogramConfig,
+    path: &Path,
+) -> Result<(), FftImageError> {
+    let input = path.to_string_lossy();
+    let (img_name, format) = image_target(cli, preset, &input)?;
+    check_mode_flags(cli, preset)?;
+
+    let data = load_matrix(path)?;
+    debug!(
+        "Loaded {} frames of {} bins at {} Hz",
+        data.frame_count(),
+        data.frequencies.len(),
+        data.sample_rate
+    );
+    let mut config = preset
+        .clone()
+        .sample_rate(data.sample_rate)
+        .window(data.window)
+        .hop(data.hop);
+    match *data.frequencies.as_slice() {
+        // Constant-Q bins start above DC and lie a fixed ratio apart
+        [lowest, next, ..] if lowest > 0.0 => {
+            config.transform = Transform::Cqt;
+            config.min_freq = lowest;
+            config.bins_per_octave = (1.0 / (next / lowest).log2()).round() as usize;
+        }
+        // FFT bins lie the sample rate over the FFT length apart
+        _ => {
+            config.fft_size = data
+                .frequencies
+                .get(1)
+                .map(|&spacing| (data.sample_rate as f32 / spacing).round() as usize)
+                .filter(|&fft_len| fft_len != data.window);
+        }
+    }
+    let config = analysis_config(cli, config)?;
+
+    match write_spectrogram(cli, &input, &[], &data, &config, &img_name, format)? {
+        Some(img) => write_image(&img, &img_name, format, &config),
+        None => Ok(()),
+    }
+}
+
+/// Path and format of the image drawn for `input`, checked before any work is done that saving it
+/// would waste
+fn image_target(
+    cli: &RenderArgs,
+    preset: &SpectrogramConfig,
+    input: &str,
+) -> Result<(PathBuf, ImageFormat), FftImageError> {
+    let img_name = output_path(input, cli.output.clone(), cli.out_dir.as_deref());
+    if let Some(parent) = img_name.parent() {
+        if !parent.as_os_str().is_empty() && !parent.is_dir() {
+            return Err(FftImageError::MissingOutputDirectory(parent.to_path_buf()));
+        }
+    }
+    // `-o -` streams the image to stdout as PNG, the only place the format can't come from
+    let format = if img_name == Path::new("-") {
+        ImageFormat::Png
+    } else {
+        output_format(&img_name, preset.bit_depth)?
+    };
+    // OpenEXR holds the magnitudes alone, with nothing drawn over or beside them
+    if format == ImageFormat::OpenExr {
+        let drawn = [
+            ("--axes", preset.axes),
+            ("--colorbar", preset.colorbar),
+            ("--grid", preset.grid),
+            ("--centroid", preset.centroid),
+            ("--pitch-track", preset.pitch_track),
+            ("--phase", cli.phase),
+        ];
+        if let Some((flag, _)) = drawn.iter().find(|(_, given)| *given) {
+            return Err(FftImageError::ExrFlag(flag));
+        }
+        if preset.mode != Mode::Spectrogram {
+            return Err(FftImageError::ModeFlag("OpenEXR output", preset.mode));
+        }
+    }
+    Ok((img_name, format))
+}
 
-            let val = freqs.data()[sampling_y].1.val();
-            prev_val = (val * 255.0) as u8;
+/// Rejects the flags given that do not apply in `mode`, or that place frames a hop apart when they
+/// are resampled to `--time-bins`
+fn check_mode_flags(cli: &RenderArgs, preset: &SpectrogramConfig) -> Result<(), FftImageError> {
+    if preset.time_bins.is_some() {
+        let hop_spaced = [
+            ("--phase", cli.phase),
+            ("--json", cli.export.json.is_some()),
+            ("--save-matrix", cli.export.save_matrix.is_some()),
+            ("--dump-wav", cli.export.dump_wav.is_some()),
+            ("--centroid-csv", cli.export.centroid_csv.is_some()),
+            ("--pitch-csv", cli.export.pitch_csv.is_some()),
+            ("--pitch-json", cli.export.pitch_json.is_some()),
+            ("--features", cli.export.features.is_some()),
+        ];
+        if let Some((flag, _)) = hop_spaced.iter().find(|(_, given)| *given) {
+            return Err(FftImageError::TimeBinsFlag(flag));
         }
+    }
 
-        while img_row < img_height {
-            let pixel = [prev_val; 4];
-            img.put_pixel(img_x, img_row, Rgba(pixel));
-            img_row += 1;
+    let mode = preset.mode;
+    let mode_flags = [
+        ("--phase", cli.phase, Mode::Waveform),
+        ("--json", cli.export.json.is_some(), Mode::Waveform),
+        ("--csv", cli.export.csv.is_some(), Mode::Waveform),
+        ("--npy", cli.export.npy.is_some(), Mode::Waveform),
+        (
+            "--npy-freqs",
+            cli.export.npy_freqs.is_some(),
+            Mode::Waveform,
+        ),
+        (
+            "--centroid-csv",
+            cli.export.centroid_csv.is_some(),
+            Mode::Waveform,
+        ),
+        (
+            "--pitch-csv",
+            cli.export.pitch_csv.is_some(),
+            Mode::Waveform,
+        ),
+        (
+            "--pitch-json",
+            cli.export.pitch_json.is_some(),
+            Mode::Waveform,
+        ),
+        ("--features", cli.export.features.is_some(), Mode::Waveform),
+        (
+            "--save-matrix",
+            cli.export.save_matrix.is_some(),
+            Mode::Waveform,
+        ),
+        ("diff", cli.diff.is_some(), Mode::Waveform),
+        ("diff", cli.diff.is_some(), Mode::Combined),
+        ("--load-matrix", cli.load_matrix.is_some(), Mode::Waveform),
+        ("--load-matrix", cli.load_matrix.is_some(), Mode::Combined),
+        ("--phase", cli.phase, Mode::Chroma),
+        ("diff", cli.diff.is_some(), Mode::Chroma),
+        ("--phase", cli.phase, Mode::Mfcc),
+        ("--json", cli.export.json.is_some(), Mode::Mfcc),
+        ("--npy-freqs", cli.export.npy_freqs.is_some(), Mode::Mfcc),
+        (
+            "--save-matrix",
+            cli.export.save_matrix.is_some(),
+            Mode::Mfcc,
+        ),
+        ("diff", cli.diff.is_some(), Mode::Mfcc),
+    ];
+    let misused = mode_flags
+        .iter()
+        .find(|(_, given, flag_mode)| *given && *flag_mode == mode);
+    if let Some((flag, _, mode)) = misused {
+        return Err(FftImageError::ModeFlag(flag, *mode));
+    }
+    Ok(())
+}
+
+/// `config` for the input at its sample rate, widened to `--full-spectrum` and limited to the
+/// Nyquist frequency, and checked
+fn analysis_config(
+    cli: &RenderArgs,
+    mut config: SpectrogramConfig,
+) -> Result<SpectrogramConfig, FftImageError> {
+    if cli.image.full_spectrum {
+        config.span_full_spectrum();
+    }
+    // Scaling every column to its loudest bin would flatten c0, the overall level of the frame
+    if config.mode == Mode::Mfcc && config.normalize == Normalize::Column {
+        config.normalize = Normalize::None;
+    }
+    // The waveform has no frequency axis to clamp, and the default is lowered without a warning
+    if let Some(requested) = config.clamp_max_freq() {
+        if config.mode != Mode::Waveform && cli.image.max_freq_requested {
+            warn!(
+                "requested max frequency {requested} Hz exceeds the Nyquist frequency, using {} Hz",
+                config.max_freq
+            );
         }
     }
+    Ok(config.build()?)
+}
 
-    let img_name = format!("{}.png", cli.file);
-    println!("\nSaving image as {img_name:?} ...");
-    img.save(img_name).unwrap();
+/// Writes the exports of `data`, the spectrogram of `input`, and its phase image, returning the
+/// image to save unless `format` is OpenEXR, which is written here
+fn write_spectrogram(
+    cli: &RenderArgs,
+    input: &str,
+    samples: &[f32],
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+    img_name: &Path,
+    format: ImageFormat,
+) -> Result<Option<DynamicImage>, FftImageError> {
+    // In MFCC mode the CSV and NumPy exports hold the coefficients instead of the spectrum
+    let coeffs = (config.mode == Mode::Mfcc).then(|| mfcc(data, config));
+    if let Some(path) = &cli.export.csv {
+        match &coeffs {
+            Some(coeffs) => write_mfcc_csv(path, coeffs)?,
+            None => write_csv(path, data)?,
+        }
+    }
+    if let Some(path) = &cli.export.json {
+        write_json(path, data, config)?;
+    }
+    if let Some(path) = &cli.export.npy {
+        match &coeffs {
+            Some(coeffs) => write_mfcc_npy(path, coeffs)?,
+            None => write_npy(path, data)?,
+        }
+    }
+    if let Some(path) = &cli.export.npy_freqs {
+        write_npy_freqs(path, data)?;
+    }
+    if let Some(path) = &cli.export.centroid_csv {
+        write_centroid_csv(path, data, config)?;
+    }
+    if let Some(path) = &cli.export.pitch_csv {
+        write_pitch_csv(path, data, config)?;
+    }
+    if let Some(path) = &cli.export.pitch_json {
+        write_pitch_json(path, data, config)?;
+    }
+    if let Some(path) = &cli.export.features {
+        write_features_csv(path, data, config)?;
+    }
+    if let Some(path) = &cli.export.save_matrix {
+        save_matrix(path, data)?;
+    }
+
+    if cli.phase {
+        let phase_data = compute_phase_spectrogram(samples, config)?;
+        let phase_img = render_phase(&phase_data, config);
+
+        // Without an output file to sit next to, the phase image takes the default output
+        // name
+        let base = if img_name == Path::new("-") {
+            output_path(input, None, cli.out_dir.as_deref())
+        } else {
+            img_name.to_path_buf()
+        };
+        let extension = base.extension().unwrap_or_default().to_string_lossy();
+        let phase_name = base.with_extension(format!("phase.{extension}"));
+        info!("Saving phase image as {phase_name:?} ...");
+        save_image(
+            &DynamicImage::ImageRgba8(phase_img),
+            &phase_name,
+            format,
+            config,
+        )?;
+    }
+
+    if format == ImageFormat::OpenExr {
+        info!("Saving magnitudes as {img_name:?} ...");
+        write_exr(img_name, data, config)?;
+        return Ok(None);
+    }
+
+    Ok(Some(draw(cli, samples, data, coeffs.as_deref(), config)))
+}
+
+/// Saves `img` to `img_name`, or writes it to stdout as PNG when that is `-`
+fn write_image(
+    img: &DynamicImage,
+    img_name: &Path,
+    format: ImageFormat,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    if img_name == Path::new("-") {
+        info!("Writing image to stdout ...");
+        let mut bytes = Vec::new();
+        write_png(img, &mut bytes, config)?;
+        io::stdout().lock().write_all(&bytes)?;
+    } else {
+        info!("Saving image as {img_name:?} ...");
+        save_image(img, img_name, format, config)?;
+    }
+    Ok(())
+}
+
+/// Draws the mid and side signals of `input` each to its own image, named after the output with
+/// `.mid` or `.side` before the extension
+fn process_mid_side(
+    cli: &RenderArgs,
+    preset: &SpectrogramConfig,
+    input: &str,
+) -> Result<(), FftImageError> {
+    let single_outputs = [
+        ("-o -", cli.output.as_deref() == Some(Path::new("-"))),
+        ("--json", cli.export.json.is_some()),
+        ("--csv", cli.export.csv.is_some()),
+        ("--npy", cli.export.npy.is_some()),
+        ("--npy-freqs", cli.export.npy_freqs.is_some()),
+        ("--centroid-csv", cli.export.centroid_csv.is_some()),
+        ("--pitch-csv", cli.export.pitch_csv.is_some()),
+        ("--pitch-json", cli.export.pitch_json.is_some()),
+        ("--features", cli.export.features.is_some()),
+        ("--save-matrix", cli.export.save_matrix.is_some()),
+        ("--dump-wav", cli.export.dump_wav.is_some()),
+    ];
+    if let Some((flag, _)) = single_outputs.iter().find(|(_, given)| *given) {
+        return Err(FftImageError::StereoFlag(flag));
+    }
+
+    let base = output_path(input, cli.output.clone(), cli.out_dir.as_deref());
+    let extension = base.extension().unwrap_or_default().to_string_lossy();
+    for (channel, suffix) in [
+        (ChannelSelection::Mid, "mid"),
+        (ChannelSelection::Side, "side"),
+    ] {
+        let cli = RenderArgs {
+            input: InputArgs {
+                channel,
+                ..cli.input.clone()
+            },
+            stereo: None,
+            output: Some(base.with_extension(format!("{suffix}.{extension}"))),
+            ..cli.clone()
+        };
+        process_file(&cli, preset, input)?;
+    }
+    Ok(())
+}
+
+/// Draws the image of `data`, the spectrogram of `samples` (which are only needed in combined
+/// mode), in the mode of `config`
+fn draw(
+    cli: &RenderArgs,
+    samples: &[f32],
+    data: &SpectrogramData,
+    coeffs: Option<&[Vec<f32>]>,
+    config: &SpectrogramConfig,
+) -> DynamicImage {
+    if config.mode == Mode::Combined {
+        render_combined(samples, data, config)
+    } else if config.mode == Mode::Chroma {
+        render_chroma(&chromagram(data, config), config)
+    } else if let Some(coeffs) = coeffs {
+        render_mfcc(coeffs, config)
+    } else if cli.diff.is_some() && cli.diff_mode == DiffMode::Signed {
+        if config.colorbar {
+            warn!("the colorbar is not drawn on signed differences");
+        }
+        render_signed(data, config)
+    } else {
+        render_image(data, config)
+    }
+}
+
+/// Draws every input of `cli` in the strip layout and tiles the images into a contact sheet of
+/// `columns` thumbnails of `thumb_size` per row, saved to `img_name`
+///
+/// Inputs that fail are logged and left out of the sheet, which is still written.
+fn write_contact_sheet(
+    cli: &RenderArgs,
+    preset: &SpectrogramConfig,
+    img_name: &Path,
+    columns: usize,
+    thumb_size: [u32; 2],
+) -> Result<(), FftImageError> {
+    let format = output_format(img_name, BitDepth::Eight)?;
+    if format == ImageFormat::OpenExr {
+        return Err(FftImageError::ExrFlag("contact-sheet"));
+    }
+    let preset = SpectrogramConfig {
+        layout: Layout::Strip,
+        ..preset.clone()
+    };
+    check_mode_flags(cli, &preset)?;
+
+    let inputs = expand_inputs(&cli.file, cli.input.raw)?;
+    let mut thumbnails = Vec::with_capacity(inputs.len());
+    for input in &inputs {
+        info!("Drawing {input} ...");
+        match draw_input(cli, &preset, input) {
+            Ok(img) => {
+                let name = Path::new(input)
+                    .file_name()
+                    .map_or_else(|| input.clone(), |name| name.to_string_lossy().into_owned());
+                thumbnails.push((name, img));
+            }
+            Err(err) => error!("{input}: {err}"),
+        }
+    }
+
+    let failed = inputs.len() - thumbnails.len();
+    if failed < inputs.len() {
+        let sheet = DynamicImage::ImageRgba8(contact_sheet(&thumbnails, columns, thumb_size));
+        info!("Saving contact sheet as {img_name:?} ...");
+        save_image(&sheet, img_name, format, &preset)?;
+    }
+    if failed > 0 {
+        return Err(FftImageError::BatchFailed {
+            failed,
+            total: inputs.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Decodes and draws `input` with the parameters of `preset` at its sample rate
+fn draw_input(
+    cli: &RenderArgs,
+    preset: &SpectrogramConfig,
+    input: &str,
+) -> Result<DynamicImage, FftImageError> {
+    let mut stream = open_input(&cli.input, input, cli.input.hint.clone())?;
+    let input_rate = cli.input.sample_rate.unwrap_or(stream.sample_rate());
+    let sample_rate = cli.input.resample.unwrap_or(input_rate);
+    let config = analysis_config(cli, preset.clone().sample_rate(sample_rate))?;
+
+    let samples = read_samples(&cli.input, &mut stream, input_rate, sample_rate)?;
+    warn_truncated(&cli.input, &stream);
+    if config.mode == Mode::Waveform {
+        if samples.is_empty() {
+            return Err(FftImageError::EmptyInput);
+        }
+        return Ok(render_waveform(&samples, &config));
+    }
+    let data = compute_spectrogram(&samples, &config)?;
+    let coeffs = (config.mode == Mode::Mfcc).then(|| mfcc(&data, &config));
+    Ok(draw(cli, &samples, &data, coeffs.as_deref(), &config))
+}
+
+/// Fraction of `--split-duration` each image of a split input runs on into the next
+const SPLIT_OVERLAP: f64 = 0.05;
+
+/// Draws `samples` as the sequence of images `img_name` numbered `.000`, `.001`, ... before its
+/// extension, each covering `duration` seconds plus the [`SPLIT_OVERLAP`] and labeled with the
+/// time of its first sample
+fn write_split(
+    cli: &RenderArgs,
+    config: &SpectrogramConfig,
+    samples: &[f32],
+    img_name: &Path,
+    format: ImageFormat,
+    duration: f64,
+) -> Result<(), FftImageError> {
+    if samples.is_empty() {
+        return Err(FftImageError::EmptyInput);
+    }
+    let sample_rate = config.sample_rate as f64;
+    let step = ((duration * sample_rate) as usize).max(1);
+    let overlap = (duration * SPLIT_OVERLAP * sample_rate) as usize;
+    let extension = img_name.extension().unwrap_or_default().to_string_lossy();
+
+    for (index, start) in (0..samples.len()).step_by(step).enumerate() {
+        let segment = &samples[start..(start + step + overlap).min(samples.len())];
+        let config = config
+            .clone()
+            .time_offset((start as f64 / sample_rate) as f32);
+        let name = img_name.with_extension(format!("{index:03}.{extension}"));
+
+        let img = if config.mode == Mode::Waveform {
+            render_waveform(segment, &config)
+        } else {
+            let data = compute_spectrogram(segment, &config)?;
+            if format == ImageFormat::OpenExr {
+                info!("Saving magnitudes as {name:?} ...");
+                write_exr(&name, &data, &config)?;
+                continue;
+            }
+            let coeffs = (config.mode == Mode::Mfcc).then(|| mfcc(&data, &config));
+            draw(cli, segment, &data, coeffs.as_deref(), &config)
+        };
+        info!("Saving image as {name:?} ...");
+        save_image(&img, &name, format, &config)?;
+    }
+    Ok(())
 }
@@ -0,0 +1,129 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+use crate::error::FftImageError;
+use crate::spectrogram::SpectrogramData;
+
+const MAGIC: &[u8; 4] = b"FFTM";
+const VERSION: u32 = 1;
+/// Bytes of the magic and the six header fields
+const HEADER_LEN: u64 = 28;
+
+/// Writes `data` to `path` in the spectrogram matrix format
+///
+/// The file starts with the magic bytes `FFTM` followed by little-endian `u32` fields: format
+/// version, sample rate, window, hop, bin count and frame count. The bin center frequencies come
+/// next, then the magnitudes frame by frame, all as little-endian `f32`.
+pub fn save_matrix(path: &Path, data: &SpectrogramData) -> Result<(), FftImageError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    writer.write_all(MAGIC)?;
+    for field in [
+        VERSION,
+        data.sample_rate,
+        data.window as u32,
+        data.hop as u32,
+        data.frequencies.len() as u32,
+        data.frames.len() as u32,
+    ] {
+        writer.write_all(&field.to_le_bytes())?;
+    }
+
+    for value in data.frequencies.iter().chain(data.frames.iter().flatten()) {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a spectrogram matrix written by [`save_matrix`]
+///
+/// The header is checked before any data is read: the sample rate and hop must be positive, the
+/// window a power of two of at least 2, and the bin and frame counts must match the file length.
+pub fn load_matrix(path: &Path) -> Result<SpectrogramData, FftImageError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 4];
+    read_exact(&mut reader, &mut magic)?;
+    if &magic != MAGIC {
+        return Err(FftImageError::InvalidMatrix(
+            "not a spectrogram matrix file".to_string(),
+        ));
+    }
+
+    let version = read_u32(&mut reader)?;
+    if version != VERSION {
+        return Err(FftImageError::InvalidMatrix(format!(
+            "unsupported format version {version}, expected {VERSION}"
+        )));
+    }
+
+    let sample_rate = read_u32(&mut reader)?;
+    let window = read_u32(&mut reader)? as usize;
+    let hop = read_u32(&mut reader)? as usize;
+    let bins = read_u32(&mut reader)? as usize;
+    let frame_count = read_u32(&mut reader)? as usize;
+
+    if sample_rate == 0 {
+        return Err(FftImageError::InvalidMatrix("sample rate is 0".to_string()));
+    }
+    if window < 2 || !window.is_power_of_two() {
+        return Err(FftImageError::InvalidMatrix(format!(
+            "window {window} is not a power of two of at least 2"
+        )));
+    }
+    if hop == 0 {
+        return Err(FftImageError::InvalidMatrix("hop is 0".to_string()));
+    }
+
+    // The frequencies and every frame hold `bins` values, so the header fixes the file length
+    // and is checked against it before anything is allocated
+    let remaining = reader.get_ref().metadata()?.len() - HEADER_LEN;
+    let expected = (frame_count as u64 + 1)
+        .checked_mul(bins as u64 * 4)
+        .filter(|&expected| expected == remaining);
+    if expected.is_none() {
+        return Err(FftImageError::InvalidMatrix(format!(
+            "{bins} bins in {frame_count} frames do not match the {remaining} bytes of data"
+        )));
+    }
+
+    let frequencies = read_f32s(&mut reader, bins)?;
+    let frames = (0..frame_count)
+        .map(|_| read_f32s(&mut reader, bins))
+        .collect::<Result<_, _>>()?;
+
+    Ok(SpectrogramData {
+        sample_rate,
+        window,
+        hop,
+        frequencies,
+        frames,
+    })
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, FftImageError> {
+    let mut bytes = [0; 4];
+    read_exact(reader, &mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32s(reader: &mut impl Read, count: usize) -> Result<Vec<f32>, FftImageError> {
+    let mut bytes = vec![0; count * 4];
+    read_exact(reader, &mut bytes)?;
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect())
+}
+
+fn read_exact(reader: &mut impl Read, bytes: &mut [u8]) -> Result<(), FftImageError> {
+    reader.read_exact(bytes).map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => {
+            FftImageError::InvalidMatrix("the file is truncated".to_string())
+        }
+        _ => err.into(),
+    })
+}
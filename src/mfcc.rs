@@ -0,0 +1,132 @@
+use std::f32::consts::PI;
+
+use image::DynamicImage;
+use rayon::prelude::*;
+
+use crate::config::SpectrogramConfig;
+use crate::render::render_stripes;
+use crate::scale::{hz_to_mel, mel_to_hz};
+use crate::spectrogram::SpectrogramData;
+
+/// Added to the filter energies before taking their logarithm, so that silent bands stay finite
+const LOG_FLOOR: f32 = 1e-10;
+
+/// Weights of `filters` triangular filters over the bins centered on `frequencies` (Hz)
+///
+/// The filters are spaced evenly on the mel scale between `min_freq` and `max_freq`, each rising
+/// from the center of the previous filter to its own and falling to the center of the next.
+pub fn mel_filterbank(
+    frequencies: &[f32],
+    filters: usize,
+    min_freq: f32,
+    max_freq: f32,
+) -> Vec<Vec<f32>> {
+    let (mel_min, mel_max) = (hz_to_mel(min_freq), hz_to_mel(max_freq));
+    let edges: Vec<f32> = (0..filters + 2)
+        .map(|i| mel_to_hz(mel_min + (mel_max - mel_min) * i as f32 / (filters + 1) as f32))
+        .collect();
+
+    edges
+        .windows(3)
+        .map(|edge| {
+            let (low, center, high) = (edge[0], edge[1], edge[2]);
+            frequencies
+                .iter()
+                .map(|&freq| {
+                    if freq <= low || freq >= high {
+                        0.0
+                    } else if freq <= center {
+                        (freq - low) / (center - low)
+                    } else {
+                        (high - freq) / (high - center)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Mel-frequency cepstral coefficients of every frame of `data`
+///
+/// The power spectrum of each frame is summed through [`SpectrogramConfig::mel_filters`] filters
+/// of [`mel_filterbank`] between [`SpectrogramConfig::min_freq`] and
+/// [`SpectrogramConfig::max_freq`], and the first [`SpectrogramConfig::mfcc_coeffs`] coefficients
+/// of the orthonormal DCT-II of the log energies are kept, the first being the overall level.
+///
+/// `data` should hold magnitudes that are not normalized per column, as with
+/// [`Normalize::None`](crate::Normalize::None), or every frame ends up at the same level.
+pub fn mfcc(data: &SpectrogramData, config: &SpectrogramConfig) -> Vec<Vec<f32>> {
+    let filterbank = mel_filterbank(
+        &data.frequencies,
+        config.mel_filters,
+        config.min_freq,
+        config.max_freq,
+    );
+    let bands = filterbank.len();
+    let dct: Vec<Vec<f32>> = (0..config.mfcc_coeffs)
+        .map(|k| {
+            let scale = if k == 0 { 1.0 } else { 2.0 };
+            let scale = (scale / bands as f32).sqrt();
+            (0..bands)
+                .map(|m| scale * (PI * k as f32 * (m as f32 + 0.5) / bands as f32).cos())
+                .collect()
+        })
+        .collect();
+
+    data.frames
+        .par_iter()
+        .map(|frame| {
+            let log_energies: Vec<f32> = filterbank
+                .iter()
+                .map(|weights| {
+                    let energy: f32 = weights
+                        .iter()
+                        .zip(frame)
+                        .map(|(weight, magnitude)| weight * magnitude * magnitude)
+                        .sum();
+                    (energy + LOG_FLOOR).ln()
+                })
+                .collect();
+            dct.iter()
+                .map(|basis| basis.iter().zip(&log_energies).map(|(b, e)| b * e).sum())
+                .collect()
+        })
+        .collect()
+}
+
+/// Draws the coefficients from [`mfcc`] in the layout, orientation and pixel format of
+/// [`render_image`](crate::render_image), each row band split into one stripe per coefficient with
+/// the first at the bottom
+///
+/// Every coefficient is scaled to `0.0..=1.0` over its own range across the frames, since the
+/// first is far larger than the others.
+pub fn render_mfcc(coeffs: &[Vec<f32>], config: &SpectrogramConfig) -> DynamicImage {
+    let count = coeffs.first().map_or(0, Vec::len);
+    let ranges: Vec<(f32, f32)> = (0..count)
+        .map(|k| {
+            coeffs
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), frame| {
+                    (min.min(frame[k]), max.max(frame[k]))
+                })
+        })
+        .collect();
+
+    let levels: Vec<Vec<f32>> = coeffs
+        .iter()
+        .map(|frame| {
+            frame
+                .iter()
+                .zip(&ranges)
+                .map(|(&value, &(min, max))| {
+                    if max > min {
+                        (value - min) / (max - min)
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    render_stripes(&levels, config)
+}
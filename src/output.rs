@@ -0,0 +1,234 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use exr::prelude::{Image, SpecificChannels, WritableImage};
+use image::{ColorType, DynamicImage, ImageFormat, Luma};
+use serde::Deserialize;
+
+use crate::config::SpectrogramConfig;
+use crate::denoise::Denoise;
+use crate::error::FftImageError;
+use crate::render::{render_pixels, Amplitude, BitDepth};
+use crate::spectrogram::{SpectrogramData, Transform};
+
+/// Formats the image can be written as
+const SUPPORTED_FORMATS: [ImageFormat; 6] = [
+    ImageFormat::Png,
+    ImageFormat::Tiff,
+    ImageFormat::Bmp,
+    ImageFormat::Jpeg,
+    ImageFormat::WebP,
+    ImageFormat::OpenExr,
+];
+
+/// Picks the image format from the extension of `path`, checking that it can hold pixels of
+/// `bit_depth`
+///
+/// PNG and TIFF carry every layout; BMP, JPEG and WebP are limited to 8 bits per channel. OpenEXR
+/// holds the raw magnitudes written by [`write_exr`], whatever the bit depth.
+pub fn output_format(path: &Path, bit_depth: BitDepth) -> Result<ImageFormat, FftImageError> {
+    let format = ImageFormat::from_path(path)
+        .ok()
+        .filter(|format| SUPPORTED_FORMATS.contains(format))
+        .ok_or_else(|| FftImageError::UnsupportedFormat(path.to_path_buf()))?;
+
+    let sixteen_bit = matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Tiff | ImageFormat::OpenExr
+    );
+    if bit_depth == BitDepth::Sixteen && !sixteen_bit {
+        return Err(FftImageError::FormatBitDepth(format));
+    }
+    Ok(format)
+}
+
+/// Writes the magnitudes of `data` to `path` as an OpenEXR image with a single 32-bit float `Y`
+/// channel
+///
+/// Bins are placed where [`render_image`](crate::render_image) draws them, but every pixel holds
+/// the stored magnitude itself instead of a quantized intensity, so the amplitude scale, gain,
+/// gamma, threshold and colormap do not apply.
+pub fn write_exr(
+    path: &Path,
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let img = render_pixels(data, config, |magnitude| Luma([magnitude]));
+    let size = (img.width() as usize, img.height() as usize);
+
+    let channels = SpecificChannels::build()
+        .with_channel("Y")
+        .with_pixel_fn(|position| (img.get_pixel(position.x() as u32, position.y() as u32)[0],));
+    Image::from_channels(size, channels).write().to_file(path)?;
+    Ok(())
+}
+
+/// Name with which `value` is given on the command line
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map_or_else(String::new, |value| value.get_name().to_string())
+}
+
+/// Parameters of the analysis and rendering recorded in the PNG text chunks, as keyword and value
+pub fn png_params(config: &SpectrogramConfig) -> Vec<(&'static str, String)> {
+    let mut params = vec![
+        ("sample-rate", config.sample_rate.to_string()),
+        ("window", config.window.to_string()),
+        ("fft-size", config.fft_len().to_string()),
+        ("hop", config.hop.to_string()),
+        ("window-fn", value_name(config.window_fn)),
+        ("transform", value_name(config.transform)),
+    ];
+    if config.transform == Transform::Cqt {
+        params.push(("bins-per-octave", config.bins_per_octave.to_string()));
+    }
+    params.extend([
+        ("mode", value_name(config.mode)),
+        ("min-freq", config.min_freq.to_string()),
+        ("max-freq", config.max_freq.to_string()),
+        ("scale", value_name(config.scale)),
+        ("weighting", value_name(config.weighting)),
+        ("normalize", value_name(config.normalize)),
+        ("denoise", value_name(config.denoise)),
+    ]);
+    if config.denoise == Denoise::Median {
+        let [frames, bins] = config.kernel;
+        params.push(("kernel", format!("{frames}x{bins}")));
+    }
+    params.push(("smooth", config.smooth.to_string()));
+    if let Some(time_bins) = config.time_bins {
+        params.push(("time-bins", time_bins.to_string()));
+    }
+    if let Some([low, high]) = config.contrast {
+        params.push(("contrast", format!("{low},{high}")));
+    }
+    params.push(("amplitude", value_name(config.amplitude)));
+    if config.amplitude == Amplitude::Db {
+        params.push(("db-floor", config.db_floor.to_string()));
+    }
+    params.extend([
+        ("gain", config.gain.to_string()),
+        ("gamma", config.gamma.to_string()),
+        ("colormap", value_name(config.colormap)),
+        ("layout", value_name(config.layout)),
+        ("orientation", value_name(config.orientation)),
+        ("height", config.row_height.to_string()),
+        ("axes", config.axes.to_string()),
+        ("colorbar", config.colorbar.to_string()),
+    ]);
+    params
+}
+
+/// Trade-off between the size of PNG files and the time spent encoding them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    /// The `fdeflate` quick compressor, every row filtered by its left neighbor, for batches where
+    /// encoding dominates
+    Fast,
+    /// Deflate at the default zlib level 6, every row filtered by its left neighbor
+    Default,
+    /// The strongest deflate level with the filter picked row by row, for the smallest files
+    Best,
+}
+
+/// Keyword of the text chunk naming the program that wrote a PNG
+const SOFTWARE_KEYWORD: &str = "Software";
+
+/// Encodes `img` as PNG to `writer` at [`SpectrogramConfig::png_compression`], recording the
+/// [`png_params`] of `config` in `tEXt` chunks
+pub fn write_png(
+    img: &DynamicImage,
+    writer: impl Write,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    let (color, depth, bytes) = match img.color() {
+        ColorType::L8 => (
+            png::ColorType::Grayscale,
+            png::BitDepth::Eight,
+            img.as_bytes().to_vec(),
+        ),
+        ColorType::L16 => {
+            let samples = img.to_luma16().into_raw();
+            let bytes = samples
+                .iter()
+                .flat_map(|sample| sample.to_be_bytes())
+                .collect();
+            (png::ColorType::Grayscale, png::BitDepth::Sixteen, bytes)
+        }
+        _ => (
+            png::ColorType::Rgba,
+            png::BitDepth::Eight,
+            img.to_rgba8().into_raw(),
+        ),
+    };
+
+    let mut encoder = png::Encoder::new(writer, img.width(), img.height());
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    match config.png_compression {
+        PngCompression::Fast => {
+            encoder.set_compression(png::Compression::Fast);
+            encoder.set_filter(png::FilterType::Sub);
+        }
+        PngCompression::Default => {
+            encoder.set_compression(png::Compression::Default);
+            encoder.set_filter(png::FilterType::Sub);
+        }
+        PngCompression::Best => {
+            encoder.set_compression(png::Compression::Best);
+            encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+        }
+    }
+    let software = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+    encoder.add_text_chunk(SOFTWARE_KEYWORD.to_string(), software.to_string())?;
+    for (keyword, value) in png_params(config) {
+        encoder.add_text_chunk(keyword.to_string(), value)?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&bytes)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Saves `img` to `path` as `format`, through [`write_png`] for PNG so that the parameters of
+/// `config` are recorded along with it
+pub fn save_image(
+    img: &DynamicImage,
+    path: &Path,
+    format: ImageFormat,
+    config: &SpectrogramConfig,
+) -> Result<(), FftImageError> {
+    if format != ImageFormat::Png {
+        img.save_with_format(path, format)?;
+        return Ok(());
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_png(img, &mut writer, config)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads back the text chunks of the PNG at `path`, as keyword and value in the order they were
+/// written
+pub fn read_png_params(path: &Path) -> Result<Vec<(String, String)>, FftImageError> {
+    let file = File::open(path).map_err(|err| FftImageError::Open(path.to_path_buf(), err))?;
+    let reader = png::Decoder::new(file)
+        .read_info()
+        .map_err(|err| FftImageError::PngRead(path.to_path_buf(), err))?;
+    let info = reader.info();
+
+    let latin1 = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()));
+    let utf8 = info
+        .utf8_text
+        .iter()
+        .filter_map(|chunk| Some((chunk.keyword.clone(), chunk.get_text().ok()?)));
+    Ok(latin1.chain(utf8).collect())
+}
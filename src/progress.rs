@@ -0,0 +1,32 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::Level;
+
+/// Draws to stderr, next to the log output and clear of an image written to stdout, unless
+/// info-level logging is disabled
+fn draw_target() -> ProgressDrawTarget {
+    if log::log_enabled!(Level::Info) {
+        ProgressDrawTarget::stderr()
+    } else {
+        ProgressDrawTarget::hidden()
+    }
+}
+
+/// Counter of decoded samples, shown while the length of the input is still unknown
+pub(crate) fn decode_spinner() -> ProgressBar {
+    let spinner = ProgressBar::with_draw_target(None, draw_target());
+    spinner.set_style(
+        ProgressStyle::with_template("{spinner} Decoded {pos} samples [{elapsed}]")
+            .expect("progress template is valid"),
+    );
+    spinner
+}
+
+/// Bar over the `len` analysis windows of the spectrogram
+pub(crate) fn column_bar(len: usize) -> ProgressBar {
+    let bar = ProgressBar::with_draw_target(Some(len as u64), draw_target());
+    bar.set_style(
+        ProgressStyle::with_template("Processing columns {bar:40} {pos}/{len} (ETA {eta})")
+            .expect("progress template is valid"),
+    );
+    bar
+}
@@ -0,0 +1,178 @@
+use std::io::Read;
+
+use clap::ValueEnum;
+use symphonia::core::{
+    audio::Channels,
+    codecs::{self, CodecParameters, CodecType},
+    errors::{Error, Result},
+    formats::{Cue, FormatOptions, FormatReader, Packet, SeekMode, SeekTo, SeekedTo, Track},
+    io::{MediaSource, MediaSourceStream},
+    meta::{Metadata, MetadataLog},
+};
+
+use crate::error::FftImageError;
+
+/// Frames read into each packet of a raw input
+const FRAMES_PER_PACKET: usize = 4096;
+
+/// Sample encoding of a headerless PCM input
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RawFormat {
+    U8,
+    I8,
+    I16le,
+    I16be,
+    I24le,
+    I24be,
+    I32le,
+    I32be,
+    F32le,
+    F32be,
+    F64le,
+    F64be,
+}
+
+impl RawFormat {
+    /// Number of bytes each sample takes
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            RawFormat::U8 | RawFormat::I8 => 1,
+            RawFormat::I16le | RawFormat::I16be => 2,
+            RawFormat::I24le | RawFormat::I24be => 3,
+            RawFormat::I32le | RawFormat::I32be | RawFormat::F32le | RawFormat::F32be => 4,
+            RawFormat::F64le | RawFormat::F64be => 8,
+        }
+    }
+
+    fn codec(self) -> CodecType {
+        match self {
+            RawFormat::U8 => codecs::CODEC_TYPE_PCM_U8,
+            RawFormat::I8 => codecs::CODEC_TYPE_PCM_S8,
+            RawFormat::I16le => codecs::CODEC_TYPE_PCM_S16LE,
+            RawFormat::I16be => codecs::CODEC_TYPE_PCM_S16BE,
+            RawFormat::I24le => codecs::CODEC_TYPE_PCM_S24LE,
+            RawFormat::I24be => codecs::CODEC_TYPE_PCM_S24BE,
+            RawFormat::I32le => codecs::CODEC_TYPE_PCM_S32LE,
+            RawFormat::I32be => codecs::CODEC_TYPE_PCM_S32BE,
+            RawFormat::F32le => codecs::CODEC_TYPE_PCM_F32LE,
+            RawFormat::F32be => codecs::CODEC_TYPE_PCM_F32BE,
+            RawFormat::F64le => codecs::CODEC_TYPE_PCM_F64LE,
+            RawFormat::F64be => codecs::CODEC_TYPE_PCM_F64BE,
+        }
+    }
+}
+
+/// Layout of a headerless PCM input, whose samples are interleaved frame by frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawPcm {
+    /// Sample rate (Hz)
+    pub sample_rate: u32,
+    /// Number of interleaved channels
+    pub channels: usize,
+    pub format: RawFormat,
+}
+
+impl RawPcm {
+    /// Number of bytes each frame of interleaved samples takes
+    pub fn frame_size(&self) -> usize {
+        self.channels * self.format.bytes_per_sample()
+    }
+}
+
+/// Format reader handing out the bytes of a headerless PCM input in packets of whole frames, to be
+/// decoded by Symphonia's PCM decoder
+pub(crate) struct RawReader {
+    source: MediaSourceStream,
+    tracks: Vec<Track>,
+    frame_size: usize,
+    /// Number of frames handed out so far
+    position: u64,
+    cues: Vec<Cue>,
+    metadata: MetadataLog,
+}
+
+impl RawReader {
+    /// Reads `source` as samples laid out as in `raw`, checking that its length is a whole number
+    /// of frames when it is known
+    pub(crate) fn new(
+        source: MediaSourceStream,
+        raw: RawPcm,
+    ) -> std::result::Result<Self, FftImageError> {
+        let channels = u32::try_from(raw.channels)
+            .ok()
+            .filter(|&channels| channels > 0 && channels < 32)
+            .and_then(|channels| Channels::from_bits((1 << channels) - 1))
+            .ok_or(FftImageError::RawChannels(raw.channels))?;
+
+        let frame_size = raw.frame_size();
+        let len = source.byte_len();
+        if let Some(len) = len {
+            if len % frame_size as u64 != 0 {
+                return Err(FftImageError::RawLength { len, frame_size });
+            }
+        }
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(raw.format.codec())
+            .with_sample_rate(raw.sample_rate)
+            .with_channels(channels)
+            .with_bits_per_sample(8 * raw.format.bytes_per_sample() as u32)
+            .with_max_frames_per_packet(FRAMES_PER_PACKET as u64);
+        if let Some(len) = len {
+            params.with_n_frames(len / frame_size as u64);
+        }
+
+        Ok(RawReader {
+            source,
+            tracks: vec![Track::new(0, params)],
+            frame_size,
+            position: 0,
+            cues: Vec::new(),
+            metadata: MetadataLog::default(),
+        })
+    }
+}
+
+impl FormatReader for RawReader {
+    fn try_new(_source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
+        Err(Error::Unsupported("raw: the sample layout must be given"))
+    }
+
+    fn cues(&self) -> &[Cue] {
+        &self.cues
+    }
+
+    fn metadata(&mut self) -> Metadata<'_> {
+        self.metadata.metadata()
+    }
+
+    fn seek(&mut self, _mode: SeekMode, _to: SeekTo) -> Result<SeekedTo> {
+        Err(Error::Unsupported("raw: seeking"))
+    }
+
+    fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    fn next_packet(&mut self) -> Result<Packet> {
+        let mut data = Vec::with_capacity(FRAMES_PER_PACKET * self.frame_size);
+        (&mut self.source)
+            .take((FRAMES_PER_PACKET * self.frame_size) as u64)
+            .read_to_end(&mut data)?;
+        // A trailing partial frame of an input of unknown length is dropped
+        data.truncate(data.len() - data.len() % self.frame_size);
+        if data.is_empty() {
+            return Err(Error::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        let frames = (data.len() / self.frame_size) as u64;
+        let packet = Packet::new_from_boxed_slice(0, self.position, frames, data.into());
+        self.position += frames;
+        Ok(packet)
+    }
+
+    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+        self.source
+    }
+}
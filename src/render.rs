@@ -0,0 +1,553 @@
+use crate::axes::{
+    add_axes, add_colorbar, add_contour, add_grid, annotation_colors, axes_margins,
+    colorbar_margin, PITCH_COLOR,
+};
+use crate::config::SpectrogramConfig;
+use crate::features::{dominant_pitch, spectral_centroid};
+use crate::spectrogram::SpectrogramData;
+
+use clap::ValueEnum;
+use image::{DynamicImage, ImageBuffer, Luma, Pixel, Rgba};
+use rayon::prelude::*;
+use serde::Deserialize;
+
+/// Scale applied to normalized spectrum magnitudes before they are drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Amplitude {
+    Linear,
+    Db,
+}
+
+impl Amplitude {
+    /// Maps a normalized magnitude in `0.0..=1.0` to an intensity in `0.0..=1.0`
+    pub fn intensity(self, magnitude: f32, db_floor: f32) -> f32 {
+        match self {
+            Amplitude::Linear => magnitude,
+            Amplitude::Db => {
+                let db = 20.0 * magnitude.log10();
+                if db.is_nan() || db <= db_floor {
+                    0.0
+                } else {
+                    (db - db_floor) / -db_floor
+                }
+            }
+        }
+    }
+}
+
+/// Largest power of two that is less than or equal to `x`, or 0 when `x` is 0
+pub fn nearest_power_of_two_below(x: u32) -> u32 {
+    x.checked_ilog2().map_or(0, |log| 1 << log)
+}
+
+/// Bits per channel of the output image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+pub enum BitDepth {
+    /// 8-bit color, drawn with the configured colormap
+    #[value(name = "8")]
+    #[serde(rename = "8")]
+    Eight,
+    /// 16-bit grayscale
+    #[value(name = "16")]
+    #[serde(rename = "16")]
+    Sixteen,
+}
+
+/// Whether a stored magnitude lies below [`SpectrogramConfig::threshold`]
+fn below_threshold(magnitude: f32, config: &SpectrogramConfig) -> bool {
+    let Some(threshold) = config.threshold else {
+        return false;
+    };
+    let level = match config.amplitude {
+        Amplitude::Linear => magnitude,
+        Amplitude::Db => 20.0 * magnitude.log10(),
+    };
+    level.is_nan() || level < threshold
+}
+
+/// Turns a stored magnitude into a pixel intensity in `0.0..=1.0`
+pub(crate) fn intensity(magnitude: f32, config: &SpectrogramConfig) -> f32 {
+    if below_threshold(magnitude, config) {
+        return if config.invert { 1.0 } else { 0.0 };
+    }
+
+    let intensity = config
+        .amplitude
+        .intensity(magnitude * config.gain, config.db_floor);
+    let intensity = intensity.clamp(0.0, 1.0).powf(1.0 / config.gamma);
+    if config.invert {
+        1.0 - intensity
+    } else {
+        intensity
+    }
+}
+
+/// Pixel type of an output image that [`SpectrogramConfig::background`] can be drawn in
+pub(crate) trait BackgroundPixel: Pixel {
+    fn from_rgba(color: [u8; 4]) -> Self;
+}
+
+impl BackgroundPixel for Rgba<u8> {
+    fn from_rgba(color: [u8; 4]) -> Self {
+        Rgba(color)
+    }
+}
+
+impl BackgroundPixel for Luma<u8> {
+    fn from_rgba(color: [u8; 4]) -> Self {
+        Rgba(color).to_luma()
+    }
+}
+
+impl BackgroundPixel for Luma<f32> {
+    fn from_rgba(color: [u8; 4]) -> Self {
+        let Luma([luma]) = Rgba(color).to_luma();
+        Luma([f32::from(luma) / 255.0])
+    }
+}
+
+impl BackgroundPixel for Luma<u16> {
+    fn from_rgba(color: [u8; 4]) -> Self {
+        let Luma([luma]) = Rgba(color).to_luma();
+        Luma([u16::from(luma) * 257])
+    }
+}
+
+/// Turns a stored magnitude into a pixel with `to_pixel`, or into the background color when it lies
+/// below the threshold and a background is set
+pub(crate) fn magnitude_pixel<P: BackgroundPixel>(
+    magnitude: f32,
+    config: &SpectrogramConfig,
+    to_pixel: impl Fn(f32) -> P,
+) -> P {
+    match config.background {
+        Some(color) if below_threshold(magnitude, config) => P::from_rgba(color),
+        _ => to_pixel(intensity(magnitude, config)),
+    }
+}
+
+/// What each time column of the image shows
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Magnitude spectrum of the analysis window
+    Spectrogram,
+    /// Smallest and largest sample under the column, see
+    /// [`render_waveform`](crate::render_waveform)
+    Waveform,
+    /// The waveform in a panel above the spectrogram of every row, see
+    /// [`render_combined`](crate::render_combined)
+    Combined,
+    /// Magnitudes summed into the twelve pitch classes across octaves, see
+    /// [`render_chroma`](crate::render_chroma)
+    Chroma,
+    /// Mel-frequency cepstral coefficients, see [`render_mfcc`](crate::render_mfcc)
+    Mfcc,
+}
+
+/// Arrangement of the time columns in the image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Rows of time columns stacked top to bottom
+    ///
+    /// Rows are [`SpectrogramConfig::wrap_width`] columns wide, or by default a quarter of the
+    /// largest power of two below the frame count.
+    Wrapped,
+    /// A single row with one column per frame, time running left to right
+    Strip,
+}
+
+/// Direction of the time axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    /// Time runs left to right, frequency top to bottom within each row
+    Horizontal,
+    /// Time runs top to bottom, frequency left to right within each column, the transpose of
+    /// [`Orientation::Horizontal`]
+    Vertical,
+}
+
+/// Channel layout of the output image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// Four channels, colored with the configured colormap
+    Rgba,
+    /// A single intensity channel
+    Gray,
+}
+
+/// Arrangement of the frames of a spectrogram into rows of the image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageLayout {
+    /// Number of frames, one time column each
+    pub total_width: usize,
+    /// Number of time columns per row
+    pub w: usize,
+    /// Number of rows
+    pub row_count: u32,
+    /// Extent of the stacked rows, along the frequency axis
+    pub img_height: u32,
+}
+
+impl ImageLayout {
+    /// Lays out `frame_count` frames with the layout and row height of `config`
+    pub fn new(frame_count: usize, config: &SpectrogramConfig) -> Self {
+        let total_width = frame_count;
+        let (w, row_count) = match (config.layout, config.wrap_width) {
+            (Layout::Wrapped, Some(wrap_width)) => {
+                let w = wrap_width.min(total_width.max(1));
+                (w, total_width.div_ceil(w) as u32)
+            }
+            (Layout::Wrapped, None) => {
+                // Find the nearest power of two to the total width
+                let nearest_w = nearest_power_of_two_below(total_width as u32);
+                // Inputs of fewer than four frames are too short to wrap and fill a single row
+                let w = match nearest_w as usize / 4 {
+                    0 => total_width.max(1),
+                    w => w, // width of a single row
+                };
+                (w, total_width.div_ceil(w) as u32)
+            }
+            (Layout::Strip, _) => (total_width, 1),
+        };
+
+        ImageLayout {
+            total_width,
+            w,
+            row_count,
+            img_height: config.band_height() * row_count,
+        }
+    }
+
+    /// Width and height of the image drawn by [`render_image`], including the margins of the axes
+    /// and colorbar
+    pub fn dimensions(&self, config: &SpectrogramConfig) -> (u32, u32) {
+        let (mut width, mut height) = match config.orientation {
+            Orientation::Horizontal => (self.w as u32, self.img_height),
+            Orientation::Vertical => (self.img_height, self.w as u32),
+        };
+        if config.axes {
+            let (freq_margin, ruler) = axes_margins(config, self.total_width);
+            let bands = self.row_count * (config.row_height + ruler);
+            (width, height) = match config.orientation {
+                Orientation::Horizontal => (freq_margin + width, bands),
+                Orientation::Vertical => (bands, freq_margin + height),
+            };
+        }
+        if config.colorbar {
+            width += colorbar_margin(config);
+        }
+        (width, height)
+    }
+}
+
+/// Draws the spectrogram with the pixel format and bit depth selected in `config`
+/// 16-bit output is always grayscale. With [`SpectrogramConfig::centroid`] and
+/// [`SpectrogramConfig::pitch_track`], the spectral centroid and the dominant pitch of each frame
+/// are drawn over the spectrogram. With [`SpectrogramConfig::axes`], a labeled frequency axis
+/// and time ruler are added in margins, and with [`SpectrogramConfig::colorbar`] a legend on the right.
+pub fn render_image(data: &SpectrogramData, config: &SpectrogramConfig) -> DynamicImage {
+    let mut img = match (config.format, config.bit_depth) {
+        (PixelFormat::Rgba, BitDepth::Eight) => DynamicImage::ImageRgba8(render(data, config)),
+        (PixelFormat::Gray, BitDepth::Eight) => {
+            DynamicImage::ImageLuma8(render_pixels(data, config, |magnitude| {
+                magnitude_pixel(magnitude, config, |level| Luma([(level * 255.0) as u8]))
+            }))
+        }
+        (_, BitDepth::Sixteen) => {
+            DynamicImage::ImageLuma16(render_pixels(data, config, |magnitude| {
+                magnitude_pixel(magnitude, config, |level| Luma([(level * 65535.0) as u16]))
+            }))
+        }
+    };
+
+    if config.grid {
+        add_grid(&mut img, config, data.frame_count());
+    }
+    if config.centroid {
+        let (label, _) = annotation_colors(config);
+        add_contour(&mut img, &spectral_centroid(data), label, config);
+    }
+    if config.pitch_track {
+        add_contour(&mut img, &dominant_pitch(data), PITCH_COLOR, config);
+    }
+    let img = if config.axes {
+        add_axes(img, config, data.frame_count())
+    } else {
+        img
+    };
+    if config.colorbar {
+        add_colorbar(img, config)
+    } else {
+        img
+    }
+}
+
+/// Draws the spectrogram as stacked rows of time columns, low frequencies at the top of each row
+pub fn render(
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    render_pixels(data, config, |magnitude| {
+        magnitude_pixel(magnitude, config, |level| config.colormap.map_color(level))
+    })
+}
+
+/// Draws a phase spectrogram from [`compute_phase_spectrogram`](crate::compute_phase_spectrogram)
+/// with the same layout and frequency mapping as [`render`]
+pub fn render_phase(
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    render_pixels(data, config, |phase| config.colormap.map_color(phase))
+}
+
+/// Draws signed values such as those of a [`DiffMode::Signed`](crate::DiffMode::Signed) difference
+/// with the same layout and frequency mapping as [`render`]
+///
+/// Zero sits in the middle of the colormap and `-1.0` and `1.0` at its ends, after multiplying by
+/// [`SpectrogramConfig::gain`]. A diverging colormap such as [`Colormap::Coolwarm`] tells the signs
+/// apart.
+///
+/// [`Colormap::Coolwarm`]: crate::Colormap::Coolwarm
+///
+/// The grid and axes are added as in [`render_image`], but not the colorbar, whose labels assume
+/// magnitudes.
+pub fn render_signed(data: &SpectrogramData, config: &SpectrogramConfig) -> DynamicImage {
+    let level = |value: f32| {
+        let level = ((value * config.gain).clamp(-1.0, 1.0) + 1.0) / 2.0;
+        if config.invert {
+            1.0 - level
+        } else {
+            level
+        }
+    };
+
+    let mut img = match (config.format, config.bit_depth) {
+        (PixelFormat::Rgba, BitDepth::Eight) => {
+            DynamicImage::ImageRgba8(render_pixels(data, config, |value| {
+                config.colormap.map_color(level(value))
+            }))
+        }
+        (PixelFormat::Gray, BitDepth::Eight) => {
+            DynamicImage::ImageLuma8(render_pixels(data, config, |value| {
+                Luma([(level(value) * 255.0) as u8])
+            }))
+        }
+        (_, BitDepth::Sixteen) => DynamicImage::ImageLuma16(render_pixels(data, config, |value| {
+            Luma([(level(value) * 65535.0) as u16])
+        })),
+    };
+
+    if config.grid {
+        add_grid(&mut img, config, data.frame_count());
+    }
+    if config.axes {
+        add_axes(img, config, data.frame_count())
+    } else {
+        img
+    }
+}
+
+/// Draws `data`, turning each stored value into a pixel with `to_pixel`
+///
+/// Each frame is drawn into its own column buffer in parallel, then the columns are copied into the
+/// image in frame order by [`composite`].
+pub(crate) fn render_pixels<P>(
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+    to_pixel: impl Fn(f32) -> P + Sync,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: BackgroundPixel + Send + Sync,
+{
+    // Rows hold the spectrogram alone, without the waveform panel of combined mode
+    let layout_config = SpectrogramConfig {
+        mode: Mode::Spectrogram,
+        ..config.clone()
+    };
+    composite(&spectrogram_columns(data, config, to_pixel), &layout_config)
+}
+
+/// One row band per frame of `data`, along with the pixel of the last bin, or the background color
+/// when set, which fills the image below the band of the last frame in each image column
+///
+/// Bins falling on the same row are averaged into it. The rows between two bins hold the value of
+/// the lower bin, or with [`SpectrogramConfig::interpolate`] a linear ramp from it to the value of
+/// the upper bin.
+pub(crate) fn spectrogram_columns<P>(
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+    to_pixel: impl Fn(f32) -> P + Sync,
+) -> Vec<(Vec<P>, P)>
+where
+    P: BackgroundPixel + Send + Sync,
+{
+    let img_row_height = config.row_height;
+    let background = config.background.map(P::from_rgba);
+
+    let freq_min = config.min_freq;
+    let freq_max = config.max_freq;
+
+    data.frames
+        .par_iter()
+        .map(|frame| {
+            let mut column = Vec::with_capacity(img_row_height as usize);
+            let mut prev_pixel = to_pixel(0.0);
+            // Row and value of the previous row drawn from the bins
+            let mut prev_bin = None;
+            let mut draw_bin = |row: u32, value: f32| {
+                match prev_bin {
+                    Some((prev_row, prev_value)) if config.interpolate => {
+                        let span = (row - prev_row) as f32;
+                        for img_row in column.len() as u32..row {
+                            let t = (img_row - prev_row) as f32 / span;
+                            column.push(to_pixel(prev_value + (value - prev_value) * t));
+                        }
+                    }
+                    _ => column.resize(row as usize, prev_pixel),
+                }
+                prev_pixel = to_pixel(value);
+                prev_bin = Some((row, value));
+            };
+
+            // Row, sum and count of the consecutive bins falling on the same row so far
+            let mut pending: Option<(u32, f32, u32)> = None;
+            for (&freq, &value) in data.frequencies.iter().zip(frame) {
+                let Some(row) = config
+                    .scale
+                    .row_for(freq, freq_min, freq_max, img_row_height)
+                else {
+                    continue;
+                };
+
+                match &mut pending {
+                    Some((pending_row, sum, count)) if *pending_row == row => {
+                        *sum += value;
+                        *count += 1;
+                    }
+                    _ => {
+                        if let Some((pending_row, sum, count)) = pending {
+                            draw_bin(pending_row, sum / count as f32);
+                        }
+                        pending = Some((row, value, 1));
+                    }
+                }
+            }
+            if let Some((pending_row, sum, count)) = pending {
+                draw_bin(pending_row, sum / count as f32);
+            }
+
+            column.resize(img_row_height as usize, prev_pixel);
+            (column, background.unwrap_or(prev_pixel))
+        })
+        .collect()
+}
+
+/// Draws every frame of `frames` as a row band split into one stripe per value, the first value at
+/// the bottom, in the layout, orientation and pixel format of [`render_image`]
+pub(crate) fn render_stripes<V>(frames: &[V], config: &SpectrogramConfig) -> DynamicImage
+where
+    V: AsRef<[f32]> + Sync,
+{
+    match (config.format, config.bit_depth) {
+        (PixelFormat::Rgba, BitDepth::Eight) => {
+            let columns = stripe_columns(frames, config, |level| config.colormap.map_color(level));
+            DynamicImage::ImageRgba8(composite(&columns, config))
+        }
+        (PixelFormat::Gray, BitDepth::Eight) => {
+            let columns = stripe_columns(frames, config, |level| Luma([(level * 255.0) as u8]));
+            DynamicImage::ImageLuma8(composite(&columns, config))
+        }
+        (_, BitDepth::Sixteen) => {
+            let columns = stripe_columns(frames, config, |level| Luma([(level * 65535.0) as u16]));
+            DynamicImage::ImageLuma16(composite(&columns, config))
+        }
+    }
+}
+
+/// One band of [`SpectrogramConfig::row_height`] rows per frame, along with the background pixel
+/// that fills the image below the band of the last frame, `to_pixel` turning an intensity into a
+/// pixel
+fn stripe_columns<V, P>(
+    frames: &[V],
+    config: &SpectrogramConfig,
+    to_pixel: impl Fn(f32) -> P + Sync,
+) -> Vec<(Vec<P>, P)>
+where
+    V: AsRef<[f32]> + Sync,
+    P: BackgroundPixel + Send + Sync,
+{
+    let h = config.row_height as usize;
+    let background = config
+        .background
+        .map(P::from_rgba)
+        .unwrap_or_else(|| to_pixel(intensity(0.0, config)));
+
+    frames
+        .par_iter()
+        .map(|frame| {
+            let values = frame.as_ref();
+            let column: Vec<P> = (0..h)
+                .map(|row| {
+                    let stripe = values.len() - 1 - row * values.len() / h;
+                    to_pixel(intensity(values[stripe], config))
+                })
+                .collect();
+            (column, background)
+        })
+        .collect()
+}
+
+/// Copies one row band per frame into an image with the layout of `config`, along with the pixel
+/// that fills the image below the band of the last frame in each image column
+pub(crate) fn composite<P: Pixel>(
+    columns: &[(Vec<P>, P)],
+    config: &SpectrogramConfig,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let ImageLayout {
+        total_width,
+        w,
+        img_height,
+        ..
+    } = ImageLayout::new(columns.len(), config);
+    let img_row_height = config.band_height();
+
+    // Every band lies within the image, since the last frame starts band `total_width / w`, so the
+    // pixels can be written into the raw buffer directly
+    let channels = usize::from(P::CHANNEL_COUNT);
+    let (img_width, img_length) = match config.orientation {
+        Orientation::Horizontal => (w as u32, img_height),
+        Orientation::Vertical => (img_height, w as u32),
+    };
+    let stride = img_width as usize * channels;
+    let mut img = ImageBuffer::new(img_width, img_length);
+    let buf: &mut [P::Subpixel] = &mut img;
+
+    for (sampling_x, (column, last)) in columns.iter().enumerate() {
+        let img_x = sampling_x % w;
+        let img_row_offset = (sampling_x / w) * img_row_height as usize;
+        // Rows below the band stay visible only when no later frame lands in the same image column
+        let img_row_end = if sampling_x + w >= total_width {
+            img_height as usize
+        } else {
+            img_row_offset + img_row_height as usize
+        };
+
+        for img_row in img_row_offset..img_row_end {
+            let pixel = column.get(img_row - img_row_offset).unwrap_or(last);
+            let (x, y) = match config.orientation {
+                Orientation::Horizontal => (img_x, img_row),
+                Orientation::Vertical => (img_row, img_x),
+            };
+            let start = y * stride + x * channels;
+            buf[start..start + channels].copy_from_slice(pixel.channels());
+        }
+    }
+
+    img
+}
@@ -0,0 +1,47 @@
+use std::f64::consts::PI;
+
+/// Number of zero crossings of the sinc kernel on each side of the interpolated point
+const KERNEL_HALF_WIDTH: f64 = 16.0;
+
+/// Converts `samples` from `from_rate` to `to_rate` with a Hann-windowed sinc interpolator
+///
+/// When downsampling, the kernel is widened so that its cutoff sits at the new Nyquist frequency,
+/// which filters out content that would otherwise alias.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = to_rate as f64 / from_rate as f64;
+    let cutoff = ratio.min(1.0);
+    let half_width = KERNEL_HALF_WIDTH / cutoff;
+    let out_len = (samples.len() as f64 * ratio).round() as usize;
+
+    (0..out_len)
+        .map(|i| {
+            let center = i as f64 / ratio;
+            let first = (center - half_width).ceil().max(0.0) as usize;
+            let last = ((center + half_width).floor() as usize).min(samples.len() - 1);
+
+            let mut sum = 0.0;
+            for (n, &sample) in samples.iter().enumerate().take(last + 1).skip(first) {
+                let t = n as f64 - center;
+                sum += sample as f64 * cutoff * sinc(t * cutoff) * hann(t / half_width);
+            }
+            sum as f32
+        })
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Hann window over `-1.0..=1.0`
+fn hann(x: f64) -> f64 {
+    0.5 + 0.5 * (PI * x).cos()
+}
@@ -0,0 +1,75 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::f32::consts::LN_10;
+
+/// Mapping of frequency onto the vertical image axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrequencyScale {
+    Log,
+    Mel,
+    /// Critical bands of hearing, see [`hz_to_bark`]
+    Bark,
+    Linear,
+}
+
+impl FrequencyScale {
+    /// Converts a frequency in Hz to a position on this scale
+    pub fn to_axis(self, freq: f32) -> f32 {
+        match self {
+            FrequencyScale::Log => freq.ln() / LN_10,
+            FrequencyScale::Mel => hz_to_mel(freq),
+            FrequencyScale::Bark => hz_to_bark(freq),
+            FrequencyScale::Linear => freq,
+        }
+    }
+
+    /// Range of axis positions spanned by the image rows
+    /// The linear scale always starts at 0 Hz so bins sit at `freq / freq_max` of the height
+    pub fn axis_range(self, freq_min: f32, freq_max: f32) -> (f32, f32) {
+        match self {
+            FrequencyScale::Linear => (0.0, freq_max),
+            _ => (self.to_axis(freq_min), self.to_axis(freq_max)),
+        }
+    }
+
+    /// Row, counted from the top of an image row `row_height` pixels tall, at which `freq` is drawn
+    /// Returns `None` for frequencies outside `freq_min..=freq_max`, including the 0 Hz (DC) bin
+    pub fn row_for(self, freq: f32, freq_min: f32, freq_max: f32, row_height: u32) -> Option<u32> {
+        // The DC bin has no position on a logarithmic axis, `ln(0)` is `-inf`
+        if freq <= 0.0 || freq < freq_min || freq > freq_max {
+            return None;
+        }
+
+        let (axis_min, axis_max) = self.axis_range(freq_min, freq_max);
+        let position = (self.to_axis(freq) - axis_min) / (axis_max - axis_min);
+        if !position.is_finite() {
+            return None;
+        }
+
+        Some((position * row_height as f32).round() as u32)
+    }
+}
+
+/// Converts a frequency in Hz to mels
+pub fn hz_to_mel(freq: f32) -> f32 {
+    2595.0 * (1.0 + freq / 700.0).log10()
+}
+
+/// Converts a pitch in mels to Hz, the inverse of [`hz_to_mel`]
+pub fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Converts a frequency in Hz to Barks, after Traunmüller (1990)
+///
+/// One Bark spans a critical band of hearing: about 100 Hz wide up to 500 Hz, then roughly a
+/// fifth of its center frequency.
+pub fn hz_to_bark(freq: f32) -> f32 {
+    26.81 * freq / (1960.0 + freq) - 0.53
+}
+
+/// Converts a critical band rate in Barks to Hz, the inverse of [`hz_to_bark`]
+pub fn bark_to_hz(bark: f32) -> f32 {
+    1960.0 * (bark + 0.53) / (26.28 - bark)
+}
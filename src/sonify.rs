@@ -0,0 +1,103 @@
+use image::DynamicImage;
+
+use crate::config::SpectrogramConfig;
+use crate::griffin_lim::reconstruct;
+use crate::spectrogram::SpectrogramData;
+
+/// Reads `img` as a spectrogram of `frames` frames, the inverse of drawing one with
+/// [`Layout::Strip`](crate::Layout::Strip)
+///
+/// Columns are time and rows frequency, the lowest frequency at the top, placed with the same
+/// [`FrequencyScale::row_for`](crate::FrequencyScale::row_for) mapping as the renderer. Each FFT
+/// bin of `config` takes the luminance of the pixel on its row as magnitude, and each frame the
+/// column nearest to it in time, so the image is stretched or squeezed to `frames` columns.
+pub fn image_to_spectrogram(
+    img: &DynamicImage,
+    frames: usize,
+    config: &SpectrogramConfig,
+) -> SpectrogramData {
+    let img = img.to_luma32f();
+    let (width, height) = img.dimensions();
+
+    let window = config.window;
+    let bin_width = config.sample_rate as f32 / window as f32;
+    let (frequencies, rows): (Vec<f32>, Vec<u32>) = (1..=window / 2)
+        .filter_map(|bin| {
+            let freq = bin as f32 * bin_width;
+            let row = config
+                .scale
+                .row_for(freq, config.min_freq, config.max_freq, height)?;
+            Some((freq, row.min(height - 1)))
+        })
+        .unzip();
+
+    let frames = (0..frames)
+        .map(|frame| {
+            let x = (frame * width as usize / frames.max(1)) as u32;
+            rows.iter().map(|&row| img.get_pixel(x, row)[0]).collect()
+        })
+        .collect();
+
+    SpectrogramData {
+        sample_rate: config.sample_rate,
+        window,
+        hop: config.hop,
+        frequencies,
+        frames,
+    }
+}
+
+/// Silences the bins of `data` that fall on pure black pixels of `mask`, returning how many were
+///
+/// The mask is read like [`image_to_spectrogram`] reads an image: columns are time, stretched or
+/// squeezed onto the frames of `data`, and rows frequency, the lowest at the top, placed with the
+/// [`FrequencyScale::row_for`](crate::FrequencyScale::row_for) mapping of `config`. A spectrogram
+/// drawn with [`Layout::Strip`](crate::Layout::Strip) and the same frequency range and scale can so
+/// be painted over in black to mark the bins to remove. Bins outside the frequency range are left
+/// as they are.
+pub fn apply_mask(
+    data: &mut SpectrogramData,
+    mask: &DynamicImage,
+    config: &SpectrogramConfig,
+) -> usize {
+    let mask = mask.to_luma8();
+    let (width, height) = mask.dimensions();
+    if width == 0 || height == 0 {
+        return 0;
+    }
+
+    let rows: Vec<Option<u32>> = data
+        .frequencies
+        .iter()
+        .map(|&freq| {
+            let row = config
+                .scale
+                .row_for(freq, config.min_freq, config.max_freq, height)?;
+            Some(row.min(height - 1))
+        })
+        .collect();
+
+    let frames = data.frames.len();
+    let mut masked = 0;
+    for (frame, magnitudes) in data.frames.iter_mut().enumerate() {
+        let x = (frame * width as usize / frames) as u32;
+        for (magnitude, row) in magnitudes.iter_mut().zip(&rows) {
+            if row.is_some_and(|row| mask.get_pixel(x, row).0 == [0]) {
+                *magnitude = 0.0;
+                masked += 1;
+            }
+        }
+    }
+    masked
+}
+
+/// Synthesizes `duration` seconds of audio whose spectrogram looks like `img`, as read by
+/// [`image_to_spectrogram`]
+///
+/// Phases are estimated with [`reconstruct`], whose overlap-add resynthesis puts frames `hop`
+/// samples apart. The output is peak-normalized.
+pub fn sonify(img: &DynamicImage, duration: f64, config: &SpectrogramConfig) -> Vec<f32> {
+    let samples = (duration * config.sample_rate as f64) as usize;
+    let frames = (samples.saturating_sub(config.window) / config.hop + 1).max(1);
+    reconstruct(&image_to_spectrogram(img, frames, config), config)
+}
@@ -0,0 +1,787 @@
+use crate::config::SpectrogramConfig;
+use crate::cqt::{cqt_frequencies, CqtKernels};
+use crate::decode::SampleStream;
+use crate::denoise::{median_filter, Denoise};
+use crate::error::FftImageError;
+use crate::progress::column_bar;
+use crate::render::Amplitude;
+use crate::weighting::Weighting;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+/// Magnitude spectra of consecutive analysis windows
+pub struct SpectrogramData {
+    pub sample_rate: u32,
+    pub window: usize,
+    pub hop: usize,
+    /// Center frequency of each bin (Hz)
+    pub frequencies: Vec<f32>,
+    /// One entry per analysis window, each holding one magnitude per bin scaled as configured by
+    /// [`Normalize`]
+    pub frames: Vec<Vec<f32>>,
+}
+
+impl SpectrogramData {
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+/// How magnitudes are scaled before drawing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalize {
+    /// Each column (analysis frame) is divided by its own loudest bin
+    ///
+    /// Every column uses the full intensity range, so two recordings that differ only in loudness
+    /// produce identical images.
+    #[value(alias = "frame")]
+    #[serde(alias = "frame")]
+    Column,
+    /// Every frame is divided by the loudest bin of the whole input, preserving relative dynamics
+    ///
+    /// This needs a second pass over the raw spectra of all frames once the overall maximum is
+    /// known, so they are all held in memory unscaled.
+    Global,
+    /// Every magnitude is divided by the FFT length `N`
+    ///
+    /// Like [`Normalize::None`] the level of a tone does not depend on the rest of the input, but it
+    /// no longer grows with the window size.
+    Length,
+    /// Every magnitude is divided by the square root of the FFT length `N`
+    #[value(name = "sqrt-length")]
+    #[serde(rename = "sqrt-length")]
+    SqrtLength,
+    /// Raw FFT magnitudes, unscaled
+    None,
+}
+
+/// Transform turning each window of samples into the magnitudes of its frequency bins
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transform {
+    /// Fourier transform, whose bins are evenly spaced in frequency
+    Fft,
+    /// Constant-Q transform, with [`SpectrogramConfig::bins_per_octave`] bins to every octave
+    ///
+    /// Each bin is as narrow relative to its frequency as the others, matching musical pitch: low
+    /// notes get bins as far apart as high ones on a logarithmic axis, rather than sharing a few.
+    Cqt,
+}
+
+/// Scaling applied to the magnitudes of each analysis frame, see [`compute_spectrogram_with`]
+///
+/// [`Normalize`] implements the built-in choices, and any `Fn(&mut [f32], usize) + Sync` closure
+/// taking the magnitudes of one frame and the FFT length can be used as well.
+pub trait AmplitudeScaler: Sync {
+    /// Scales the magnitudes of one frame in place, `fft_len` being the length of the transform
+    fn scale_frame(&self, magnitudes: &mut [f32], fft_len: usize);
+
+    /// Scales the frames of the whole input once they have all been computed, nothing by default
+    fn scale_all(&self, _frames: &mut [Vec<f32>]) {}
+}
+
+impl AmplitudeScaler for Normalize {
+    fn scale_frame(&self, magnitudes: &mut [f32], fft_len: usize) {
+        let divisor = match self {
+            Normalize::Column => magnitudes.iter().fold(0.0f32, |max, &val| max.max(val)),
+            Normalize::Length => fft_len as f32,
+            Normalize::SqrtLength => (fft_len as f32).sqrt(),
+            Normalize::Global | Normalize::None => return,
+        };
+        for val in magnitudes {
+            *val = if divisor != 0.0 { *val / divisor } else { 0.0 };
+        }
+    }
+
+    fn scale_all(&self, frames: &mut [Vec<f32>]) {
+        if *self == Normalize::Global {
+            normalize_global(frames);
+        }
+    }
+}
+
+impl<F> AmplitudeScaler for F
+where
+    F: Fn(&mut [f32], usize) + Sync,
+{
+    fn scale_frame(&self, magnitudes: &mut [f32], fft_len: usize) {
+        self(magnitudes, fft_len)
+    }
+}
+
+/// Number of analysis windows needed to cover `sample_count` samples
+/// The last window may extend past the end of the input, in which case it is zero-padded
+pub fn frame_count(sample_count: usize, window: usize, hop: usize) -> usize {
+    if sample_count == 0 {
+        0
+    } else if sample_count <= window {
+        1
+    } else {
+        (sample_count - window).div_ceil(hop) + 1
+    }
+}
+
+/// Coefficients each block of samples is multiplied by before the transform: those of
+/// [`SpectrogramConfig::window_fn`], except with the constant-Q transform whose kernels are tapered
+/// instead
+fn block_coefficients(config: &SpectrogramConfig) -> Vec<f32> {
+    match config.transform {
+        Transform::Fft => config.window_fn.coefficients(config.window),
+        Transform::Cqt => vec![1.0; config.window],
+    }
+}
+
+/// Multiplies `block` by the window `coefficients` into `windowed`, zero-padded to `fft_len`
+fn apply_window(windowed: &mut Vec<f32>, block: &[f32], coefficients: &[f32], fft_len: usize) {
+    windowed.clear();
+    windowed.extend(
+        block
+            .iter()
+            .zip(coefficients)
+            .map(|(sample, coefficient)| sample * coefficient),
+    );
+    windowed.resize(fft_len, 0.0);
+}
+
+/// Center frequencies of the bins kept in [`SpectrogramData::frequencies`], from DC up to
+/// [`SpectrogramConfig::max_freq`] for the FFT, or those of [`cqt_frequencies`] (Hz)
+pub fn bin_frequencies(config: &SpectrogramConfig) -> Vec<f32> {
+    if config.transform == Transform::Cqt {
+        return cqt_frequencies(config);
+    }
+    let fft_len = config.fft_len();
+    let bin_width = config.sample_rate as f32 / fft_len as f32;
+    (0..=fft_len / 2)
+        .map(|bin| bin as f32 * bin_width)
+        .take_while(|&freq| freq <= config.max_freq)
+        .collect()
+}
+
+/// Forward FFT planned once for the configured length and shared by every window, with the
+/// kernels of the constant-Q transform when it is the one configured
+struct Analyzer {
+    fft: Arc<dyn Fft<f32>>,
+    /// Center frequency of each bin kept, from DC up to the configured maximum for the FFT (Hz)
+    frequencies: Vec<f32>,
+    /// Spectral kernels of the constant-Q bins, `None` for the FFT
+    cqt: Option<CqtKernels>,
+    /// Gain of [`SpectrogramConfig::weighting`] at each kept bin, `None` when unweighted
+    weights: Option<Vec<f32>>,
+}
+
+impl Analyzer {
+    fn new(config: &SpectrogramConfig) -> Self {
+        let frequencies = bin_frequencies(config);
+        let weights = (config.weighting != Weighting::Z).then(|| {
+            frequencies
+                .iter()
+                .map(|&freq| config.weighting.gain(freq))
+                .collect()
+        });
+        let fft = FftPlanner::new().plan_fft_forward(config.fft_len());
+        let cqt = (config.transform == Transform::Cqt)
+            .then(|| CqtKernels::new(config, &frequencies, fft.as_ref()));
+        Analyzer {
+            fft,
+            frequencies,
+            cqt,
+            weights,
+        }
+    }
+
+    /// Complex spectrum of one windowed block over the kept bins
+    fn bins(&self, windowed: &[f32]) -> Vec<Complex<f32>> {
+        let mut buffer: Vec<Complex<f32>> = windowed
+            .iter()
+            .map(|&sample| Complex::new(sample, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+        match &self.cqt {
+            Some(kernels) => kernels.apply(&buffer),
+            None => {
+                buffer.truncate(self.frequencies.len());
+                buffer
+            }
+        }
+    }
+
+    /// Magnitudes of the kept bins of one windowed block, weighted and then scaled per frame by
+    /// `scaler`
+    fn magnitudes(&self, windowed: &[f32], scaler: &dyn AmplitudeScaler) -> Vec<f32> {
+        let mut magnitudes: Vec<f32> = self
+            .bins(windowed)
+            .iter()
+            .map(|value| (value.re * value.re + value.im * value.im).sqrt())
+            .collect();
+        if let Some(weights) = &self.weights {
+            for (magnitude, weight) in magnitudes.iter_mut().zip(weights) {
+                *magnitude *= weight;
+            }
+        }
+
+        scaler.scale_frame(&mut magnitudes, windowed.len());
+        magnitudes
+    }
+}
+
+/// Rejects an input without samples, which has no frames to draw
+fn check_not_empty(samples: &[f32]) -> Result<(), FftImageError> {
+    if samples.is_empty() {
+        Err(FftImageError::EmptyInput)
+    } else {
+        Ok(())
+    }
+}
+
+/// Rejects samples that would turn every bin of their windows into NaN
+fn check_finite(samples: &[f32]) -> Result<(), FftImageError> {
+    if samples.iter().all(|sample| sample.is_finite()) {
+        Ok(())
+    } else {
+        Err(FftImageError::NonFiniteSamples)
+    }
+}
+
+/// Divides every magnitude by the loudest one, for [`Normalize::Global`]
+fn normalize_global(frames: &mut [Vec<f32>]) {
+    let max = frames
+        .iter()
+        .flatten()
+        .fold(0.0f32, |max, &val| max.max(val));
+    if max > 0.0 {
+        for val in frames.iter_mut().flatten() {
+            *val /= max;
+        }
+    }
+}
+
+/// Runs the [`SpectrogramConfig::denoise`] filter over `frames`
+fn denoise_frames(frames: &mut Vec<Vec<f32>>, config: &SpectrogramConfig) {
+    match config.denoise {
+        Denoise::None => {}
+        Denoise::Median => median_filter(frames, config.kernel),
+    }
+}
+
+/// Replaces the magnitude of every bin of `frames` with its average over the `width` frames centered
+/// on its own, for [`SpectrogramConfig::smooth`]
+///
+/// An even `width` spans one more frame with half weight at either end, so that the average stays
+/// centered and nothing moves in time. Near the first and last frames the average only covers the
+/// frames there are, rather than fading towards silence.
+fn smooth_frames(frames: &mut Vec<Vec<f32>>, width: usize) {
+    if width <= 1 || frames.is_empty() {
+        return;
+    }
+    let radius = width / 2;
+    let weight = |offset: usize| {
+        if offset == radius && width.is_multiple_of(2) {
+            0.5
+        } else {
+            1.0
+        }
+    };
+
+    let smoothed = (0..frames.len())
+        .into_par_iter()
+        .map(|index| {
+            let first = index.saturating_sub(radius);
+            let last = (index + radius).min(frames.len() - 1);
+            let mut sum = vec![0.0; frames[index].len()];
+            let mut total = 0.0;
+            for (neighbor, frame) in frames.iter().enumerate().take(last + 1).skip(first) {
+                let weight = weight(neighbor.abs_diff(index));
+                for (sum, &magnitude) in sum.iter_mut().zip(frame) {
+                    *sum += weight * magnitude;
+                }
+                total += weight;
+            }
+            for sum in &mut sum {
+                *sum /= total;
+            }
+            sum
+        })
+        .collect();
+    *frames = smoothed;
+}
+
+/// Resamples `frames` onto `columns` frames evenly covering the same span, for
+/// [`SpectrogramConfig::time_bins`]
+///
+/// With more frames than columns, each column averages the frames it covers, weighted by how much
+/// of each it covers. With fewer, each column interpolates linearly between the two frames nearest
+/// its center.
+fn resample_frames(frames: &mut Vec<Vec<f32>>, columns: Option<usize>) {
+    let Some(columns) = columns else {
+        return;
+    };
+    let count = frames.len();
+    if count == 0 || count == columns {
+        return;
+    }
+    // Frames per column
+    let scale = count as f64 / columns as f64;
+
+    let resampled = (0..columns)
+        .into_par_iter()
+        .map(|column| {
+            let mut sum = vec![0.0; frames[0].len()];
+            let mut add = |index: usize, weight: f64| {
+                for (sum, &magnitude) in sum.iter_mut().zip(&frames[index]) {
+                    *sum += weight as f32 * magnitude;
+                }
+            };
+            if scale >= 1.0 {
+                let (start, end) = (column as f64 * scale, (column + 1) as f64 * scale);
+                let last = (end.ceil() as usize).min(count);
+                for index in start.floor() as usize..last {
+                    let covered = end.min((index + 1) as f64) - start.max(index as f64);
+                    add(index, covered / scale);
+                }
+            } else {
+                let position = ((column as f64 + 0.5) * scale - 0.5).clamp(0.0, (count - 1) as f64);
+                let before = position.floor() as usize;
+                let t = position - before as f64;
+                add(before, 1.0 - t);
+                if t > 0.0 {
+                    add(before + 1, t);
+                }
+            }
+            sum
+        })
+        .collect();
+    *frames = resampled;
+}
+
+/// Value at `percentile` (0 to 100) of `values`, taking the nearest rank
+fn percentile(values: &mut [f32], percentile: f32) -> f32 {
+    let rank = (percentile / 100.0 * (values.len() - 1) as f32).round() as usize;
+    *values.select_nth_unstable_by(rank, f32::total_cmp).1
+}
+
+/// Maps the magnitudes of `frames` between the [`SpectrogramConfig::contrast`] percentiles of all
+/// of them onto the full intensity range of [`SpectrogramConfig::amplitude`], clipping the others
+fn stretch_contrast(frames: &mut [Vec<f32>], config: &SpectrogramConfig) {
+    let Some([low, high]) = config.contrast else {
+        return;
+    };
+    let mut magnitudes: Vec<f32> = frames.iter().flatten().copied().collect();
+    if magnitudes.is_empty() {
+        return;
+    }
+    let (low, high) = (
+        percentile(&mut magnitudes, low),
+        percentile(&mut magnitudes, high),
+    );
+    drop(magnitudes);
+
+    let stretch = |level: f32, low: f32, high: f32| ((level - low) / (high - low)).clamp(0.0, 1.0);
+    match config.amplitude {
+        Amplitude::Linear if high > low => {
+            for magnitude in frames.iter_mut().flatten() {
+                *magnitude = stretch(*magnitude, low, high);
+            }
+        }
+        Amplitude::Db if high > 0.0 => {
+            let high_db = 20.0 * high.log10();
+            let floor_db = high_db + config.db_floor;
+            let level = |magnitude: f32| (20.0 * magnitude.log10()).max(floor_db);
+            let low_db = level(low);
+            if high_db > low_db {
+                // Drawn from `db_floor` up to 0 dB, the intensity span of decibel mode
+                for magnitude in frames.iter_mut().flatten() {
+                    let t = stretch(level(*magnitude), low_db, high_db);
+                    *magnitude = 10f32.powf(config.db_floor * (1.0 - t) / 20.0);
+                }
+            }
+        }
+        Amplitude::Linear | Amplitude::Db => {}
+    }
+}
+
+/// Calls `f` with every block of `samples`, multiplied by the window function and zero-padded to
+/// the FFT length, and collects the results in window order
+///
+/// Windows are independent of each other, so they are processed in parallel on the rayon pool.
+fn map_windows<T: Send>(
+    samples: &[f32],
+    config: &SpectrogramConfig,
+    f: impl Fn(&[f32]) -> T + Sync,
+) -> Vec<T> {
+    let sample_count = samples.len();
+    let window = config.window;
+    let hop = config.hop;
+
+    let total_width = frame_count(sample_count, window, hop);
+
+    let window_coefficients = block_coefficients(config);
+    let fft_len = config.fft_len();
+
+    match config.transform {
+        Transform::Fft => log::debug!(
+            "Analyzing {total_width} windows of {window} samples, {hop} samples apart, with a \
+             {fft_len}-point FFT"
+        ),
+        Transform::Cqt => log::debug!(
+            "Analyzing {total_width} windows of {window} samples, {hop} samples apart, with a \
+             constant-Q transform of {} bins to the octave",
+            config.bins_per_octave
+        ),
+    }
+    let progress = column_bar(total_width);
+
+    let results = (0..total_width)
+        .into_par_iter()
+        .map_init(
+            || Vec::with_capacity(fft_len),
+            |windowed, sampling_x| {
+                let sample_start = sampling_x * hop;
+                let sample_end = (sample_start + window).min(sample_count);
+
+                apply_window(
+                    windowed,
+                    &samples[sample_start..sample_end],
+                    &window_coefficients,
+                    fft_len,
+                );
+
+                let result = f(windowed);
+                progress.inc(1);
+                result
+            },
+        )
+        .collect();
+
+    progress.finish_and_clear();
+    results
+}
+
+/// Computes the magnitude spectrum of every window of `samples`
+pub fn compute_spectrogram(
+    samples: &[f32],
+    config: &SpectrogramConfig,
+) -> Result<SpectrogramData, FftImageError> {
+    compute_spectrogram_with(samples, config, &config.normalize)
+}
+
+/// Computes the magnitude spectrum of every window of `samples`, scaled by `scaler` instead of
+/// [`SpectrogramConfig::normalize`]
+pub fn compute_spectrogram_with(
+    samples: &[f32],
+    config: &SpectrogramConfig,
+    scaler: &dyn AmplitudeScaler,
+) -> Result<SpectrogramData, FftImageError> {
+    check_not_empty(samples)?;
+    check_finite(samples)?;
+
+    let transform = Analyzer::new(config);
+    let mut frames = map_windows(samples, config, |windowed| {
+        transform.magnitudes(windowed, scaler)
+    });
+    scaler.scale_all(&mut frames);
+    denoise_frames(&mut frames, config);
+    smooth_frames(&mut frames, config.smooth);
+    stretch_contrast(&mut frames, config);
+    resample_frames(&mut frames, config.time_bins);
+
+    Ok(SpectrogramData {
+        sample_rate: config.sample_rate,
+        window: config.window,
+        hop: config.hop,
+        frequencies: transform.frequencies,
+        frames,
+    })
+}
+
+/// Number of complete windows [`SpectrogramStream`] collects before transforming them in parallel
+const STREAM_BATCH: usize = 64;
+
+/// Incremental counterpart of [`compute_spectrogram`], fed with samples as they are decoded
+///
+/// Only the samples of the windows that are not complete yet are held in memory. The result of
+/// [`SpectrogramStream::finish`] is the same as that of [`compute_spectrogram`] over all samples.
+pub struct SpectrogramStream<'a> {
+    config: &'a SpectrogramConfig,
+    scaler: &'a dyn AmplitudeScaler,
+    transform: Analyzer,
+    coefficients: Vec<f32>,
+    /// Samples from the start of the next window onwards
+    buffer: Vec<f32>,
+    /// Samples to drop before the next window starts, when the hop is longer than the buffer
+    skip: usize,
+    /// Windowed blocks waiting to be transformed
+    batch: Vec<Vec<f32>>,
+    /// Number of windows started so far, whether transformed or still in `batch`
+    window_count: usize,
+    sample_count: usize,
+    frames: Vec<Vec<f32>>,
+}
+
+impl<'a> SpectrogramStream<'a> {
+    pub fn new(config: &'a SpectrogramConfig) -> Self {
+        Self::with_scaler(config, &config.normalize)
+    }
+
+    /// Stream whose frames are scaled by `scaler` instead of [`SpectrogramConfig::normalize`]
+    pub fn with_scaler(config: &'a SpectrogramConfig, scaler: &'a dyn AmplitudeScaler) -> Self {
+        SpectrogramStream {
+            config,
+            scaler,
+            transform: Analyzer::new(config),
+            coefficients: block_coefficients(config),
+            buffer: Vec::with_capacity(config.window),
+            skip: 0,
+            batch: Vec::with_capacity(STREAM_BATCH),
+            window_count: 0,
+            sample_count: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Appends `samples` to the input, transforming every window that is now complete
+    pub fn push(&mut self, samples: &[f32]) -> Result<(), FftImageError> {
+        check_finite(samples)?;
+        self.sample_count += samples.len();
+
+        let skipped = self.skip.min(samples.len());
+        self.skip -= skipped;
+        self.buffer.extend_from_slice(&samples[skipped..]);
+
+        while self.buffer.len() >= self.config.window {
+            self.queue_window();
+        }
+        if self.batch.len() >= STREAM_BATCH {
+            self.flush();
+        }
+        Ok(())
+    }
+
+    /// Transforms the zero-padded final windows and returns the whole spectrogram
+    pub fn finish(mut self) -> Result<SpectrogramData, FftImageError> {
+        let total_width = frame_count(self.sample_count, self.config.window, self.config.hop);
+        if total_width == 0 {
+            return Err(FftImageError::EmptyInput);
+        }
+        while self.window_count < total_width {
+            self.queue_window();
+        }
+        self.flush();
+
+        self.scaler.scale_all(&mut self.frames);
+        denoise_frames(&mut self.frames, self.config);
+        smooth_frames(&mut self.frames, self.config.smooth);
+        stretch_contrast(&mut self.frames, self.config);
+        resample_frames(&mut self.frames, self.config.time_bins);
+
+        Ok(SpectrogramData {
+            sample_rate: self.config.sample_rate,
+            window: self.config.window,
+            hop: self.config.hop,
+            frequencies: self.transform.frequencies,
+            frames: self.frames,
+        })
+    }
+
+    /// Windows the start of the buffer into the batch and advances the buffer by one hop
+    fn queue_window(&mut self) {
+        let end = self.config.window.min(self.buffer.len());
+        let mut windowed = Vec::with_capacity(self.config.fft_len());
+        apply_window(
+            &mut windowed,
+            &self.buffer[..end],
+            &self.coefficients,
+            self.config.fft_len(),
+        );
+        self.batch.push(windowed);
+        self.window_count += 1;
+        self.skip = advance(&mut self.buffer, self.config.hop);
+    }
+
+    fn flush(&mut self) {
+        let frames: Vec<Vec<f32>> = self
+            .batch
+            .par_iter()
+            .map(|windowed| self.transform.magnitudes(windowed, self.scaler))
+            .collect();
+        self.frames.extend(frames);
+        self.batch.clear();
+    }
+}
+
+/// Drops the first `hop` samples of `buffer`, returning how many of the samples still to come must
+/// be dropped as well when the hop is longer than the buffer
+fn advance(buffer: &mut Vec<f32>, hop: usize) -> usize {
+    if hop >= buffer.len() {
+        let skip = hop - buffer.len();
+        buffer.clear();
+        skip
+    } else {
+        buffer.drain(..hop);
+        0
+    }
+}
+
+/// Magnitude spectrum of one analysis window, yielded by [`SpectralFrames`]
+pub struct SpectralFrame {
+    /// Position of the frame in the spectrogram, counted from 0
+    pub index: usize,
+    /// Index of the first sample of the window in the input
+    pub start_sample: usize,
+    /// Time of the first sample of the window (seconds)
+    pub start_time: f64,
+    /// One magnitude per bin of [`SpectralFrames::frequencies`]
+    pub magnitudes: Vec<f32>,
+}
+
+/// Iterator over the magnitude spectra of the windows of a [`SampleStream`], computed as the input
+/// is decoded
+///
+/// Only the samples of the window being transformed are held in memory, so frames can be rendered,
+/// analyzed or forwarded as they come. The frames are the same as those of [`compute_spectrogram`],
+/// except with [`Normalize::Global`], [`SpectrogramConfig::denoise`],
+/// [`SpectrogramConfig::smooth`], [`SpectrogramConfig::contrast`] and
+/// [`SpectrogramConfig::time_bins`], which need the frames around each one and are left unapplied
+/// here.
+/// Frame times are computed from [`SpectrogramConfig::sample_rate`], which should be that of the
+/// stream.
+pub struct SpectralFrames<'a> {
+    stream: &'a mut SampleStream,
+    config: &'a SpectrogramConfig,
+    transform: Analyzer,
+    coefficients: Vec<f32>,
+    /// Samples from the start of the next window onwards
+    buffer: Vec<f32>,
+    /// Samples to drop before the next window starts, when the hop is longer than the buffer
+    skip: usize,
+    window_count: usize,
+    sample_count: usize,
+    /// Number of frames of the whole input, known once the stream is exhausted
+    total: Option<usize>,
+}
+
+impl<'a> SpectralFrames<'a> {
+    pub fn new(stream: &'a mut SampleStream, config: &'a SpectrogramConfig) -> Self {
+        SpectralFrames {
+            stream,
+            config,
+            transform: Analyzer::new(config),
+            coefficients: block_coefficients(config),
+            buffer: Vec::with_capacity(config.window),
+            skip: 0,
+            window_count: 0,
+            sample_count: 0,
+            total: None,
+        }
+    }
+
+    /// Center frequency of each bin of the yielded frames (Hz)
+    pub fn frequencies(&self) -> &[f32] {
+        &self.transform.frequencies
+    }
+
+    /// Transforms the window at the start of the buffer, zero-padded if the input ends within it
+    fn next_frame(&mut self) -> SpectralFrame {
+        let end = self.config.window.min(self.buffer.len());
+        let mut windowed = Vec::with_capacity(self.config.fft_len());
+        apply_window(
+            &mut windowed,
+            &self.buffer[..end],
+            &self.coefficients,
+            self.config.fft_len(),
+        );
+        let index = self.window_count;
+        self.window_count += 1;
+        self.skip = advance(&mut self.buffer, self.config.hop);
+
+        let start_sample = index * self.config.hop;
+        SpectralFrame {
+            index,
+            start_sample,
+            start_time: start_sample as f64 / self.config.sample_rate as f64,
+            magnitudes: self.transform.magnitudes(&windowed, &self.config.normalize),
+        }
+    }
+}
+
+impl Iterator for SpectralFrames<'_> {
+    type Item = Result<SpectralFrame, FftImageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.total {
+                Some(total) if self.window_count < total => return Some(Ok(self.next_frame())),
+                Some(_) => return None,
+                None if self.buffer.len() >= self.config.window => {
+                    return Some(Ok(self.next_frame()))
+                }
+                None => {}
+            }
+
+            let chunk = match self.stream.next_chunk() {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    self.total = Some(self.window_count);
+                    return Some(Err(err));
+                }
+            };
+            match chunk {
+                Some(samples) => {
+                    if let Err(err) = check_finite(samples) {
+                        self.total = Some(self.window_count);
+                        return Some(Err(err));
+                    }
+                    self.sample_count += samples.len();
+                    let skipped = self.skip.min(samples.len());
+                    self.skip -= skipped;
+                    self.buffer.extend_from_slice(&samples[skipped..]);
+                }
+                None => {
+                    self.total = Some(frame_count(
+                        self.sample_count,
+                        self.config.window,
+                        self.config.hop,
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Computes the phase spectrum of every window of `samples`
+///
+/// The frames of the result hold the phase angle of each bin mapped from `-π..=π` onto `0.0..=1.0`
+/// instead of a magnitude, over the same bins as [`compute_spectrogram`], so the result can be
+/// drawn with [`render_phase`](crate::render_phase) to line up with the magnitude image.
+pub fn compute_phase_spectrogram(
+    samples: &[f32],
+    config: &SpectrogramConfig,
+) -> Result<SpectrogramData, FftImageError> {
+    check_not_empty(samples)?;
+    check_finite(samples)?;
+
+    let transform = Analyzer::new(config);
+
+    let frames = map_windows(samples, config, |windowed| {
+        transform
+            .bins(windowed)
+            .iter()
+            .map(|value| (value.arg() + PI) / (2.0 * PI))
+            .collect()
+    });
+
+    Ok(SpectrogramData {
+        sample_rate: config.sample_rate,
+        window: config.window,
+        hop: config.hop,
+        frequencies: transform.frequencies,
+        frames,
+    })
+}
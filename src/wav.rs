@@ -0,0 +1,21 @@
+use std::path::Path;
+
+use crate::error::FftImageError;
+
+/// Writes mono `samples` to `path` as a 32-bit float WAV file
+pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<(), FftImageError> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
+    }
+    writer.finalize()?;
+
+    Ok(())
+}
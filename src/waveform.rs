@@ -0,0 +1,153 @@
+use image::{DynamicImage, ImageBuffer, Luma, Pixel};
+use rayon::prelude::*;
+
+use crate::config::SpectrogramConfig;
+use crate::render::{
+    composite, magnitude_pixel, spectrogram_columns, BackgroundPixel, BitDepth, PixelFormat,
+};
+use crate::spectrogram::{frame_count, SpectrogramData};
+
+/// Smallest and largest sample under each time column
+///
+/// There is one column per analysis window, as in a spectrogram of the same samples, each
+/// covering the `hop` samples from the start of its window.
+pub fn waveform_envelope(samples: &[f32], config: &SpectrogramConfig) -> Vec<(f32, f32)> {
+    let columns = frame_count(samples.len(), config.window, config.hop);
+    (0..columns)
+        .into_par_iter()
+        .map(|column| {
+            let start = (column * config.hop).min(samples.len());
+            let end = (start + config.hop).min(samples.len());
+            samples[start..end]
+                .iter()
+                .fold(None, |envelope, &sample| match envelope {
+                    None => Some((sample, sample)),
+                    Some((min, max)) => Some((sample.min(min), sample.max(max))),
+                })
+                .unwrap_or((0.0, 0.0))
+        })
+        .collect()
+}
+
+/// Draws the min/max envelope of `samples` as a waveform, full scale spanning the row height with
+/// positive samples at the top, in the layout, orientation and pixel format of
+/// [`render_image`](crate::render_image)
+///
+/// The envelope is drawn with the loudest color of the colormap on its quietest color.
+pub fn render_waveform(samples: &[f32], config: &SpectrogramConfig) -> DynamicImage {
+    let envelope = waveform_envelope(samples, config);
+    let h = config.row_height;
+
+    match (config.format, config.bit_depth) {
+        (PixelFormat::Rgba, BitDepth::Eight) => {
+            let columns = waveform_columns(&envelope, h, config, |level| {
+                config.colormap.map_color(level)
+            });
+            DynamicImage::ImageRgba8(composite(&columns, config))
+        }
+        (PixelFormat::Gray, BitDepth::Eight) => {
+            let columns =
+                waveform_columns(&envelope, h, config, |level| Luma([(level * 255.0) as u8]));
+            DynamicImage::ImageLuma8(composite(&columns, config))
+        }
+        (_, BitDepth::Sixteen) => {
+            let columns = waveform_columns(&envelope, h, config, |level| {
+                Luma([(level * 65535.0) as u16])
+            });
+            DynamicImage::ImageLuma16(composite(&columns, config))
+        }
+    }
+}
+
+/// Draws the waveform of `samples` in a panel of [`SpectrogramConfig::waveform_height`] above every
+/// row of the spectrogram `data`, which must be computed from the same samples so that the two line
+/// up column for column
+pub fn render_combined(
+    samples: &[f32],
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+) -> DynamicImage {
+    let envelope = waveform_envelope(samples, config);
+
+    match (config.format, config.bit_depth) {
+        (PixelFormat::Rgba, BitDepth::Eight) => {
+            DynamicImage::ImageRgba8(stack(&envelope, data, config, |level| {
+                config.colormap.map_color(level)
+            }))
+        }
+        (PixelFormat::Gray, BitDepth::Eight) => {
+            DynamicImage::ImageLuma8(stack(&envelope, data, config, |level| {
+                Luma([(level * 255.0) as u8])
+            }))
+        }
+        (_, BitDepth::Sixteen) => {
+            DynamicImage::ImageLuma16(stack(&envelope, data, config, |level| {
+                Luma([(level * 65535.0) as u16])
+            }))
+        }
+    }
+}
+
+/// Draws each waveform column on top of the spectrogram column of the same frame, `to_pixel`
+/// turning an intensity into a pixel
+fn stack<P>(
+    envelope: &[(f32, f32)],
+    data: &SpectrogramData,
+    config: &SpectrogramConfig,
+    to_pixel: impl Fn(f32) -> P + Sync,
+) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: BackgroundPixel + Send + Sync,
+{
+    let waveform = waveform_columns(envelope, config.waveform_height, config, &to_pixel);
+    let spectrogram = spectrogram_columns(data, config, |magnitude| {
+        magnitude_pixel(magnitude, config, &to_pixel)
+    });
+
+    let columns: Vec<(Vec<P>, P)> = waveform
+        .into_iter()
+        .zip(spectrogram)
+        .map(|((mut column, _), (spectrum, last))| {
+            column.extend(spectrum);
+            (column, last)
+        })
+        .collect();
+    composite(&columns, config)
+}
+
+/// One band of `h` rows per envelope column, `to_pixel` turning a level of `0.0` (background) or
+/// `1.0` (envelope) into a pixel
+fn waveform_columns<P>(
+    envelope: &[(f32, f32)],
+    h: u32,
+    config: &SpectrogramConfig,
+    to_pixel: impl Fn(f32) -> P,
+) -> Vec<(Vec<P>, P)>
+where
+    P: Pixel + Send + Sync,
+{
+    let (background, foreground) = if config.invert {
+        (to_pixel(1.0), to_pixel(0.0))
+    } else {
+        (to_pixel(0.0), to_pixel(1.0))
+    };
+    // Row of a sample value, full scale positive at the top
+    let row_for = |sample: f32| ((1.0 - sample.clamp(-1.0, 1.0)) / 2.0 * (h - 1) as f32).round();
+
+    envelope
+        .par_iter()
+        .map(|&(min, max)| {
+            let (top, bottom) = (row_for(max) as usize, row_for(min) as usize);
+            let column = (0..h as usize)
+                .map(|row| {
+                    if (top..=bottom).contains(&row) {
+                        foreground
+                    } else {
+                        background
+                    }
+                })
+                .collect();
+            (column, background)
+        })
+        .collect()
+}
@@ -0,0 +1,36 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Frequency weighting curve applied to the magnitudes, to approximate the sensitivity of hearing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weighting {
+    /// IEC 61672 A-weighting, following hearing at moderate levels and cutting the low bass
+    A,
+    /// IEC 61672 C-weighting, nearly flat over the audible range, for loud sounds
+    C,
+    /// No weighting
+    Z,
+}
+
+impl Weighting {
+    /// Factor the magnitude of a bin centered on `freq` (Hz) is multiplied by, 1 at 1 kHz
+    pub fn gain(self, freq: f32) -> f32 {
+        let f2 = (freq as f64).powi(2);
+        let (response, offset_db) = match self {
+            Weighting::A => (
+                12194f64.powi(2) * f2 * f2
+                    / ((f2 + 20.6f64.powi(2))
+                        * ((f2 + 107.7f64.powi(2)) * (f2 + 737.9f64.powi(2))).sqrt()
+                        * (f2 + 12194f64.powi(2))),
+                2.0,
+            ),
+            Weighting::C => (
+                12194f64.powi(2) * f2 / ((f2 + 20.6f64.powi(2)) * (f2 + 12194f64.powi(2))),
+                0.06,
+            ),
+            Weighting::Z => return 1.0,
+        };
+        (response * 10f64.powf(offset_db / 20.0)) as f32
+    }
+}
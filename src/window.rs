@@ -0,0 +1,31 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Tapering applied to each analysis window to reduce spectral leakage
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowFn {
+    Rect,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl WindowFn {
+    /// Computes the window coefficients for a window of `len` samples
+    pub fn coefficients(self, len: usize) -> Vec<f32> {
+        let denom = (len - 1) as f32;
+        (0..len)
+            .map(|n| {
+                let phase = 2.0 * PI * n as f32 / denom;
+                match self {
+                    WindowFn::Rect => 1.0,
+                    WindowFn::Hann => 0.5 - 0.5 * phase.cos(),
+                    WindowFn::Hamming => 0.54 - 0.46 * phase.cos(),
+                    WindowFn::Blackman => 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos(),
+                }
+            })
+            .collect()
+    }
+}
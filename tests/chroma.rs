@@ -0,0 +1,36 @@
+use fft_image_extractor::{chromagram, pitch_class, SpectrogramConfig};
+
+mod common;
+
+#[test]
+fn pitch_classes_count_semitones_from_c() {
+    assert_eq!(pitch_class(261.63), 0);
+    assert_eq!(pitch_class(329.63), 4);
+    assert_eq!(pitch_class(440.0), 9);
+    assert_eq!(pitch_class(880.0), 9);
+    assert_eq!(pitch_class(493.88), 11);
+    assert_eq!(pitch_class(123.47), 11);
+}
+
+#[test]
+fn chromagram_sums_the_octaves_of_each_pitch_class() {
+    let config = SpectrogramConfig::default()
+        .min_freq(100.0)
+        .max_freq(5000.0)
+        .build()
+        .unwrap();
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 50.0, 220.0, 261.63, 329.63, 440.0, 6000.0],
+        vec![vec![5.0, 7.0, 1.0, 1.0, 0.5, 1.0, 9.0], vec![0.0; 7]],
+    );
+    let chroma = chromagram(&data, &config);
+
+    // The DC bin and the bins outside the frequency range are left out, A (220 Hz and 440 Hz)
+    // is the loudest class
+    let mut expected = [0.0; 12];
+    expected[0] = 0.5;
+    expected[4] = 0.25;
+    expected[9] = 1.0;
+    assert_eq!(chroma, [expected, [0.0; 12]]);
+}
@@ -0,0 +1,633 @@
+//! Runs the `fft-resampler` binary the way it is used from a shell
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use fft_image_extractor::{frame_count, write_wav};
+use image::ImageFormat;
+
+mod common;
+
+/// An empty directory named after `name` in the temporary directory
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = common::temp_path(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Writes one second of a 440 Hz tone sampled at 8 kHz to `path`
+fn write_tone(path: &Path) {
+    write_wav(path, &common::tone(440.0, 8000, 8000), 8000).unwrap();
+}
+
+/// Runs the binary with `args`
+fn fft_resampler(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fft-resampler"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs the binary with `args`, checking that it succeeds
+fn run(args: &[&str]) -> Output {
+    let output = fft_resampler(args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+#[test]
+fn output_dash_writes_the_png_to_stdout() {
+    let dir = scratch_dir("cli-stdout");
+    let input = dir.join("tone.wav");
+    let file = dir.join("tone.png");
+    write_tone(&input);
+    let [input, file] = [&input, &file].map(|path| path.to_str().unwrap());
+
+    run(&["render", "-f", input, "-o", file]);
+    let stdout = run(&["render", "-f", input, "-o", "-"]).stdout;
+
+    let streamed = image::load_from_memory_with_format(&stdout, ImageFormat::Png).unwrap();
+    assert_eq!(streamed.to_rgba8(), image::open(file).unwrap().to_rgba8());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn several_inputs_get_one_image_each() {
+    let dir = scratch_dir("cli-batch");
+    let out_dir = dir.join("out");
+    std::fs::create_dir(&out_dir).unwrap();
+    let [a, b] = ["a.wav", "b.wav"].map(|name| dir.join(name));
+    write_tone(&a);
+    write_tone(&b);
+    let [a, b, out_dir] = [&a, &b, &out_dir].map(|path| path.to_str().unwrap());
+
+    run(&["render", "-f", a, b, "--out-dir", out_dir]);
+    let mut written: Vec<_> = std::fs::read_dir(out_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    written.sort();
+    assert_eq!(written, ["a.png", "b.png"]);
+
+    // A single output path cannot name several images
+    let output = fft_resampler(&["render", "-f", a, b, "-o", "both.png"]);
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn glob_patterns_expand_to_the_audio_files() {
+    let dir = scratch_dir("cli-glob");
+    write_tone(&dir.join("a.wav"));
+    write_tone(&dir.join("b.WAV"));
+    std::fs::write(dir.join("notes.txt"), "not audio").unwrap();
+    let pattern = dir.join("*");
+    let pattern = pattern.to_str().unwrap();
+
+    run(&["render", "-f", pattern]);
+    let mut images: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .filter(|name| name.to_string_lossy().ends_with(".png"))
+        .collect();
+    images.sort();
+    assert_eq!(images, ["a.png", "b.png"]);
+
+    // A pattern that matches nothing leaves no inputs to draw
+    let missing = dir.join("*.flac");
+    let output = fft_resampler(&["render", "-f", missing.to_str().unwrap()]);
+    assert!(!output.status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dry_run_reports_the_size_without_rendering() {
+    let dir = scratch_dir("cli-dry-run");
+    let input = dir.join("tone.wav");
+    let image = dir.join("tone.png");
+    write_tone(&input);
+    let [input, image] = [&input, &image].map(|path| path.to_str().unwrap());
+
+    let stdout = run(&["render", "-f", input, "--dry-run", "--window", "256"]).stdout;
+    assert!(!Path::new(image).exists());
+    let report = String::from_utf8(stdout).unwrap();
+    let size = report
+        .lines()
+        .find_map(|line| line.strip_prefix("Image:"))
+        .unwrap()
+        .trim()
+        .to_string();
+
+    run(&["render", "-f", input, "--window", "256"]);
+    let (width, height) = image::image_dimensions(image).unwrap();
+    assert_eq!(size, format!("{width} x {height} pixels"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dump_wav_writes_the_analyzed_range() {
+    let dir = scratch_dir("cli-dump-wav");
+    let input = dir.join("tone.wav");
+    let dump = dir.join("dump.wav");
+    write_tone(&input);
+    let [input, dump_path] = [&input, &dump].map(|path| path.to_str().unwrap());
+
+    run(&[
+        "render",
+        "-f",
+        input,
+        "-o",
+        "-",
+        "--start",
+        "0.25",
+        "--end",
+        "0.75",
+        "--dump-wav",
+        dump_path,
+    ]);
+    let mut reader = hound::WavReader::open(&dump).unwrap();
+    assert_eq!(reader.spec().sample_rate, 8000);
+    assert_eq!(reader.spec().channels, 1);
+    let dumped: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+    let tone = common::tone(440.0, 8000, 8000);
+    assert_eq!(dumped.len(), 4000);
+    for (dumped, original) in dumped.iter().zip(&tone[2000..6000]) {
+        assert!((dumped - original).abs() < 1e-6);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mfcc_keeps_the_level_of_each_frame() {
+    let dir = scratch_dir("cli-mfcc");
+    let input = dir.join("fade.wav");
+    let csv = dir.join("fade.csv");
+    // A tone dropping by 40 dB halfway through
+    let samples: Vec<f32> = common::tone(440.0, 8000, 8000)
+        .into_iter()
+        .enumerate()
+        .map(|(i, sample)| if i < 4000 { sample } else { sample / 100.0 })
+        .collect();
+    write_wav(&input, &samples, 8000).unwrap();
+    let [input, csv_path] = [&input, &csv].map(|path| path.to_str().unwrap());
+
+    run(&[
+        "render", "-f", input, "-o", "-", "--mode", "mfcc", "--window", "512", "--hop", "256",
+        "--csv", csv_path,
+    ]);
+    let csv = std::fs::read_to_string(&csv).unwrap();
+    let c0: Vec<f32> = csv
+        .lines()
+        .skip(1)
+        .map(|row| row.split(',').next().unwrap().parse().unwrap())
+        .collect();
+    let (loud, quiet) = (c0[2], c0[c0.len() - 3]);
+    // The band energies drop by a factor of 10^4, and c0 sums their logs over the bands
+    assert!(loud - quiet > 100f32.ln(), "{loud} vs {quiet}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn max_freq_above_nyquist_warns_only_when_requested() {
+    let dir = scratch_dir("cli-nyquist");
+    let input = dir.join("tone.wav");
+    let image = dir.join("tone.png");
+    let config = dir.join("config.toml");
+    write_tone(&input);
+    std::fs::write(&config, "max_freq = 6000.0\n").unwrap();
+    let [input, image, config] = [&input, &image, &config].map(|path| path.to_str().unwrap());
+
+    let warned = |args: &[&str]| {
+        let output = run(&[&["render", "-f", input, "-o", image][..], args].concat());
+        String::from_utf8_lossy(&output.stderr).contains("exceeds the Nyquist frequency")
+    };
+    // The default of 10 kHz is lowered to the 4 kHz Nyquist frequency of the tone silently
+    assert!(!warned(&[]));
+    assert!(warned(&["--max-freq", "10000"]));
+    assert!(warned(&["--config", config]));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn split_duration_numbers_one_image_per_stretch() {
+    let dir = scratch_dir("cli-split");
+    let input = dir.join("tone.wav");
+    let image = dir.join("tone.png");
+    write_tone(&input);
+    let [input, image] = [&input, &image].map(|path| path.to_str().unwrap());
+
+    run(&[
+        "render",
+        "-f",
+        input,
+        "-o",
+        image,
+        "--split-duration",
+        "0.4",
+        "--window",
+        "256",
+        "--hop",
+        "64",
+        "--layout",
+        "strip",
+    ]);
+    let mut written: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    written.sort();
+    assert_eq!(
+        written,
+        ["tone.000.png", "tone.001.png", "tone.002.png", "tone.wav"]
+    );
+
+    // 0.4 s and the 5% overlap into the next image, then the 0.2 s left at the end
+    let widths: Vec<u32> = ["tone.000.png", "tone.001.png", "tone.002.png"]
+        .iter()
+        .map(|name| image::image_dimensions(dir.join(name)).unwrap().0)
+        .collect();
+    let frames = |samples| frame_count(samples, 256, 64) as u32;
+    assert_eq!(widths, [frames(3360), frames(3360), frames(1600)]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stereo_mid_side_draws_both_signals() {
+    let dir = scratch_dir("cli-mid-side");
+    let input = dir.join("stereo.wav");
+    let image = dir.join("stereo.png");
+    // The same tone on both channels, so the side signal is silent
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 8000,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+    for sample in common::tone(440.0, 8000, 8000) {
+        writer.write_sample(sample).unwrap();
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    let [input, image] = [&input, &image].map(|path| path.to_str().unwrap());
+
+    run(&[
+        "render", "-f", input, "-o", image, "--stereo", "mid-side", "--format", "gray",
+    ]);
+    let [mid, side] = ["stereo.mid.png", "stereo.side.png"]
+        .map(|name| image::open(dir.join(name)).unwrap().into_luma8());
+    assert!(mid.pixels().any(|pixel| pixel.0 != [0]));
+    assert!(side.pixels().all(|pixel| pixel.0 == [0]));
+    assert!(!Path::new(image).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn info_lists_the_tracks_of_the_input() {
+    let dir = scratch_dir("cli-info");
+    let input = dir.join("tone.wav");
+    write_tone(&input);
+    let input = input.to_str().unwrap();
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&run(&["info", input, "--json"]).stdout).unwrap();
+    let tracks = json["tracks"].as_array().unwrap();
+    assert_eq!(tracks.len(), 1);
+    let track = &tracks[0];
+    assert_eq!(track["id"], 0);
+    assert_eq!(track["default"], true);
+    assert_eq!(track["sample_rate"], 8000);
+    assert_eq!(track["channels"], 1);
+    assert_eq!(track["bits_per_sample"], 32);
+    assert_eq!(track["frames"], 8000);
+    assert_eq!(track["duration"], 1.0);
+
+    let text = String::from_utf8(run(&["info", input]).stdout).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "Track 0 (default)");
+    assert!(lines.contains(&"  Sample rate:  8000 Hz"));
+    assert!(lines.contains(&"  Duration:     1.000 s (8000 frames)"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn arguments_without_a_subcommand_render() {
+    let dir = scratch_dir("cli-default-render");
+    let input = dir.join("tone.wav");
+    write_tone(&input);
+    let input = input.to_str().unwrap();
+
+    // Bare flags, and flags after the global options, are taken as `render`
+    let rendered = run(&["render", "-f", input, "-o", "-"]).stdout;
+    assert_eq!(run(&["-f", input, "-o", "-"]).stdout, rendered);
+    assert_eq!(
+        run(&["-q", "--threads", "1", "-f", input, "-o", "-"]).stdout,
+        rendered
+    );
+
+    // Subcommands pass through unchanged
+    let info = String::from_utf8(run(&["-q", "info", input]).stdout).unwrap();
+    assert!(info.starts_with("Track 0"));
+
+    let help = String::from_utf8(run(&["--help"]).stdout).unwrap();
+    assert!(help.starts_with("Usage: fft-resampler [OPTIONS] <COMMAND>"));
+    let help = String::from_utf8(run(&["help", "render"]).stdout).unwrap();
+    assert!(help.contains("Usage: fft-resampler render"));
+    let version = String::from_utf8(run(&["--version"]).stdout).unwrap();
+    assert_eq!(
+        version.trim_end(),
+        concat!("fft-resampler ", env!("CARGO_PKG_VERSION"))
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn load_matrix_rejects_the_analysis_options() {
+    let dir = scratch_dir("cli-load-matrix");
+    let input = dir.join("tone.wav");
+    let matrix = dir.join("tone.matrix");
+    let image = dir.join("tone.png");
+    write_tone(&input);
+    let [input, matrix, image] = [&input, &matrix, &image].map(|path| path.to_str().unwrap());
+
+    run(&["render", "-f", input, "-o", "-", "--save-matrix", matrix]);
+    run(&[
+        "render",
+        "--load-matrix",
+        matrix,
+        "-o",
+        image,
+        "--gamma",
+        "2",
+    ]);
+    for analysis in [
+        ["--window", "512"],
+        ["--fft-size", "4096"],
+        ["--hop", "128"],
+        ["--normalize", "global"],
+        ["--smooth", "3"],
+        ["--time-bins", "10"],
+    ] {
+        let output = fft_resampler(
+            &[
+                &["render", "--load-matrix", matrix, "-o", image],
+                &analysis[..],
+            ]
+            .concat(),
+        );
+        assert!(!output.status.success(), "{analysis:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("cannot be used with"), "{stderr}");
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invert_rejects_constant_q_matrices() {
+    let dir = scratch_dir("cli-invert-cqt");
+    let input = dir.join("tone.wav");
+    let [fft, cqt] = ["fft.matrix", "cqt.matrix"].map(|name| dir.join(name));
+    let output = dir.join("out.wav");
+    write_tone(&input);
+    let [input, fft, cqt, output] =
+        [&input, &fft, &cqt, &output].map(|path| path.to_str().unwrap());
+
+    run(&["render", "-f", input, "-o", "-", "--save-matrix", fft]);
+    run(&["invert", "-i", fft, "-o", output, "--gl-iters", "2"]);
+    assert!(Path::new(output).exists());
+
+    run(&[
+        "render",
+        "-f",
+        input,
+        "-o",
+        "-",
+        "--transform",
+        "cqt",
+        "--save-matrix",
+        cqt,
+    ]);
+    let inverted = fft_resampler(&["invert", "-i", cqt, "-o", output]);
+    assert!(!inverted.status.success());
+    let stderr = String::from_utf8_lossy(&inverted.stderr);
+    assert!(stderr.contains("evenly spaced from 0 Hz"), "{stderr}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn contact_sheet_rejects_the_layout_options() {
+    let dir = scratch_dir("cli-contact-sheet");
+    let [a, b] = ["a.wav", "b.wav"].map(|name| dir.join(name));
+    let sheet = dir.join("sheet.png");
+    write_tone(&a);
+    write_tone(&b);
+    let [a, b, sheet] = [&a, &b, &sheet].map(|path| path.to_str().unwrap());
+
+    run(&["contact-sheet", a, b, "-o", sheet, "--thumb-size", "64x32"]);
+    assert!(Path::new(sheet).exists());
+
+    for layout in [["--layout", "wrapped"], ["--wrap-width", "16"]] {
+        let output = fft_resampler(&[&["contact-sheet", a, b, "-o", sheet], &layout[..]].concat());
+        assert!(!output.status.success(), "{layout:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("cannot be used with contact-sheet"),
+            "{stderr}"
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn time_bins_rejects_the_exports_of_hop_spaced_frames() {
+    let dir = scratch_dir("cli-time-bins");
+    let input = dir.join("tone.wav");
+    let export = dir.join("export");
+    write_tone(&input);
+    let [input, export] = [&input, &export].map(|path| path.to_str().unwrap());
+
+    run(&["render", "-f", input, "-o", "-", "--time-bins", "10"]);
+    for flag in ["--json", "--save-matrix", "--dump-wav", "--pitch-csv"] {
+        let output = fft_resampler(&[
+            "render",
+            "-f",
+            input,
+            "-o",
+            "-",
+            "--time-bins",
+            "10",
+            flag,
+            export,
+        ]);
+        assert!(!output.status.success(), "{flag}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("{flag} needs the frames a hop apart")),
+            "{stderr}"
+        );
+    }
+    assert!(!Path::new(export).exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn size_sets_the_image_dimensions_in_both_orientations() {
+    let dir = scratch_dir("cli-size");
+    let input = dir.join("tone.wav");
+    let image = dir.join("tone.png");
+    write_tone(&input);
+    let [input, image] = [&input, &image].map(|path| path.to_str().unwrap());
+
+    for orientation in ["horizontal", "vertical"] {
+        run(&[
+            "render",
+            "-f",
+            input,
+            "-o",
+            image,
+            "--size",
+            "64x20",
+            "--orientation",
+            orientation,
+        ]);
+        assert_eq!(
+            image::image_dimensions(image).unwrap(),
+            (64, 20),
+            "{orientation}"
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invert_refuses_masks_with_margins() {
+    let dir = scratch_dir("cli-invert-mask");
+    let input = dir.join("tone.wav");
+    let matrix = dir.join("tone.matrix");
+    let [strip, axes, wrapped, vertical, combined] = [
+        "strip.png",
+        "axes.png",
+        "wrapped.png",
+        "vertical.png",
+        "combined.png",
+    ]
+    .map(|name| dir.join(name));
+    let output = dir.join("out.wav");
+    write_tone(&input);
+    let [input, matrix, strip, axes, wrapped, vertical, combined, output] = [
+        &input, &matrix, &strip, &axes, &wrapped, &vertical, &combined, &output,
+    ]
+    .map(|path| path.to_str().unwrap());
+
+    let render = ["render", "-f", input, "--window", "256", "--hop", "256"];
+    run(&[
+        &render[..],
+        &["-o", strip, "--layout", "strip", "--save-matrix", matrix],
+    ]
+    .concat());
+    run(&[&render[..], &["-o", axes, "--layout", "strip", "--axes"]].concat());
+    run(&[&render[..], &["-o", wrapped, "--wrap-width", "4"]].concat());
+    let strip_render = [&render[..], &["--layout", "strip"]].concat();
+    run(&[
+        &strip_render[..],
+        &["-o", vertical, "--orientation", "vertical"],
+    ]
+    .concat());
+    run(&[&strip_render[..], &["-o", combined, "--mode", "combined"]].concat());
+
+    let invert = [
+        "invert",
+        "-i",
+        matrix,
+        "-o",
+        output,
+        "--gl-iters",
+        "2",
+        "--mask",
+    ];
+    run(&[&invert[..], &[strip]].concat());
+    for (mask, flag) in [
+        (axes, "--axes"),
+        (wrapped, "--layout wrapped"),
+        (vertical, "--orientation vertical"),
+        (combined, "--mode combined"),
+    ] {
+        let inverted = fft_resampler(&[&invert[..], &[mask]].concat());
+        assert!(!inverted.status.success(), "{mask}");
+        let stderr = String::from_utf8_lossy(&inverted.stderr);
+        assert!(
+            stderr.contains(&format!("was drawn with {flag}")),
+            "{stderr}"
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invert_places_the_mask_rows_as_drawn() {
+    let dir = scratch_dir("cli-invert-mask-rows");
+    let input = dir.join("tone.wav");
+    let matrix = dir.join("tone.matrix");
+    let mask = dir.join("mel.png");
+    let output = dir.join("out.wav");
+    write_tone(&input);
+    let [input, matrix, mask, output] =
+        [&input, &matrix, &mask, &output].map(|path| path.to_str().unwrap());
+
+    // Everything but the tone is black in its spectrogram, and silenced by it as a mask
+    run(&[
+        "render",
+        "-f",
+        input,
+        "-o",
+        mask,
+        "--window",
+        "256",
+        "--hop",
+        "256",
+        "--layout",
+        "strip",
+        "--scale",
+        "mel",
+        "--save-matrix",
+        matrix,
+    ]);
+    let invert = [
+        "invert",
+        "-i",
+        matrix,
+        "-o",
+        output,
+        "--gl-iters",
+        "2",
+        "--mask",
+        mask,
+    ];
+    let rms = || {
+        let samples: Vec<f32> = hound::WavReader::open(output)
+            .unwrap()
+            .into_samples::<f32>()
+            .map(Result::unwrap)
+            .collect();
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    };
+
+    run(&invert[..invert.len() - 2]);
+    let unmasked = rms();
+
+    // The rows are placed on the mel scale recorded in the mask, keeping the tone
+    run(&invert);
+    assert!(rms() > 0.9 * unmasked, "{} of {unmasked}", rms());
+    run(&[&invert[..], &["--scale", "mel", "--max-freq", "10000"]].concat());
+    assert!(rms() > 0.9 * unmasked, "{} of {unmasked}", rms());
+
+    let conflicting = fft_resampler(&[&invert[..], &["--scale", "log"]].concat());
+    assert!(!conflicting.status.success());
+    let stderr = String::from_utf8_lossy(&conflicting.stderr);
+    assert!(stderr.contains("was drawn with --scale mel"), "{stderr}");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,35 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+use fft_image_extractor::{SpectrogramConfig, SpectrogramData};
+
+/// Spectrogram of `frames` over the bins centered on `frequencies`, as if analyzed at the sample
+/// rate, window and hop of `config`
+pub fn spectrogram(
+    config: &SpectrogramConfig,
+    frequencies: Vec<f32>,
+    frames: Vec<Vec<f32>>,
+) -> SpectrogramData {
+    SpectrogramData {
+        sample_rate: config.sample_rate,
+        window: config.window,
+        hop: config.hop,
+        frequencies,
+        frames,
+    }
+}
+
+/// `len` samples of a full-scale sine at `freq` Hz, sampled at `sample_rate`
+pub fn tone(freq: f32, sample_rate: u32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| (2.0 * PI * freq * n as f32 / sample_rate as f32).sin())
+        .collect()
+}
+
+/// Path in the temporary directory for the file `name`, prefixed with the crate name
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fft-image-extractor-{name}"))
+}
@@ -0,0 +1,57 @@
+use fft_image_extractor::{compute_spectrogram, Colormap, FftImageError, SpectrogramConfig};
+
+mod common;
+
+#[test]
+fn max_freq_above_nyquist_is_clamped() {
+    let mut config = SpectrogramConfig::default()
+        .sample_rate(22_050)
+        .max_freq(20_000.0);
+
+    assert_eq!(config.clamp_max_freq(), Some(20_000.0));
+    assert_eq!(config.max_freq, 11_025.0);
+    let config = config.build().unwrap();
+
+    let samples: Vec<f32> = (0..10_000).map(|n| (n as f32 * 0.2).sin()).collect();
+    let data = compute_spectrogram(&samples, &config).unwrap();
+    assert_eq!(data.frequencies.last(), Some(&11_025.0));
+}
+
+#[test]
+fn max_freq_below_nyquist_is_kept() {
+    let mut config = SpectrogramConfig::default()
+        .sample_rate(44_100)
+        .max_freq(10_000.0);
+
+    assert_eq!(config.clamp_max_freq(), None);
+    assert_eq!(config.max_freq, 10_000.0);
+}
+
+#[test]
+fn nan_settings_are_rejected() {
+    let builds = |config: SpectrogramConfig| config.build().is_ok();
+    assert!(!builds(SpectrogramConfig::default().min_freq(f32::NAN)));
+    assert!(!builds(SpectrogramConfig::default().max_freq(f32::NAN)));
+    assert!(!builds(SpectrogramConfig::default().db_floor(f32::NAN)));
+    assert!(!builds(
+        SpectrogramConfig::default().db_floor(f32::NEG_INFINITY)
+    ));
+}
+
+#[test]
+fn toml_file_sets_the_fields_it_names() {
+    let path = common::temp_path("config.toml");
+    std::fs::write(&path, "window = 4096\ncolormap = \"magma\"\ngamma = 2.2\n").unwrap();
+    let config = SpectrogramConfig::from_toml_file(&path).unwrap();
+    assert_eq!(config.window, 4096);
+    assert_eq!(config.colormap, Colormap::Magma);
+    assert_eq!(config.gamma, 2.2);
+    // The hop follows the window when the file leaves it out, other fields keep their defaults
+    assert_eq!(config.hop, 4096);
+    assert_eq!(config.max_freq, SpectrogramConfig::default().max_freq);
+
+    std::fs::write(&path, "windw = 4096\n").unwrap();
+    let result = SpectrogramConfig::from_toml_file(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(FftImageError::ConfigFile(..))));
+}
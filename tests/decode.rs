@@ -0,0 +1,264 @@
+use std::io::Cursor;
+
+use fft_image_extractor::{
+    extract_samples, open_source, open_source_with, probe_tracks, trim_samples, trim_silence,
+    write_wav, ChannelSelection, DecodeOptions, FftImageError, RawFormat, RawPcm, SampleStream,
+    SUPPORTED_CONTAINERS,
+};
+
+mod common;
+
+const STEREO_I16: RawPcm = RawPcm {
+    sample_rate: 8000,
+    channels: 2,
+    format: RawFormat::I16le,
+};
+
+#[test]
+fn raw_pcm_is_decoded_without_probing() {
+    let frames: [[i16; 2]; 3] = [[0, 16384], [-16384, 8192], [32767, -32768]];
+    let bytes: Vec<u8> = frames
+        .iter()
+        .flatten()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+
+    let options = DecodeOptions::default()
+        .channel(ChannelSelection::Index(1))
+        .raw(Some(STEREO_I16));
+    let mut stream = SampleStream::open(Box::new(Cursor::new(bytes)), &options).unwrap();
+    assert_eq!(stream.sample_rate(), 8000);
+    assert_eq!(stream.reported_len(), Some(3));
+    assert_eq!(stream.read_all().unwrap().samples, [0.5, 0.25, -1.0]);
+}
+
+#[test]
+fn mid_and_side_mix_the_two_channels() {
+    let frames: [[i16; 2]; 2] = [[16384, 8192], [-16384, 16384]];
+    let bytes: Vec<u8> = frames
+        .iter()
+        .flatten()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    let decode = |channel| {
+        let options = DecodeOptions::default()
+            .channel(channel)
+            .raw(Some(STEREO_I16));
+        let mut stream =
+            SampleStream::open(Box::new(Cursor::new(bytes.clone())), &options).unwrap();
+        stream.read_all().map(|audio| audio.samples)
+    };
+    assert_eq!(decode(ChannelSelection::Mid).unwrap(), [0.375, 0.0]);
+    assert_eq!(decode(ChannelSelection::Side).unwrap(), [0.125, -0.5]);
+
+    // A single channel has no side signal
+    let options = DecodeOptions::default()
+        .channel(ChannelSelection::Side)
+        .raw(Some(RawPcm {
+            channels: 1,
+            ..STEREO_I16
+        }));
+    let err = SampleStream::open(Box::new(Cursor::new(bytes)), &options).err();
+    assert!(matches!(
+        err,
+        Some(FftImageError::NotStereo(ChannelSelection::Side))
+    ));
+}
+
+#[test]
+fn raw_pcm_of_partial_frames_is_rejected() {
+    let options = DecodeOptions::default().raw(Some(STEREO_I16));
+    let err = SampleStream::open(Box::new(Cursor::new(vec![0u8; 10])), &options).err();
+    assert!(matches!(
+        err,
+        Some(FftImageError::RawLength {
+            len: 10,
+            frame_size: 4
+        })
+    ));
+}
+
+#[test]
+fn trim_silence_keeps_the_sound_between_quiet_ends() {
+    let mut samples = vec![0.0; 100];
+    samples.extend([0.5, 0.0001, -0.2]);
+    samples.extend(vec![0.0005; 50]);
+
+    assert_eq!(trim_silence(&samples, -60.0), [0.5, 0.0001, -0.2]);
+    assert!(trim_silence(&[0.0; 10], -60.0).is_empty());
+}
+
+#[test]
+fn trim_samples_rejects_nan_bounds() {
+    let samples: Vec<f32> = (0..8000).map(|n| n as f32).collect();
+    assert_eq!(
+        trim_samples(&samples, 8000, Some(0.25), None).unwrap(),
+        &samples[2000..]
+    );
+    assert!(trim_samples(&samples, 8000, Some(f64::NAN), None).is_err());
+    assert!(trim_samples(&samples, 8000, None, Some(f64::NAN)).is_err());
+}
+
+#[test]
+fn max_duration_keeps_the_beginning() {
+    let path = common::temp_path("max-duration.wav");
+    let tone = common::tone(440.0, 8000, 8000);
+    write_wav(&path, &tone, 8000).unwrap();
+
+    let decode = |max_duration| {
+        let options = DecodeOptions::default().max_duration(Some(max_duration));
+        extract_samples(open_source(path.to_str().unwrap()).unwrap(), &options).unwrap()
+    };
+    let half = decode(0.5);
+    let exact = decode(1.0);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(half.samples, tone[..4000]);
+    assert!(half.truncated);
+    // An input that fits exactly was decoded whole
+    assert_eq!(exact.samples, tone);
+    assert!(!exact.truncated);
+}
+
+/// Ogg page of the logical stream `serial` holding `packet`, `header_type` flagging the first or
+/// last page
+fn ogg_page(serial: u32, sequence: u32, header_type: u8, granule: u64, packet: &[u8]) -> Vec<u8> {
+    let mut page = b"OggS\0".to_vec();
+    page.push(header_type);
+    page.extend(granule.to_le_bytes());
+    page.extend(serial.to_le_bytes());
+    page.extend(sequence.to_le_bytes());
+    page.extend([0; 4]);
+    page.push(1);
+    page.push(packet.len() as u8);
+    page.extend(packet);
+
+    // CRC-32 with the polynomial 0x04c11db7, unreflected and without a final xor
+    let crc = page.iter().fold(0u32, |crc, &byte| {
+        (0..8).fold(crc ^ (u32::from(byte) << 24), |crc, _| {
+            if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            }
+        })
+    });
+    page[22..26].copy_from_slice(&crc.to_le_bytes());
+    page
+}
+
+/// Identification and comment headers of a mono Opus stream
+fn opus_headers() -> [Vec<u8>; 2] {
+    let mut head = b"OpusHead\x01\x01".to_vec();
+    head.extend(0u16.to_le_bytes());
+    head.extend(48_000u32.to_le_bytes());
+    head.extend([0, 0, 0]);
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend(0u32.to_le_bytes());
+    tags.extend(0u32.to_le_bytes());
+    [head, tags]
+}
+
+/// Opus packet of a 20 ms frame of silence
+const OPUS_SILENCE: [u8; 3] = [0xf8, 0xff, 0xfe];
+
+#[test]
+fn undecodable_codecs_are_named() {
+    let [head, tags] = opus_headers();
+    let mut ogg = ogg_page(1, 0, 0x02, 0, &head);
+    ogg.extend(ogg_page(1, 1, 0x00, 0, &tags));
+    ogg.extend(ogg_page(1, 2, 0x04, 960, &OPUS_SILENCE));
+
+    let options = DecodeOptions::default().hint(Some("opus".to_string()));
+    let err = SampleStream::open(Box::new(Cursor::new(ogg)), &options)
+        .err()
+        .unwrap();
+    assert!(matches!(
+        &err,
+        FftImageError::UnsupportedCodec {
+            codec,
+            feature: None
+        } if codec == "Opus"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "the input is encoded with Opus, which Symphonia has no decoder for"
+    );
+}
+
+#[test]
+fn unrecognized_containers_point_to_the_missing_feature() {
+    let options = DecodeOptions::default().hint(Some("m4a".to_string()));
+    let garbage = vec![0x5a; 4096];
+    let err = SampleStream::open(Box::new(Cursor::new(garbage)), &options)
+        .err()
+        .unwrap();
+    assert!(matches!(
+        &err,
+        FftImageError::UnrecognizedFormat { extension: Some(extension) } if extension == "m4a"
+    ));
+    assert!(err.to_string().contains(SUPPORTED_CONTAINERS));
+    assert!(err
+        .to_string()
+        .ends_with(".m4a files need Symphonia's `isomp4` feature"));
+}
+
+#[test]
+fn track_picks_a_stream_of_the_container() {
+    // An Opus stream next to one in a format the Ogg reader does not know
+    let [head, tags] = opus_headers();
+    let mut ogg = ogg_page(1, 0, 0x02, 0, &head);
+    ogg.extend(ogg_page(2, 0, 0x02, 0, b"unknown"));
+    ogg.extend(ogg_page(1, 1, 0x00, 0, &tags));
+    ogg.extend(ogg_page(1, 2, 0x04, 960, &OPUS_SILENCE));
+    ogg.extend(ogg_page(2, 1, 0x04, 0, b"unknown"));
+
+    let tracks = probe_tracks(Box::new(Cursor::new(ogg.clone())), Some("ogg")).unwrap();
+    let ids: Vec<u32> = tracks.iter().map(|track| track.id).collect();
+    assert_eq!(ids, [1, 2]);
+
+    let open = |track| {
+        let options = DecodeOptions::default()
+            .hint(Some("ogg".to_string()))
+            .track(track);
+        SampleStream::open(Box::new(Cursor::new(ogg.clone())), &options).err()
+    };
+    let codec = |track| match open(track) {
+        Some(FftImageError::UnsupportedCodec { codec, .. }) => codec,
+        err => panic!("{err:?}"),
+    };
+    assert_eq!(codec(None), "Opus");
+    assert_eq!(codec(Some(1)), "Opus");
+    assert!(codec(Some(2)).starts_with("an unknown codec"));
+    assert!(matches!(
+        open(Some(3)),
+        Some(FftImageError::TrackNotFound {
+            requested: 3,
+            available
+        }) if available == [1, 2]
+    ));
+}
+
+#[test]
+fn mapped_and_read_files_decode_alike() {
+    let path = common::temp_path("mmap.wav");
+    let tone = common::tone(440.0, 8000, 8000);
+    write_wav(&path, &tone, 8000).unwrap();
+    let size = std::fs::metadata(&path).unwrap().len();
+
+    // Thresholds on either side of the file size, so that it is mapped once and read once
+    let decode = |mmap_threshold| {
+        let source = open_source_with(path.to_str().unwrap(), mmap_threshold).unwrap();
+        let options = DecodeOptions::default().hint(Some("wav".to_string()));
+        SampleStream::open(source, &options)
+            .unwrap()
+            .read_all()
+            .unwrap()
+            .samples
+    };
+    let mapped = decode(size);
+    let read = decode(size + 1);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(mapped, tone);
+    assert_eq!(read, tone);
+}
@@ -0,0 +1,18 @@
+use fft_image_extractor::median_filter;
+
+#[test]
+fn median_filter_removes_specks_and_keeps_edges() {
+    // A tone filling the lower half of the bins, with a click in one frame and a speck of noise
+    let mut frames: Vec<Vec<f32>> = (0..8)
+        .map(|_| (0..10).map(|bin| if bin < 5 { 1.0 } else { 0.0 }).collect())
+        .collect();
+    frames[3] = vec![1.0; 10];
+    frames[6][8] = 0.7;
+
+    median_filter(&mut frames, [3, 3]);
+
+    for frame in &frames {
+        assert_eq!(frame[..5], [1.0; 5]);
+        assert_eq!(frame[5..], [0.0; 5]);
+    }
+}
@@ -0,0 +1,21 @@
+use fft_image_extractor::{diff_spectrograms, DiffMode, SpectrogramConfig};
+
+mod common;
+
+#[test]
+fn diff_pairs_frames_from_the_start() {
+    let config = SpectrogramConfig::default();
+    let frequencies = vec![0.0, 1000.0];
+    let a = common::spectrogram(
+        &config,
+        frequencies.clone(),
+        vec![vec![1.0, 0.25], vec![0.5, 0.5], vec![1.0, 1.0]],
+    );
+    let b = common::spectrogram(&config, frequencies, vec![vec![0.5, 0.75], vec![0.5, 0.0]]);
+
+    let abs = diff_spectrograms(&a, &b, DiffMode::Abs);
+    assert_eq!(abs.frames, [[0.5, 0.5], [0.0, 0.5]]);
+    let signed = diff_spectrograms(&a, &b, DiffMode::Signed);
+    assert_eq!(signed.frames, [[0.5, -0.5], [0.0, 0.5]]);
+    assert_eq!(signed.frequencies, a.frequencies);
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use fft_image_extractor::{
+    write_npy, write_npy_freqs, write_pitch_csv, write_pitch_json, SpectrogramConfig,
+};
+
+mod common;
+
+/// Header dictionary and `float32` values of the version 1.0 `.npy` file at `path`
+fn read_npy(path: &Path) -> (String, Vec<f32>) {
+    let bytes = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(&bytes[..8], b"\x93NUMPY\x01\x00");
+    let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+    // The data starts 64-byte aligned, right after the newline ending the padded header
+    let data_start = 10 + header_len;
+    assert_eq!(data_start % 64, 0);
+    let header = std::str::from_utf8(&bytes[10..data_start]).unwrap();
+    assert!(header.ends_with('\n'));
+
+    let values = bytes[data_start..]
+        .chunks_exact(4)
+        .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+        .collect();
+    (header.trim_end().to_string(), values)
+}
+
+#[test]
+fn npy_holds_the_frames_in_c_order() {
+    let data = common::spectrogram(
+        &SpectrogramConfig::default(),
+        vec![0.0, 1000.0],
+        vec![vec![0.5, 1.0], vec![0.25, -2.0], vec![3.0, 0.0]],
+    );
+
+    let path = common::temp_path("frames.npy");
+    write_npy(&path, &data).unwrap();
+    let (header, values) = read_npy(&path);
+    assert_eq!(
+        header,
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 2), }"
+    );
+    assert_eq!(values, [0.5, 1.0, 0.25, -2.0, 3.0, 0.0]);
+
+    let path = common::temp_path("freqs.npy");
+    write_npy_freqs(&path, &data).unwrap();
+    let (header, values) = read_npy(&path);
+    assert_eq!(
+        header,
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (2,), }"
+    );
+    assert_eq!(values, [0.0, 1000.0]);
+}
+
+#[test]
+fn pitch_exports_hold_a_row_per_frame() {
+    let config = SpectrogramConfig::default()
+        .sample_rate(8000)
+        .max_freq(4000.0)
+        .hop(400)
+        .time_offset(1.0)
+        .build()
+        .unwrap();
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 1000.0, 2000.0, 3000.0],
+        vec![
+            vec![0.0, 1.0, 0.0, 0.0],
+            vec![0.0; 4],
+            vec![0.0, 0.0, 0.0, 0.5],
+        ],
+    );
+
+    let path = common::temp_path("pitch.csv");
+    write_pitch_csv(&path, &data, &config).unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows, ["time,pitch", "1,1000", "1.05,", "1.1,3000"]);
+
+    let path = common::temp_path("pitch.json");
+    write_pitch_json(&path, &data, &config).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            {"time": 1.0, "pitch": 1000.0},
+            {"time": 1.05, "pitch": null},
+            {"time": 1.1, "pitch": 3000.0},
+        ])
+    );
+}
@@ -0,0 +1,37 @@
+use fft_image_extractor::{spectral_flux, spectral_rolloff, SpectrogramConfig, SpectrogramData};
+
+mod common;
+
+fn data(frames: Vec<Vec<f32>>) -> SpectrogramData {
+    common::spectrogram(
+        &SpectrogramConfig::default(),
+        vec![0.0, 1000.0, 2000.0, 3000.0, 4000.0],
+        frames,
+    )
+}
+
+#[test]
+fn rolloff_holds_most_of_the_energy() {
+    let data = data(vec![
+        // 80% of the energy up to 1 kHz, 90% up to 3 kHz
+        vec![0.0, 0.8f32.sqrt(), 0.0, 0.1f32.sqrt(), 0.1f32.sqrt()],
+        vec![0.0, 0.0, 1.0, 0.0, 0.0],
+        vec![0.0; 5],
+    ]);
+    assert_eq!(
+        spectral_rolloff(&data),
+        vec![Some(3000.0), Some(2000.0), None]
+    );
+}
+
+#[test]
+fn flux_measures_the_change_from_the_previous_frame() {
+    let data = data(vec![
+        vec![0.0, 1.0, 0.0, 0.0, 0.0],
+        vec![0.0, 1.0, 0.0, 0.0, 0.0],
+        vec![0.0, 0.0, 0.0, 3.0, 4.0],
+    ]);
+    let flux = spectral_flux(&data);
+    assert_eq!(flux[..2], [0.0, 0.0]);
+    assert!((flux[2] - 26f32.sqrt()).abs() < 1e-6);
+}
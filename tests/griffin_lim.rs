@@ -0,0 +1,34 @@
+use fft_image_extractor::{compute_spectrogram, reconstruct, SpectrogramConfig};
+
+mod common;
+
+/// Cosine similarity of the spectrograms of `a` and `b`, 1 when they have the same shape
+fn similarity(a: &[f32], b: &[f32], config: &SpectrogramConfig) -> f32 {
+    let [a, b] = [a, b].map(|samples| compute_spectrogram(samples, config).unwrap().frames);
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().flatten().zip(b.iter().flatten()) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    dot / (norm_a * norm_b).sqrt()
+}
+
+#[test]
+fn zero_padded_spectrograms_are_inverted_at_their_fft_length() {
+    let config = SpectrogramConfig::default()
+        .sample_rate(8000)
+        .window(256)
+        .hop(64)
+        .max_freq(4000.0);
+    let samples = common::tone(1015.0, 8000, 8000);
+
+    for fft_size in [None, Some(1024), Some(2048)] {
+        let config = config.clone().fft_size(fft_size).build().unwrap();
+        let data = compute_spectrogram(&samples, &config).unwrap();
+        let resynthesized = reconstruct(&data, &config);
+        assert_eq!(resynthesized.len(), samples.len());
+        let similarity = similarity(&samples, &resynthesized, &config);
+        assert!(similarity > 0.95, "{fft_size:?}: {similarity}");
+    }
+}
@@ -0,0 +1,79 @@
+use fft_image_extractor::{load_matrix, save_matrix, FftImageError, SpectrogramConfig};
+
+mod common;
+
+#[test]
+fn matrix_round_trips_and_rejects_other_versions() {
+    let config = SpectrogramConfig::default()
+        .sample_rate(22_050)
+        .window(1024)
+        .hop(256);
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 21.5, 43.0],
+        vec![vec![0.25, 1.0, 0.0], vec![0.5, 0.125, 0.75]],
+    );
+    let path = common::temp_path("matrix.bin");
+    save_matrix(&path, &data).unwrap();
+
+    let loaded = load_matrix(&path).unwrap();
+    assert_eq!(
+        (loaded.sample_rate, loaded.window, loaded.hop),
+        (22_050, 1024, 256)
+    );
+    assert_eq!(loaded.frequencies, data.frequencies);
+    assert_eq!(loaded.frames, data.frames);
+
+    // The format version follows the magic bytes
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[4..8].copy_from_slice(&99u32.to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+    let result = load_matrix(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(FftImageError::InvalidMatrix(_))));
+}
+
+#[test]
+fn matrix_rejects_truncated_and_corrupt_headers() {
+    let config = SpectrogramConfig::default().sample_rate(8000).window(4);
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 2000.0, 4000.0],
+        vec![vec![0.25, 1.0, 0.0]],
+    );
+    let path = common::temp_path("corrupt-matrix.bin");
+    save_matrix(&path, &data).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+
+    let field = |offset: usize, value: u32| {
+        let mut bytes = bytes.clone();
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        bytes
+    };
+    let corrupt = [
+        bytes[..20].to_vec(),
+        bytes[..bytes.len() - 4].to_vec(),
+        [bytes.as_slice(), &[0; 4]].concat(),
+        field(8, 0),
+        field(12, 0),
+        field(12, 3),
+        field(16, 0),
+        // A 28-byte header claiming more bins and frames than memory holds
+        [
+            &bytes[..20],
+            &u32::MAX.to_le_bytes(),
+            &u32::MAX.to_le_bytes(),
+        ]
+        .concat(),
+    ];
+    for bytes in corrupt {
+        std::fs::write(&path, &bytes).unwrap();
+        let result = load_matrix(&path).err();
+        assert!(
+            matches!(result, Some(FftImageError::InvalidMatrix(_))),
+            "{} bytes gave {result:?}",
+            bytes.len()
+        );
+    }
+    std::fs::remove_file(&path).unwrap();
+}
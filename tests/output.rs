@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use exr::prelude::read_first_flat_layer_from_file;
+use fft_image_extractor::{
+    output_format, png_params, read_png_params, render_image, save_image, write_exr, write_png,
+    BitDepth, FftImageError, FrequencyScale, Layout, PngCompression, SpectrogramConfig,
+};
+use image::ImageFormat;
+
+mod common;
+
+#[test]
+fn png_records_the_analysis_parameters() {
+    let config = SpectrogramConfig::default().window(1024).build().unwrap();
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 100.0, 1000.0],
+        vec![vec![0.0, 1.0, 0.5]; 4],
+    );
+    let path = common::temp_path("params.png");
+    save_image(
+        &render_image(&data, &config),
+        &path,
+        ImageFormat::Png,
+        &config,
+    )
+    .unwrap();
+
+    let params = read_png_params(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(params[0].0, "Software");
+    let expected: Vec<(String, String)> = png_params(&config)
+        .into_iter()
+        .map(|(keyword, value)| (keyword.to_string(), value))
+        .collect();
+    assert_eq!(params[1..], expected);
+    assert!(expected.contains(&("window".to_string(), "1024".to_string())));
+}
+
+/// `FLEVEL` field of the zlib header opening the first `IDAT` chunk of `png`, from 0 for the
+/// fastest compression to 3 for the strongest
+fn compression_level(png: &[u8]) -> u8 {
+    let mut chunks = &png[8..];
+    loop {
+        let len = u32::from_be_bytes(chunks[..4].try_into().unwrap()) as usize;
+        if &chunks[4..8] == b"IDAT" {
+            return chunks[9] >> 6;
+        }
+        chunks = &chunks[12 + len..];
+    }
+}
+
+#[test]
+fn png_compression_keeps_the_pixels() {
+    let config = SpectrogramConfig::default();
+    let data = common::spectrogram(
+        &config,
+        (0..64).map(|bin| bin as f32 * 100.0).collect(),
+        (0..32)
+            .map(|frame| {
+                (0..64)
+                    .map(|bin| ((frame * bin) % 7) as f32 / 7.0)
+                    .collect()
+            })
+            .collect(),
+    );
+    let img = render_image(&data, &config);
+
+    let encode = |compression| {
+        let mut bytes = Vec::new();
+        write_png(
+            &img,
+            &mut bytes,
+            &config.clone().png_compression(compression),
+        )
+        .unwrap();
+        bytes
+    };
+    let fast = encode(PngCompression::Fast);
+    let default = encode(PngCompression::Default);
+    let best = encode(PngCompression::Best);
+    // The zlib stream of the image data records the deflate level it was written at
+    assert_eq!(
+        [&fast, &default, &best].map(|bytes| compression_level(bytes)),
+        [0, 2, 3]
+    );
+    assert!(default.len() < fast.len() && best.len() < fast.len());
+    for bytes in [fast, default, best] {
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!(decoded.to_rgba8(), img.to_rgba8());
+    }
+}
+
+#[test]
+fn output_format_follows_the_extension() {
+    let format = |name: &str, bit_depth| output_format(Path::new(name), bit_depth);
+    assert_eq!(
+        format("a.tiff", BitDepth::Sixteen).unwrap(),
+        ImageFormat::Tiff
+    );
+    assert_eq!(format("a.jpg", BitDepth::Eight).unwrap(), ImageFormat::Jpeg);
+    assert!(matches!(
+        format("a.jpg", BitDepth::Sixteen),
+        Err(FftImageError::FormatBitDepth(ImageFormat::Jpeg))
+    ));
+    assert!(matches!(
+        format("a.gif", BitDepth::Eight),
+        Err(FftImageError::UnsupportedFormat(_))
+    ));
+
+    // Lossless formats other than PNG keep the pixels
+    let config = SpectrogramConfig::default().row_height(16);
+    let img = render_image(
+        &common::spectrogram(&config, vec![0.0, 1000.0], vec![vec![0.5, 1.0]; 8]),
+        &config,
+    );
+    for name in ["format.tiff", "format.bmp"] {
+        let path = common::temp_path(name);
+        let format = output_format(&path, BitDepth::Eight).unwrap();
+        save_image(&img, &path, format, &config).unwrap();
+        let saved = image::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.to_rgba8(), img.to_rgba8(), "{name}");
+    }
+}
+
+#[test]
+fn exr_holds_the_raw_magnitudes() {
+    let config = SpectrogramConfig::default()
+        .row_height(16)
+        .layout(Layout::Strip)
+        .scale(FrequencyScale::Linear)
+        .build()
+        .unwrap();
+    // Every frame one level across bins filling every row, well past the 0..1 range of an image
+    let mut frequencies: Vec<f32> = (0..=20).map(|bin| bin as f32 * 500.0).collect();
+    frequencies.insert(1, 100.0);
+    let frames = (0..8).map(|frame| vec![frame as f32 * 1.5; 22]).collect();
+    let data = common::spectrogram(&config, frequencies, frames);
+    let path = common::temp_path("magnitudes.exr");
+    write_exr(&path, &data, &config).unwrap();
+
+    let exr = read_first_flat_layer_from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let layer = &exr.layer_data;
+    assert_eq!((layer.size.width(), layer.size.height()), (8, 16));
+    let [channel] = &layer.channel_data.list[..] else {
+        panic!("{} channels", layer.channel_data.list.len());
+    };
+    assert_eq!(channel.name.to_string(), "Y");
+    for (i, value) in channel.sample_data.values_as_f32().enumerate() {
+        assert_eq!(value, (i % 8) as f32 * 1.5, "pixel {i}");
+    }
+}
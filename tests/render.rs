@@ -0,0 +1,456 @@
+use fft_image_extractor::{
+    apply_mask, compute_spectrogram, contact_sheet, frame_count, nearest_power_of_two_below,
+    render, render_image, spectral_centroid, Colormap, FrequencyScale, ImageLayout, Layout,
+    Orientation, PixelFormat, SpectrogramConfig, SpectrogramData,
+};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+mod common;
+
+/// Gray spectrogram of `frames` frames over bins every 500 Hz up to 10 kHz, every frame louder
+/// than the last and every bin of a frame a different level
+fn ramp(config: &SpectrogramConfig, frames: usize) -> SpectrogramData {
+    let frequencies: Vec<f32> = (0..=20).map(|bin| bin as f32 * 500.0).collect();
+    let frames = (0..frames)
+        .map(|frame| {
+            (0..frequencies.len())
+                .map(|bin| ((frame + 1) * (bin + 1) % 23) as f32 / 22.0)
+                .collect()
+        })
+        .collect();
+    common::spectrogram(config, frequencies, frames)
+}
+
+#[test]
+fn nearest_power_of_two_below_includes_exact_powers() {
+    assert_eq!(nearest_power_of_two_below(1), 1);
+    assert_eq!(nearest_power_of_two_below(2), 2);
+    assert_eq!(nearest_power_of_two_below(3), 2);
+    assert_eq!(nearest_power_of_two_below(4), 4);
+    assert_eq!(nearest_power_of_two_below(5), 4);
+    assert_eq!(nearest_power_of_two_below(1024), 1024);
+}
+
+#[test]
+fn wrapped_layout_has_no_blank_rows() {
+    let config = SpectrogramConfig::default().build().unwrap();
+
+    // Frame counts that fill the last row exactly, and ones that leave it partly filled
+    for frames in [1, 3, 4, 7, 16, 17, 64, 100] {
+        let samples = vec![0.25; (frames - 1) * config.hop + config.window];
+        assert_eq!(
+            frame_count(samples.len(), config.window, config.hop),
+            frames
+        );
+
+        let layout = ImageLayout::new(frames, &config);
+        let populated = frames.div_ceil(layout.w) as u32;
+        assert_eq!(layout.row_count, populated, "{frames} frames");
+
+        let data = compute_spectrogram(&samples, &config).unwrap();
+        let img = render(&data, &config);
+        assert_eq!(
+            img.height(),
+            populated * config.row_height,
+            "{frames} frames"
+        );
+    }
+}
+
+#[test]
+fn bins_sharing_a_row_are_averaged() {
+    let config = SpectrogramConfig::default()
+        .min_freq(100.0)
+        .max_freq(5000.0)
+        .row_height(4)
+        .format(PixelFormat::Gray)
+        .build()
+        .unwrap();
+    // The first two bins land on the top row, the last one on the bottom edge
+    let data = common::spectrogram(
+        &config,
+        vec![100.0, 100.5, 5000.0],
+        vec![vec![1.0, 0.0, 0.2]],
+    );
+
+    let img = render_image(&data, &config).into_luma8();
+    assert_eq!(img.dimensions(), (1, 4));
+    assert!(img.pixels().all(|pixel| pixel.0 == [127]));
+}
+
+#[test]
+fn centroid_is_drawn_at_the_mean_frequency() {
+    let config = SpectrogramConfig::default()
+        .min_freq(100.0)
+        .max_freq(4000.0)
+        .scale(FrequencyScale::Linear)
+        .row_height(40)
+        .layout(Layout::Strip)
+        .format(PixelFormat::Gray)
+        .centroid(true)
+        .build()
+        .unwrap();
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 1000.0, 2000.0, 3000.0, 4000.0],
+        vec![
+            vec![0.0, 0.1, 0.0, 0.1, 0.0],
+            vec![0.0; 5],
+            vec![0.0, 0.0, 0.0, 0.1, 0.0],
+        ],
+    );
+    assert_eq!(
+        spectral_centroid(&data),
+        vec![Some(2000.0), None, Some(3000.0)]
+    );
+
+    let img = render_image(&data, &config).into_luma8();
+    let line_rows = |x: u32| -> Vec<u32> {
+        (0..img.height())
+            .filter(|&y| img.get_pixel(x, y).0 == [255])
+            .collect()
+    };
+    assert_eq!(line_rows(0), [20]);
+    // The silent frame breaks the line rather than joining its neighbors
+    assert!(line_rows(1).is_empty());
+    assert_eq!(line_rows(2), [30]);
+}
+
+#[test]
+fn contact_sheet_tiles_captioned_thumbnails() {
+    let white = DynamicImage::ImageRgba8(RgbaImage::from_pixel(300, 40, Rgba([255; 4])));
+    let images: Vec<(String, DynamicImage)> = ["a.wav", "b.wav", "c.wav"]
+        .into_iter()
+        .map(|name| (name.to_string(), white.clone()))
+        .collect();
+
+    let sheet = contact_sheet(&images, 2, [64, 32]);
+    // Two rows of two tiles, each a thumbnail over a caption, with gaps around them
+    let tile_height = 32 + 2 + 7 + 4;
+    assert_eq!(sheet.dimensions(), (4 + 2 * (64 + 4), 4 + 2 * tile_height));
+    // The third thumbnail starts the second row, and the last tile stays empty
+    assert_eq!(sheet.get_pixel(4, 4 + tile_height), &Rgba([255; 4]));
+    assert_eq!(
+        sheet.get_pixel(4 + 68, 4 + tile_height),
+        &Rgba([0, 0, 0, 255])
+    );
+}
+
+#[test]
+fn mask_silences_bins_under_black_pixels() {
+    let config = SpectrogramConfig::default()
+        .min_freq(100.0)
+        .max_freq(4000.0)
+        .scale(FrequencyScale::Linear)
+        .build()
+        .unwrap();
+    let mut data = common::spectrogram(
+        &config,
+        vec![0.0, 1000.0, 2000.0, 3000.0],
+        vec![vec![1.0; 4]; 4],
+    );
+    // A mask of half the frames, black over 2 kHz in its second column
+    let mut mask = RgbaImage::from_pixel(2, 4, Rgba([255; 4]));
+    mask.put_pixel(1, 2, Rgba([0, 0, 0, 255]));
+
+    let masked = apply_mask(&mut data, &DynamicImage::ImageRgba8(mask), &config);
+    assert_eq!(masked, 2);
+    for (frame, magnitudes) in data.frames.iter().enumerate() {
+        let expected = if frame >= 2 { 0.0 } else { 1.0 };
+        assert_eq!(magnitudes, &[1.0, 1.0, expected, 1.0]);
+    }
+}
+
+#[test]
+fn pitch_track_is_drawn_apart_from_the_centroid() {
+    let config = SpectrogramConfig::default()
+        .min_freq(100.0)
+        .max_freq(4000.0)
+        .scale(FrequencyScale::Linear)
+        .row_height(40)
+        .layout(Layout::Strip)
+        .centroid(true)
+        .pitch_track(true)
+        .build()
+        .unwrap();
+    // The centroid lies at 2500 Hz, between the bins, the pitch on the louder one
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 1000.0, 2000.0, 3000.0, 4000.0],
+        vec![vec![0.0, 0.1, 0.0, 0.3, 0.0]],
+    );
+
+    let img = render_image(&data, &config).into_rgba8();
+    let rows_in = |color: [u8; 4]| -> Vec<u32> {
+        (0..img.height())
+            .filter(|&y| img.get_pixel(0, y).0 == color)
+            .collect()
+    };
+    assert_eq!(rows_in([255; 4]), [25]);
+    assert_eq!(rows_in([255, 64, 64, 255]), [30]);
+}
+
+#[test]
+fn strip_layout_unwraps_the_rows() {
+    let config = SpectrogramConfig::default()
+        .row_height(16)
+        .format(PixelFormat::Gray);
+    let data = ramp(&config, 40);
+    // 40 frames wrap into rows of 8
+    let wrapped = render_image(&data, &config.clone().build().unwrap()).into_luma8();
+    let strip = render_image(&data, &config.layout(Layout::Strip).build().unwrap()).into_luma8();
+    assert_eq!(wrapped.dimensions(), (8, 5 * 16));
+    assert_eq!(strip.dimensions(), (40, 16));
+
+    for (x, y, pixel) in strip.enumerate_pixels() {
+        assert_eq!(
+            pixel,
+            wrapped.get_pixel(x % 8, x / 8 * 16 + y),
+            "({x}, {y})"
+        );
+    }
+}
+
+#[test]
+fn wrap_width_sets_the_columns_per_row() {
+    let config = SpectrogramConfig::default()
+        .row_height(16)
+        .wrap_width(Some(6))
+        .format(PixelFormat::Gray)
+        .build()
+        .unwrap();
+    let layout = ImageLayout::new(20, &config);
+    assert_eq!((layout.w, layout.row_count), (6, 4));
+    let img = render_image(&ramp(&config, 20), &config).into_luma8();
+    assert_eq!(img.dimensions(), (6, 4 * 16));
+
+    // Wider than the whole input, a single row holds every frame
+    let config = config.wrap_width(Some(50)).build().unwrap();
+    let layout = ImageLayout::new(20, &config);
+    assert_eq!((layout.w, layout.row_count), (20, 1));
+}
+
+#[test]
+fn vertical_orientation_transposes_the_image() {
+    let config = SpectrogramConfig::default()
+        .row_height(16)
+        .format(PixelFormat::Gray);
+    let data = ramp(&config, 40);
+    let horizontal = render_image(&data, &config.clone().build().unwrap()).into_luma8();
+    let vertical = render_image(
+        &data,
+        &config.orientation(Orientation::Vertical).build().unwrap(),
+    )
+    .into_luma8();
+
+    assert_eq!(vertical.dimensions(), (5 * 16, 8));
+    for (x, y, pixel) in horizontal.enumerate_pixels() {
+        assert_eq!(pixel, vertical.get_pixel(y, x), "({x}, {y})");
+    }
+}
+
+#[test]
+fn invert_flips_the_ramp_through_the_colormap() {
+    // Every row holds a bin, none is left in the color of silence
+    let config = SpectrogramConfig::default()
+        .scale(FrequencyScale::Linear)
+        .row_height(8)
+        .colormap(Colormap::Viridis);
+    let data = ramp(&config, 16);
+    let mut flipped = ramp(&config, 16);
+    for magnitude in flipped.frames.iter_mut().flatten() {
+        *magnitude = 1.0 - *magnitude;
+    }
+
+    let inverted = render_image(&data, &config.clone().invert(true).build().unwrap());
+    assert_eq!(inverted, render_image(&flipped, &config.build().unwrap()));
+}
+
+/// Spectrogram of `frames` silent frames, on which only the annotations show up
+fn silence(config: &SpectrogramConfig, frames: usize) -> SpectrogramData {
+    common::spectrogram(
+        config,
+        vec![0.0, 5000.0, 10_000.0],
+        vec![vec![0.0; 3]; frames],
+    )
+}
+
+#[test]
+fn axes_tick_the_decades_on_the_frequency_scale() {
+    let config = SpectrogramConfig::default()
+        .row_height(64)
+        .layout(Layout::Strip)
+        .format(PixelFormat::Gray)
+        .axes(true)
+        .build()
+        .unwrap();
+    let img = render_image(&silence(&config, 10), &config).into_luma8();
+    let margin = img.width() - 10;
+
+    let ticks: Vec<u32> = (0..64)
+        .filter(|&y| img.get_pixel(margin - 1, y).0 == [255])
+        .collect();
+    let expected: Vec<u32> = [20.0, 100.0, 1000.0, 10_000.0]
+        .iter()
+        .map(|&freq| {
+            FrequencyScale::Log
+                .row_for(freq, 20.0, 10_000.0, 64)
+                .unwrap()
+                .min(63)
+        })
+        .collect();
+    assert_eq!(ticks, expected);
+    // Labels sit left of the ticks, the spectrogram right of them
+    assert!((0..margin - 4).any(|x| (0..64).any(|y| img.get_pixel(x, y).0 == [255])));
+    assert!((margin..img.width()).all(|x| (0..64).all(|y| img.get_pixel(x, y).0 == [0])));
+}
+
+#[test]
+fn grid_marks_the_octaves_of_one_kilohertz() {
+    let config = SpectrogramConfig::default()
+        .row_height(64)
+        .layout(Layout::Strip)
+        .format(PixelFormat::Gray)
+        .grid(true)
+        .grid_opacity(1.0)
+        .build()
+        .unwrap();
+    let img = render_image(&silence(&config, 10), &config).into_luma8();
+    assert_eq!(img.dimensions(), (10, 64));
+
+    let lines: Vec<u32> = (0..64)
+        .filter(|&y| img.get_pixel(5, y).0 == [255])
+        .collect();
+    // 31.25 Hz up to 8 kHz
+    let expected: Vec<u32> = (-5..=3)
+        .map(|octave| {
+            FrequencyScale::Log
+                .row_for(1000.0 * 2f32.powi(octave), 20.0, 10_000.0, 64)
+                .unwrap()
+                .min(63)
+        })
+        .collect();
+    assert_eq!(lines, expected);
+    // The first time line falls on the first frame, the next one past the end of the image
+    assert!((0..64).all(|y| img.get_pixel(0, y).0 == [255]));
+}
+
+#[test]
+fn colorbar_ramps_from_the_loudest_level_at_the_top() {
+    let config = SpectrogramConfig::default()
+        .row_height(32)
+        .layout(Layout::Strip)
+        .format(PixelFormat::Gray)
+        .colorbar(true)
+        .build()
+        .unwrap();
+    let img = render_image(&silence(&config, 10), &config).into_luma8();
+    assert!(img.width() > 10);
+
+    // The bar starts a small gap right of the spectrogram
+    let bar: Vec<u8> = (0..32).map(|y| img.get_pixel(12, y).0[0]).collect();
+    assert_eq!((bar[0], bar[31]), (255, 0));
+    assert!(bar.windows(2).all(|pair| pair[0] >= pair[1]));
+}
+
+#[test]
+fn time_ruler_of_each_row_starts_at_its_first_frame() {
+    // Rows of 50 columns, 2.5 seconds each
+    let config = SpectrogramConfig::default()
+        .sample_rate(8000)
+        .window(1024)
+        .hop(400)
+        .max_freq(4000.0)
+        .row_height(32)
+        .wrap_width(Some(50))
+        .format(PixelFormat::Gray)
+        .axes(true)
+        .build()
+        .unwrap();
+    let img = render_image(&silence(&config, 100), &config).into_luma8();
+    let band_pitch = img.height() / 2;
+    let margin = img.width() - 50;
+    let ticks = |band: u32| -> Vec<u32> {
+        (0..50)
+            .filter(|&column| img.get_pixel(margin + column, band * band_pitch + 32).0 == [255])
+            .collect()
+    };
+
+    let first = ticks(0);
+    assert!(first.len() >= 2 && first[0] == 0);
+    let step = first[1];
+    assert!(first.windows(2).all(|pair| pair[1] - pair[0] == step));
+    // The second row goes on from 2.5 seconds, its ticks falling on the same multiples of the step
+    let expected: Vec<u32> = (0..50).filter(|column| (50 + column) % step == 0).collect();
+    assert_eq!(ticks(1), expected);
+    assert!(expected[0] != 0);
+}
+
+#[test]
+fn gain_boosts_quiet_bins_and_clips_loud_ones() {
+    let config = SpectrogramConfig::default()
+        .scale(FrequencyScale::Linear)
+        .row_height(2)
+        .layout(Layout::Strip)
+        .format(PixelFormat::Gray)
+        .gain(2.0)
+        .build()
+        .unwrap();
+    let data = common::spectrogram(
+        &config,
+        vec![0.0, 2000.0, 7000.0],
+        vec![vec![0.0, 0.25, 0.75]],
+    );
+
+    let img = render_image(&data, &config).into_luma8();
+    assert_eq!(img.get_pixel(0, 0).0, [127]);
+    assert_eq!(img.get_pixel(0, 1).0, [255]);
+}
+
+#[test]
+fn background_fills_quiet_and_unfilled_pixels() {
+    let background = [10, 20, 30, 255];
+    let config = SpectrogramConfig::default()
+        .row_height(8)
+        .wrap_width(Some(4))
+        .colormap(Colormap::Viridis)
+        .background(Some(background))
+        .build()
+        .unwrap();
+
+    // Two rows of four columns for six frames, the last two columns of the second row unfilled
+    let img = render_image(&silence(&config, 6), &config).into_rgba8();
+    assert_eq!(img.dimensions(), (4, 16));
+    let unfilled = |x: u32, y: u32| x >= 2 && y >= 8;
+    for (x, y, pixel) in img.enumerate_pixels() {
+        assert_eq!(pixel.0 == background, unfilled(x, y), "({x}, {y})");
+    }
+
+    // Below the threshold, silence takes the background color too
+    let config = config.threshold(Some(0.1)).build().unwrap();
+    let img = render_image(&silence(&config, 6), &config).into_rgba8();
+    assert!(img.pixels().all(|pixel| pixel.0 == background));
+}
+
+#[test]
+fn time_bins_and_row_height_fix_the_image_size() {
+    for (seconds, hop) in [(1, 256), (3, 64)] {
+        let config = SpectrogramConfig::default()
+            .sample_rate(8000)
+            .max_freq(4000.0)
+            .window(512)
+            .hop(hop)
+            .time_bins(Some(50))
+            .row_height(30)
+            .layout(Layout::Strip);
+        let samples = common::tone(440.0, 8000, seconds * 8000);
+        let build = |orientation| config.clone().orientation(orientation).build().unwrap();
+
+        let horizontal = build(Orientation::Horizontal);
+        let data = compute_spectrogram(&samples, &horizontal).unwrap();
+        assert_eq!(data.frame_count(), 50);
+        assert_eq!(render_image(&data, &horizontal).dimensions(), (50, 30));
+        let vertical = build(Orientation::Vertical);
+        assert_eq!(render_image(&data, &vertical).dimensions(), (30, 50));
+    }
+}
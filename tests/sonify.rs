@@ -0,0 +1,43 @@
+use fft_image_extractor::{
+    compute_spectrogram, dominant_pitch, image_to_spectrogram, sonify, FrequencyScale,
+    SpectrogramConfig,
+};
+use image::{DynamicImage, GrayImage, Luma};
+
+#[test]
+fn bright_row_sonifies_to_a_tone_at_its_frequency() {
+    let config = SpectrogramConfig::default()
+        .sample_rate(8000)
+        .window(512)
+        .hop(128)
+        .min_freq(100.0)
+        .max_freq(4000.0)
+        .scale(FrequencyScale::Linear)
+        .build()
+        .unwrap();
+    let mut img = GrayImage::new(40, 64);
+    for x in 0..40 {
+        img.put_pixel(x, 20, Luma([255]));
+    }
+    let img = DynamicImage::ImageLuma8(img);
+
+    // The bins read from the bright row, and the tone they should add up to
+    let data = image_to_spectrogram(&img, 10, &config);
+    let lit: Vec<f32> = data
+        .frequencies
+        .iter()
+        .zip(&data.frames[0])
+        .filter(|&(_, &magnitude)| magnitude > 0.0)
+        .map(|(&freq, _)| freq)
+        .collect();
+    assert!(!lit.is_empty());
+    let expected = lit.iter().sum::<f32>() / lit.len() as f32;
+
+    let samples = sonify(&img, 1.0, &config);
+    let pitch = dominant_pitch(&compute_spectrogram(&samples, &config).unwrap());
+    let bin_width = 8000.0 / 512.0;
+    for tracked in &pitch[2..pitch.len() - 2] {
+        let tracked = tracked.unwrap();
+        assert!((tracked - expected).abs() < 2.0 * bin_width, "{tracked} Hz");
+    }
+}
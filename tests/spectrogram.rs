@@ -0,0 +1,370 @@
+use std::f32::consts::PI;
+use std::fs::File;
+
+use fft_image_extractor::{
+    bark_to_hz, compute_spectrogram, compute_spectrogram_with, cqt_frequencies, dominant_pitch,
+    hz_to_bark, render, write_wav, DecodeOptions, FftImageError, FrequencyScale, Normalize,
+    SampleStream, SpectralFrame, SpectralFrames, SpectrogramConfig, SpectrogramStream, Transform,
+    WindowFn,
+};
+use spectrum_analyzer::scaling::{scale_to_zero_to_one, SpectrumScalingFunction};
+use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
+
+mod common;
+
+#[test]
+fn dc_signal_maps_to_finite_rows() {
+    let config = SpectrogramConfig::default().build().unwrap();
+    let samples = vec![0.5; config.window * 64];
+
+    let data = compute_spectrogram(&samples, &config).unwrap();
+    assert_eq!(data.frequencies[0], 0.0);
+
+    for scale in [
+        FrequencyScale::Log,
+        FrequencyScale::Mel,
+        FrequencyScale::Bark,
+        FrequencyScale::Linear,
+    ] {
+        assert_eq!(
+            scale.row_for(0.0, config.min_freq, config.max_freq, config.row_height),
+            None
+        );
+        for &freq in &data.frequencies[1..] {
+            if let Some(row) =
+                scale.row_for(freq, config.min_freq, config.max_freq, config.row_height)
+            {
+                assert!(row <= config.row_height);
+            }
+        }
+    }
+
+    for frame in &data.frames {
+        assert!(frame.iter().all(|magnitude| magnitude.is_finite()));
+    }
+
+    let img = render(&data, &config);
+    assert!(img.height() > 0);
+}
+
+#[test]
+fn bark_scale_round_trips() {
+    // 1 kHz lies eight and a half critical bands up
+    assert!((hz_to_bark(1000.0) - 8.53).abs() < 0.01);
+    for freq in [20.0, 440.0, 3000.0, 15_000.0] {
+        assert!((bark_to_hz(hz_to_bark(freq)) - freq).abs() < 1e-3 * freq);
+    }
+    let rows: Vec<u32> = [100.0, 1000.0, 10_000.0]
+        .iter()
+        .filter_map(|&freq| FrequencyScale::Bark.row_for(freq, 20.0, 20_000.0, 100))
+        .collect();
+    assert!(rows.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn partial_final_window_is_zero_padded() {
+    let config = SpectrogramConfig::default().build().unwrap();
+    let samples: Vec<f32> = (0..config.window * 3 + 100)
+        .map(|n| (n as f32 * 0.1).sin())
+        .collect();
+
+    let data = compute_spectrogram(&samples, &config).unwrap();
+
+    assert_eq!(data.frame_count(), 4);
+    for frame in &data.frames {
+        assert_eq!(frame.len(), data.frequencies.len());
+    }
+}
+
+#[test]
+fn streaming_matches_buffered() {
+    let samples: Vec<f32> = (0..20_000).map(|n| (n as f32 * 0.05).sin()).collect();
+
+    for (hop, normalize) in [
+        (2048, Normalize::Column),
+        (500, Normalize::Global),
+        (3000, Normalize::None),
+    ] {
+        let config = SpectrogramConfig::default()
+            .hop(hop)
+            .normalize(normalize)
+            .build()
+            .unwrap();
+        let buffered = compute_spectrogram(&samples, &config).unwrap();
+
+        let mut stream = SpectrogramStream::new(&config);
+        for chunk in samples.chunks(1152) {
+            stream.push(chunk).unwrap();
+        }
+        let streamed = stream.finish().unwrap();
+
+        assert_eq!(streamed.frequencies, buffered.frequencies);
+        assert_eq!(streamed.frames, buffered.frames);
+    }
+}
+
+#[test]
+fn spectral_frames_match_buffered() {
+    let samples: Vec<f32> = (0..20_000).map(|n| (n as f32 * 0.05).sin()).collect();
+    let path = common::temp_path("spectral-frames.wav");
+    write_wav(&path, &samples, 44_100).unwrap();
+
+    for hop in [2048, 500, 3000] {
+        let config = SpectrogramConfig::default().hop(hop).build().unwrap();
+        let buffered = compute_spectrogram(&samples, &config).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let mut stream = SampleStream::open(Box::new(file), &DecodeOptions::default()).unwrap();
+        let frames: Vec<SpectralFrame> = SpectralFrames::new(&mut stream, &config)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(frames.len(), buffered.frame_count());
+        for (frame, magnitudes) in frames.iter().zip(&buffered.frames) {
+            assert_eq!(frame.start_sample, frame.index * hop);
+            assert_eq!(&frame.magnitudes, magnitudes);
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn matches_spectrum_analyzer() {
+    let samples: Vec<f32> = (0..16_384)
+        .map(|n| (n as f32 * 0.07).sin() + 0.3 * (n as f32 * 0.9).cos())
+        .collect();
+
+    for normalize in [Normalize::Column, Normalize::None] {
+        let config = SpectrogramConfig::default()
+            .window_fn(WindowFn::Hann)
+            .normalize(normalize)
+            .build()
+            .unwrap();
+        let data = compute_spectrogram(&samples, &config).unwrap();
+
+        let coefficients = config.window_fn.coefficients(config.window);
+        let scaling = match normalize {
+            Normalize::Column => Some(&scale_to_zero_to_one as &SpectrumScalingFunction),
+            _ => None,
+        };
+
+        for (block, frame) in samples.chunks(config.window).zip(&data.frames) {
+            let windowed: Vec<f32> = block
+                .iter()
+                .zip(&coefficients)
+                .map(|(s, c)| s * c)
+                .collect();
+            let spectrum = samples_fft_to_spectrum(
+                &windowed,
+                config.sample_rate,
+                FrequencyLimit::Range(0.0, config.max_freq),
+                scaling,
+            )
+            .unwrap();
+
+            let expected = spectrum.data();
+            assert_eq!(expected.len(), frame.len());
+
+            let tolerance = 1e-4 * spectrum.max().1.val();
+            for (&(freq, value), (&actual_freq, &actual)) in
+                expected.iter().zip(data.frequencies.iter().zip(frame))
+            {
+                assert_eq!(freq.val(), actual_freq);
+                assert!(
+                    (value.val() - actual).abs() <= tolerance,
+                    "{} Hz: expected {}, got {}",
+                    actual_freq,
+                    value.val(),
+                    actual
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn input_shorter_than_one_window_renders_a_single_frame() {
+    let config = SpectrogramConfig::default().build().unwrap();
+    let samples: Vec<f32> = (0..100).map(|n| (n as f32 * 0.3).sin()).collect();
+
+    let data = compute_spectrogram(&samples, &config).unwrap();
+    assert_eq!(data.frame_count(), 1);
+
+    let img = render(&data, &config);
+    assert!(img.width() > 0 && img.height() > 0);
+}
+
+#[test]
+fn empty_input_is_rejected() {
+    let config = SpectrogramConfig::default().build().unwrap();
+
+    assert!(matches!(
+        compute_spectrogram(&[], &config),
+        Err(FftImageError::EmptyInput)
+    ));
+}
+
+#[test]
+fn closure_scaler_matches_builtin() {
+    let config = SpectrogramConfig::default().build().unwrap();
+    let samples: Vec<f32> = (0..10_000).map(|n| (n as f32 * 0.05).sin()).collect();
+
+    let divide_by_length = |magnitudes: &mut [f32], fft_len: usize| {
+        for magnitude in magnitudes {
+            *magnitude /= fft_len as f32;
+        }
+    };
+    let custom = compute_spectrogram_with(&samples, &config, &divide_by_length).unwrap();
+    let builtin =
+        compute_spectrogram(&samples, &config.clone().normalize(Normalize::Length)).unwrap();
+
+    assert_eq!(custom.frames, builtin.frames);
+}
+
+#[test]
+fn smoothing_is_centered_and_keeps_steady_edges() {
+    let config = SpectrogramConfig::default()
+        .normalize(Normalize::None)
+        .build()
+        .unwrap();
+    // A tone repeating every window, with a burst of noise in the middle window
+    let period = config.window as f32 / 64.0;
+    let mut samples: Vec<f32> = (0..config.window * 9)
+        .map(|n| (2.0 * PI * (n % config.window) as f32 / period).sin())
+        .collect();
+    for (n, sample) in samples[config.window * 4..config.window * 5]
+        .iter_mut()
+        .enumerate()
+    {
+        *sample += if n % 3 == 0 { 1.0 } else { -0.5 };
+    }
+    let raw = compute_spectrogram(&samples, &config).unwrap();
+
+    for width in [3, 4] {
+        let smoothed = compute_spectrogram(&samples, &config.clone().smooth(width)).unwrap();
+        assert_eq!(smoothed.frame_count(), raw.frame_count());
+        // The burst spreads evenly to both sides
+        for bin in 0..raw.frequencies.len() {
+            let (before, after) = (smoothed.frames[3][bin], smoothed.frames[5][bin]);
+            assert!((before - after).abs() <= 1e-3 * before.abs().max(1.0));
+        }
+        // Frames at the edges only average the steady tone, which stays as loud
+        for bin in 0..raw.frequencies.len() {
+            let (edge, steady) = (smoothed.frames[0][bin], raw.frames[0][bin]);
+            assert!((edge - steady).abs() <= 1e-2 * steady.abs().max(1.0));
+        }
+    }
+}
+
+#[test]
+fn contrast_stretches_the_percentile_range() {
+    let samples: Vec<f32> = (0..20_000)
+        .map(|n| (n as f32 * 0.05).sin() + 0.1 * (n as f32 * 1.3).sin())
+        .collect();
+    let config = SpectrogramConfig::default()
+        .normalize(Normalize::Global)
+        .contrast(Some([10.0, 90.0]))
+        .build()
+        .unwrap();
+    let data = compute_spectrogram(&samples, &config).unwrap();
+
+    let magnitudes: Vec<f32> = data.frames.iter().flatten().copied().collect();
+    assert!(magnitudes.iter().all(|m| (0.0..=1.0).contains(m)));
+    let clipped = |level: f32| magnitudes.iter().filter(|&&m| m == level).count();
+    // A tenth of the magnitudes lie at or beyond each percentile
+    let tenth = magnitudes.len() / 10;
+    assert!(clipped(0.0).abs_diff(tenth) <= 2);
+    assert!(clipped(1.0).abs_diff(tenth) <= 2);
+}
+
+#[test]
+fn cqt_finds_tones_in_geometric_bins() {
+    let config = SpectrogramConfig::default()
+        .sample_rate(16_000)
+        .window(4096)
+        .window_fn(WindowFn::Hann)
+        .transform(Transform::Cqt)
+        .bins_per_octave(12)
+        .min_freq(110.0)
+        .max_freq(4000.0)
+        .build()
+        .unwrap();
+    let frequencies = cqt_frequencies(&config);
+    // Five octaves of semitones from 110 Hz
+    assert_eq!(frequencies.len(), 63);
+    for pair in frequencies.windows(2) {
+        assert!((pair[1] / pair[0] - 2f32.powf(1.0 / 12.0)).abs() < 1e-4);
+    }
+
+    for bin in [0, 24, 50] {
+        let freq = frequencies[bin];
+        let samples = common::tone(freq, 16_000, config.window * 4);
+        let data = compute_spectrogram(&samples, &config).unwrap();
+        assert_eq!(data.frequencies, frequencies);
+        for frame in &data.frames {
+            let peak = (0..frame.len())
+                .max_by(|&a, &b| frame[a].total_cmp(&frame[b]))
+                .unwrap();
+            assert_eq!(peak, bin);
+            // A whole tone away lies in the first null of the kernel, down to the lowest bin
+            assert!(frame[bin + 2] < 0.1 * frame[bin]);
+        }
+    }
+}
+
+#[test]
+fn dominant_pitch_interpolates_between_bins() {
+    let config = SpectrogramConfig::default()
+        .window(2048)
+        .window_fn(WindowFn::Hann)
+        .build()
+        .unwrap();
+    let bin_width = config.sample_rate as f32 / config.window as f32;
+    // A third of the way between two bins
+    let freq = 20.333 * bin_width;
+    let mut samples = common::tone(freq, config.sample_rate, config.window * 4);
+    samples.extend(vec![0.0; config.window]);
+
+    let data = compute_spectrogram(&samples, &config).unwrap();
+    let pitch = dominant_pitch(&data);
+    for tracked in &pitch[..4] {
+        let tracked = tracked.unwrap();
+        assert!((tracked - freq).abs() < 0.1 * bin_width, "{tracked} Hz");
+    }
+    assert_eq!(pitch[4], None);
+}
+
+#[test]
+fn time_bins_resample_to_a_fixed_width() {
+    let config = SpectrogramConfig::default()
+        .window(256)
+        .hop(256)
+        .build()
+        .unwrap();
+    // A quiet first half and a loud second half
+    let samples: Vec<f32> = (0..config.window * 12)
+        .map(|n| {
+            let amplitude = if n < config.window * 6 { 0.2 } else { 1.0 };
+            amplitude * (2.0 * PI * 8.0 * n as f32 / config.window as f32).sin()
+        })
+        .collect();
+    let frames = compute_spectrogram(&samples, &config).unwrap();
+    let peak = |frame: &[f32]| frame.iter().copied().fold(0.0, f32::max);
+
+    for columns in [1, 4, 5, 30] {
+        let binned =
+            compute_spectrogram(&samples, &config.clone().time_bins(Some(columns))).unwrap();
+        assert_eq!(binned.frame_count(), columns);
+        assert_eq!(binned.frequencies, frames.frequencies);
+        let first = peak(&binned.frames[0]);
+        let last = peak(&binned.frames[columns - 1]);
+        if columns == 1 {
+            // A single column averages the whole input
+            let mean = (peak(&frames.frames[0]) + peak(&frames.frames[11])) / 2.0;
+            assert!((first - mean).abs() < 1e-3 * mean);
+        } else {
+            assert!((first - peak(&frames.frames[0])).abs() < 1e-3 * first);
+            assert!((last - peak(&frames.frames[11])).abs() < 1e-3 * last);
+        }
+    }
+}
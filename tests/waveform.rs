@@ -0,0 +1,67 @@
+use fft_image_extractor::{
+    compute_spectrogram, render_combined, render_image, render_waveform, waveform_envelope, Layout,
+    Mode, PixelFormat, SpectrogramConfig,
+};
+
+#[test]
+fn waveform_spans_the_envelope_of_each_column() {
+    let config = SpectrogramConfig::default()
+        .window(64)
+        .hop(64)
+        .row_height(9)
+        .layout(Layout::Strip)
+        .format(PixelFormat::Gray)
+        .build()
+        .unwrap();
+    // Full scale, then silence, then a positive half swing
+    let mut samples = vec![1.0, -1.0];
+    samples.resize(64, 0.0);
+    samples.extend(vec![0.0; 64]);
+    samples.extend(vec![0.5; 64]);
+
+    assert_eq!(
+        waveform_envelope(&samples, &config),
+        [(-1.0, 1.0), (0.0, 0.0), (0.5, 0.5)]
+    );
+    let img = render_waveform(&samples, &config).into_luma8();
+    assert_eq!(img.dimensions(), (3, 9));
+    let drawn =
+        |x: u32| -> Vec<u32> { (0..9).filter(|&y| img.get_pixel(x, y).0 == [255]).collect() };
+    assert_eq!(drawn(0), (0..9).collect::<Vec<_>>());
+    assert_eq!(drawn(1), [4]);
+    assert_eq!(drawn(2), [2]);
+}
+
+#[test]
+fn combined_mode_stacks_the_waveform_over_the_spectrogram() {
+    let config = SpectrogramConfig::default()
+        .window(256)
+        .hop(256)
+        .row_height(32)
+        .waveform_height(12)
+        .format(PixelFormat::Gray);
+    let samples: Vec<f32> = (0..256 * 20).map(|n| (n as f32 * 0.3).sin()).collect();
+    let data = compute_spectrogram(&samples, &config.clone().build().unwrap()).unwrap();
+
+    let combined = render_combined(
+        &samples,
+        &data,
+        &config.clone().mode(Mode::Combined).build().unwrap(),
+    )
+    .into_luma8();
+    let waveform =
+        render_waveform(&samples, &config.clone().row_height(12).build().unwrap()).into_luma8();
+    let spectrogram = render_image(&data, &config.build().unwrap()).into_luma8();
+
+    // 20 frames wrap into rows of 4, each band a waveform panel over a spectrogram row
+    assert_eq!(combined.dimensions(), (4, 5 * (12 + 32)));
+    for (x, y, pixel) in combined.enumerate_pixels() {
+        let (band, row) = (y / 44, y % 44);
+        let expected = if row < 12 {
+            waveform.get_pixel(x, band * 12 + row)
+        } else {
+            spectrogram.get_pixel(x, band * 32 + row - 12)
+        };
+        assert_eq!(pixel, expected, "({x}, {y})");
+    }
+}
@@ -0,0 +1,27 @@
+use fft_image_extractor::Weighting;
+
+fn gain_db(weighting: Weighting, freq: f32) -> f32 {
+    20.0 * weighting.gain(freq).log10()
+}
+
+#[test]
+fn weighting_curves_match_the_standard_tables() {
+    // Values at the nominal frequencies of IEC 61672-1, which round the exact ones, hence the slack
+    for (freq, a, c) in [
+        (31.5, -39.4, -3.0),
+        (100.0, -19.1, -0.3),
+        (1000.0, 0.0, 0.0),
+        (4000.0, 1.0, -0.8),
+        (10000.0, -2.5, -4.4),
+    ] {
+        assert!(
+            (gain_db(Weighting::A, freq) - a).abs() < 0.2,
+            "A at {freq} Hz"
+        );
+        assert!(
+            (gain_db(Weighting::C, freq) - c).abs() < 0.2,
+            "C at {freq} Hz"
+        );
+        assert_eq!(Weighting::Z.gain(freq), 1.0);
+    }
+}